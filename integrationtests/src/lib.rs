@@ -1,4 +1,5 @@
 pub mod bitcoin_client;
 pub mod lnbitsmock;
 pub mod lnd_client;
+pub mod lnurlmock;
 pub mod setup;
@@ -0,0 +1,95 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use axum::{routing::get, Router};
+use bitcoin::hashes::{sha256, Hash};
+use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+use secp256k1::Secp256k1;
+use secp256k1::SecretKey;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// mallory returns invoices for a different amount than requested
+const USERS: [&str; 2] = ["alice", "mallory"];
+
+#[derive(Clone)]
+struct LnurlState {
+    private_key: SecretKey,
+    port: u16,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PayRequestResponse {
+    callback: String,
+    min_sendable: u64,
+    max_sendable: u64,
+    metadata: String,
+    tag: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackParams {
+    amount: u64,
+}
+
+fn metadata(user: &str) -> String {
+    format!(r#"[["text/plain","Pay to {user}"]]"#)
+}
+
+async fn get_pay_request(
+    State(state): State<LnurlState>,
+    Path(user): Path<String>,
+) -> Json<serde_json::Value> {
+    if !USERS.contains(&user.as_str()) {
+        return Json(serde_json::json!({"status": "ERROR", "reason": "unknown user"}));
+    }
+
+    Json(serde_json::json!(PayRequestResponse {
+        callback: format!("http://127.0.0.1:{}/lnurlp/{user}/callback", state.port),
+        min_sendable: 1_000,
+        max_sendable: 1_000_000_000,
+        metadata: metadata(&user),
+        tag: "payRequest".to_owned(),
+    }))
+}
+
+async fn get_invoice(
+    State(state): State<LnurlState>,
+    Path(user): Path<String>,
+    Query(params): Query<CallbackParams>,
+) -> Json<serde_json::Value> {
+    let amount = if user == "mallory" {
+        params.amount + 1_000
+    } else {
+        params.amount
+    };
+
+    let invoice = InvoiceBuilder::new(Currency::Regtest)
+        .description_hash(sha256::Hash::hash(metadata(&user).as_bytes()))
+        .amount_milli_satoshis(amount)
+        .payment_hash(sha256::Hash::hash(&rand::random::<[u8; 32]>()))
+        .payment_secret(PaymentSecret([42u8; 32]))
+        .current_timestamp()
+        .min_final_cltv_expiry_delta(144)
+        .build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &state.private_key))
+        .expect("Can't create invoice");
+
+    Json(serde_json::json!({"pr": invoice.to_string(), "routes": []}))
+}
+
+/// Serves LNURL-pay requests at `/.well-known/lnurlp/:user` like a Lightning address provider
+pub async fn run_server(port: u16) -> anyhow::Result<()> {
+    let state = LnurlState {
+        private_key: SecretKey::new(&mut rand::thread_rng()),
+        port,
+    };
+    let app = Router::new()
+        .route("/.well-known/lnurlp/:user", get(get_pay_request))
+        .route("/lnurlp/:user/callback", get(get_invoice))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&SocketAddr::from(([127, 0, 0, 1], port))).await?;
+    axum::serve(listener, app.into_make_service()).await?;
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use itests::setup::{read_fixture, start_mint};
-use moksha_core::primitives::{CurrencyUnit, PaymentMethod};
+use moksha_core::primitives::{CurrencyUnit, PaymentMethod, SpentState};
 
 use moksha_wallet::client::CashuClient;
 use moksha_wallet::http::CrossPlatformHttpClient;
@@ -51,7 +51,7 @@ pub async fn test_bolt11_lnbitsmock() -> anyhow::Result<()> {
     // create wallet
     let localstore = SqliteLocalStore::with_in_memory().await?;
     let wallet = WalletBuilder::default()
-        .with_client(client)
+        .with_client(client.clone())
         .with_localstore(localstore)
         .build()
         .await?;
@@ -103,15 +103,58 @@ pub async fn test_bolt11_lnbitsmock() -> anyhow::Result<()> {
     let balance = wallet.get_balance().await?;
     assert_eq!(5_010, balance);
 
+    // the received proofs are spent at the mint
+    let ys = token_10
+        .proofs()
+        .proofs()
+        .iter()
+        .map(|proof| proof.y())
+        .collect::<Result<Vec<_>, _>>()?;
+    let states = client.post_check_state(&mint_url, ys).await?;
+    assert!(states
+        .states
+        .iter()
+        .all(|state| state.state == SpentState::Spent));
+
     // send 10 tokens
     let result_send = wallet.send_tokens(wallet_keyset, 10).await;
     assert!(result_send.is_ok());
-    assert_eq!(10, result_send.unwrap().total_amount());
+    let token_send = result_send.unwrap();
+    assert_eq!(10, token_send.total_amount());
     let balance = wallet.get_balance().await?;
     assert_eq!(5_000, balance);
 
+    // the sent proofs are unspent until they are received
+    let ys = token_send
+        .proofs()
+        .proofs()
+        .iter()
+        .map(|proof| proof.y())
+        .collect::<Result<Vec<_>, _>>()?;
+    let states = client.post_check_state(&mint_url, ys).await?;
+    assert!(states
+        .states
+        .iter()
+        .all(|state| state.state == SpentState::Unspent));
+    wallet.receive_tokens(wallet_keyset, &token_send).await?;
+    let balance = wallet.get_balance().await?;
+    assert_eq!(5_010, balance);
+
+    // restore the proofs of the wallet from its seed
+    let restored_wallet = WalletBuilder::default()
+        .with_client(CrossPlatformHttpClient::new())
+        .with_localstore(SqliteLocalStore::with_in_memory().await?)
+        .with_seed_words(wallet.seed_words())
+        .build()
+        .await?;
+    let restored = restored_wallet.restore(&mint_url).await?;
+    assert_eq!(balance, restored);
+    assert_eq!(balance, restored_wallet.get_balance().await?);
+
     // get info
     let info = wallet.get_mint_info(&mint_url).await?;
     assert!(!info.nuts.nut4.disabled);
+    assert!(info.nuts.nut7.is_some_and(|nut| nut.supported));
+    assert!(info.nuts.nut9.is_some_and(|nut| nut.supported));
     Ok(())
 }
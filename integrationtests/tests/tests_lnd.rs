@@ -110,7 +110,9 @@ async fn test_btc_onchain_mint_melt() -> anyhow::Result<()> {
         .await?;
 
     let first_quote = melt_quotes.first().expect("No quote returned from mint");
-    let result = wallet.pay_onchain(wallet_keyset, first_quote).await?;
+    let result = wallet
+        .pay_onchain(wallet_keyset, first_quote, btc_address.clone())
+        .await?;
     assert!(!result.paid);
     btc_client.mine_blocks(1).await?;
 
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use bech32::ToBase32;
+use moksha_wallet::error::MokshaWalletError;
+use moksha_wallet::http::CrossPlatformHttpClient;
+use moksha_wallet::lnurl;
+
+fn encode_lnurl(url: &str) -> anyhow::Result<String> {
+    Ok(
+        bech32::encode("lnurl", url.as_bytes().to_base32(), bech32::Variant::Bech32)?
+            .to_uppercase(),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+pub async fn test_lnurl_pay() -> anyhow::Result<()> {
+    let _lnurl_thread = tokio::spawn(async {
+        let _ = itests::lnurlmock::run_server(6110).await;
+    });
+
+    // Wait for the server to start
+    tokio::time::sleep(Duration::from_millis(800)).await;
+
+    let client = CrossPlatformHttpClient::new();
+
+    let alice = encode_lnurl("http://127.0.0.1:6110/.well-known/lnurlp/alice")?;
+    assert!(lnurl::is_lnurl(&alice));
+    let pay_request = client.get_lnurl_pay_request(&alice).await?;
+    assert_eq!(Some("Pay to alice".to_owned()), pay_request.description());
+    assert_eq!(1, pay_request.min_amount());
+
+    let invoice = client.get_lnurl_invoice(&pay_request, 2_000).await?;
+    assert!(invoice.starts_with("lnbcrt20u"));
+
+    let result = client.get_lnurl_invoice(&pay_request, 2_000_000).await;
+    assert!(matches!(result, Err(MokshaWalletError::Lnurl(_))));
+
+    // the invoice of mallory is for a different amount
+    let mallory = encode_lnurl("http://127.0.0.1:6110/.well-known/lnurlp/mallory")?;
+    let pay_request = client.get_lnurl_pay_request(&mallory).await?;
+    let result = client.get_lnurl_invoice(&pay_request, 2_000).await;
+    assert!(matches!(
+        result,
+        Err(MokshaWalletError::InvalidLnurlInvoice(_))
+    ));
+
+    let unknown = encode_lnurl("http://127.0.0.1:6110/.well-known/lnurlp/bob")?;
+    let result = client.get_lnurl_pay_request(&unknown).await;
+    assert!(matches!(result, Err(MokshaWalletError::Lnurl(reason)) if reason == "unknown user"));
+    Ok(())
+}
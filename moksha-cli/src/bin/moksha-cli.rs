@@ -1,20 +1,22 @@
 use clap::{Parser, Subcommand};
 use console::{style, Term};
-use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
+use moksha_core::amount::SplitStrategy;
+use moksha_core::payment_request::{PaymentRequest, Transport, TransportKind};
 use moksha_core::primitives::{
     CurrencyUnit, PaymentMethod, PostMeltBtcOnchainResponse, PostMintQuoteBolt11Response,
     PostMintQuoteBtcOnchainResponse,
 };
-use moksha_core::token::TokenV3;
+use moksha_core::token::{TokenV3, TokenV4};
 use moksha_wallet::client::CashuClient;
+use moksha_wallet::error::MokshaWalletError;
 
 use moksha_wallet::http::CrossPlatformHttpClient;
 
-use moksha_wallet::localstore::WalletKeysetFilter;
-use mokshacli::cli::{self, choose_mint, get_mints_with_balance};
+use moksha_wallet::localstore::{TransactionFilter, TransactionKind, WalletKeysetFilter};
+use moksha_wallet::wallet::{MintQuoteResult, RecoveryResult};
+use mokshacli::cli::{self, choose_mint};
 use num_format::{Locale, ToFormattedString};
-use qrcode::render::unicode;
-use qrcode::QrCode;
 
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -27,6 +29,10 @@ struct Opts {
     #[clap(short, long)]
     db_dir: Option<PathBuf>,
 
+    /// Denominations of the tokens kept in the wallet: minimal, target:<count per denomination> or custom:<amount>,<amount>,...
+    #[clap(long, default_value_t = SplitStrategy::Minimal)]
+    split_strategy: SplitStrategy,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -34,28 +40,176 @@ struct Opts {
 #[derive(Subcommand, Clone)]
 enum Command {
     /// Mint tokens
-    Mint { amount: u64 },
+    Mint {
+        #[clap(required_unless_present = "resume")]
+        amount: Option<u64>,
+
+        /// Mint the tokens of paid quotes that were created earlier
+        #[clap(long, conflicts_with = "amount")]
+        resume: bool,
+    },
 
-    /// Pay Lightning invoice
-    Pay { invoice: String },
+    /// Pay Lightning invoice, Lightning address or LNURL-pay link
+    Pay {
+        invoice: String,
+
+        /// Split the payment across all mints with a balance (NUT-15)
+        #[clap(long)]
+        mpp: bool,
+    },
 
     /// Pay Bitcoin on chain
     PayOnchain { address: String, amount: u64 },
 
+    /// Move tokens from one mint to another via Lightning
+    Transfer {
+        amount: u64,
+
+        /// Destination mint. If not set, one of the known mints can be selected
+        #[clap(long)]
+        to: Option<Url>,
+    },
+
     /// Send tokens
-    Send { amount: u64 },
+    Send {
+        amount: u64,
+
+        /// Create a token in the legacy V3 format (cashuA)
+        #[clap(long)]
+        v3: bool,
+    },
+
+    /// Create a payment request (creqA) that is paid with tokens sent as HTTP POST request to the given url
+    Request {
+        /// Url that receives the tokens
+        #[clap(long)]
+        post_url: Url,
+
+        /// Requested amount in sat. If not set the sender chooses the amount
+        #[clap(long)]
+        amount: Option<u64>,
+
+        #[clap(long)]
+        description: Option<String>,
+
+        /// Id to match the received payment with the request
+        #[clap(long)]
+        id: Option<String>,
+
+        /// Accepted mints. Defaults to the mints of the wallet
+        #[clap(long = "mint")]
+        mints: Vec<Url>,
+    },
+
+    /// Pay a payment request (creqA) with tokens of an accepted mint
+    PayRequest { request: String },
+
+    /// Receive tokens. Accepts the first part of an animated QR code and asks for the remaining parts
+    Receive {
+        token: String,
+
+        /// Verify the tokens without connecting to the mint. The tokens must be locked to the public key
+        /// shown by info. Run sync once online to redeem them.
+        #[clap(long)]
+        offline: bool,
+    },
+
+    /// Redeem the tokens that were received offline
+    Sync,
 
-    /// Receive tokens
-    Receive { token: String },
+    /// Reclaim sent tokens that were not claimed by the receiver
+    Reclaim,
 
     /// Show local balance
     Balance,
 
+    /// Show transaction history
+    History {
+        /// Only show transactions of this kind (mint, melt, send, receive, swap)
+        #[clap(long)]
+        kind: Option<TransactionKind>,
+
+        /// Only show transactions of this mint
+        #[clap(long)]
+        mint_url: Option<Url>,
+
+        #[clap(long, default_value_t = 20)]
+        limit: u64,
+
+        #[clap(long, default_value_t = 0)]
+        offset: u64,
+    },
+
     /// Show version and configuration
     Info,
 
     /// Add a new mint to the wallet
     AddMint { mint_url: Url },
+
+    /// Trust a mint. Once a mint is trusted, received tokens of untrusted mints are moved to the preferred mint
+    TrustMint {
+        mint_url: Url,
+
+        /// Move tokens of untrusted mints to this mint
+        #[clap(long)]
+        preferred: bool,
+    },
+
+    /// Remove a mint from the trusted mints
+    UntrustMint { mint_url: Url },
+
+    /// Swap the tokens of a mint into fewer proofs
+    Optimize {
+        /// Number of proofs to keep per denomination
+        #[clap(long, default_value_t = 3)]
+        target_count: usize,
+    },
+
+    /// Update the keysets of all mints and move tokens of inactive keysets to the active keysets
+    Refresh,
+
+    /// Set, change or remove the password that encrypts the seed
+    Password,
+
+    /// Initialize the wallet. Without --from-mnemonic a new seed is generated
+    Init {
+        /// Import an existing BIP39 mnemonic
+        #[clap(long)]
+        from_mnemonic: bool,
+    },
+
+    /// Manage the wallet seed
+    Seed {
+        #[clap(subcommand)]
+        command: SeedCommand,
+    },
+
+    /// Restore the tokens of a mint from the wallet seed
+    Restore {
+        #[clap(long)]
+        mint: Url,
+    },
+
+    /// Export or import an encrypted backup of the wallet
+    Backup {
+        #[clap(subcommand)]
+        command: BackupCommand,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum SeedCommand {
+    /// Show the seed words of the wallet
+    Show,
+}
+
+#[derive(Subcommand, Clone)]
+enum BackupCommand {
+    /// Write proofs, keysets, transactions and trusted mints to an encrypted file
+    Export { file: PathBuf },
+
+    /// Merge an encrypted backup file into the wallet
+    Import { file: PathBuf },
 }
 
 #[tokio::main]
@@ -75,29 +229,209 @@ async fn main() -> anyhow::Result<()> {
 
     let term = Term::stdout();
     let localstore = SqliteLocalStore::with_path(db_path.clone()).await?;
-    let client = CrossPlatformHttpClient::new();
-    let wallet = moksha_wallet::wallet::WalletBuilder::default()
-        .with_client(client)
-        .with_localstore(localstore)
-        .build()
-        .await
-        .map_err(|e| {
-            if matches!(
-                e,
-                moksha_wallet::error::MokshaWalletError::UnsupportedApiVersion
-            ) {
-                term.write_line("Error: Mint does not support /v1 api")
-                    .expect("write_line failed");
+    let seed_words = match cli.command {
+        Command::Init {
+            from_mnemonic: true,
+        } => Some(
+            Input::<String>::new()
+                .with_prompt("Seed words")
+                .interact_text()?,
+        ),
+        _ => None,
+    };
+
+    let mut password: Option<String> = None;
+    let wallet = loop {
+        let mut builder = moksha_wallet::wallet::WalletBuilder::default()
+            .with_client(CrossPlatformHttpClient::new())
+            .with_localstore(localstore.clone())
+            .with_split_strategy(cli.split_strategy.clone());
+        if let Some(password) = &password {
+            builder = builder.with_password(password);
+        }
+        if let Some(seed_words) = &seed_words {
+            builder = builder.with_seed_words(seed_words.trim());
+        }
+
+        match builder.build().await {
+            Err(MokshaWalletError::WalletLocked) | Err(MokshaWalletError::InvalidPassword) => {
+                if password.is_some() {
+                    term.write_line("Error: Invalid password")?;
+                }
+                password = Some(Password::new().with_prompt("Wallet password").interact()?);
+            }
+            Err(MokshaWalletError::SeedAlreadyExists) => {
+                term.write_line(&format!(
+                    "Error: {db_path} already contains a different seed. Use --db-dir to create a new wallet"
+                ))?;
                 std::process::exit(1);
             }
-            e
-        })?;
+            Err(MokshaWalletError::UnsupportedApiVersion) => {
+                term.write_line("Error: Mint does not support /v1 api")?;
+                std::process::exit(1);
+            }
+            result => break result?,
+        }
+    };
+
+    for (operation, result) in wallet.recover_pending_operations().await? {
+        let message = match result {
+            RecoveryResult::Replayed(amount) => format!("recovered {amount} (sat)"),
+            RecoveryResult::Restored(amount) => format!("restored {amount} (sat)"),
+            RecoveryResult::RolledBack(amount) => format!("rolled back {amount} (sat)"),
+            RecoveryResult::Spent(amount) => format!("{amount} (sat) already spent"),
+            RecoveryResult::Pending => "outcome unknown, retrying next time".to_owned(),
+        };
+        term.write_line(&format!(
+            "Interrupted {} at {}: {}",
+            operation.kind,
+            operation.mint_url,
+            style(message).cyan()
+        ))?;
+    }
 
     match cli.command {
         Command::AddMint { mint_url } => {
             wallet.add_mint_keysets(&mint_url).await?;
             term.write_line("Mint added successfully ")?;
         }
+        Command::TrustMint {
+            mint_url,
+            preferred,
+        } => {
+            wallet.trust_mint(&mint_url, preferred).await?;
+            term.write_line(&format!("Mint {mint_url} is trusted"))?;
+        }
+        Command::UntrustMint { mint_url } => {
+            wallet.untrust_mint(&mint_url).await?;
+            term.write_line(&format!("Mint {mint_url} is no longer trusted"))?;
+        }
+        Command::Init { .. } => {
+            term.write_line(&format!("Wallet initialized: {}", style(&db_path).cyan()))?;
+            term.write_line("Show the seed words with: moksha-cli seed show")?;
+        }
+        Command::Seed {
+            command: SeedCommand::Show,
+        } => {
+            let show_seed = Confirm::new()
+                .with_prompt("The seed words give full access to your tokens. Show them?")
+                .interact()?;
+            if show_seed {
+                term.write_line(&style(wallet.seed_words()).cyan().to_string())?;
+            }
+        }
+        Command::Optimize { target_count } => {
+            let currency_unit = CurrencyUnit::Sat;
+            let mint_url = choose_mint(&wallet, &currency_unit).await?.0;
+            let result = wallet
+                .consolidate(&mint_url, &currency_unit, target_count)
+                .await?;
+            if result.proofs_after == result.proofs_before {
+                term.write_line(&format!(
+                    "Tokens of {mint_url} are already optimized ({} proofs)",
+                    result.proofs_before
+                ))?;
+            } else {
+                term.write_line(&format!(
+                    "Consolidated {} proofs into {} proofs (fee {} sat)",
+                    result.proofs_before, result.proofs_after, result.fee
+                ))?;
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Sync => {
+            for result in wallet.sync_offline_proofs().await? {
+                term.write_line(&format!(
+                    "Redeemed {} (sat) at {}",
+                    style(result.amount.to_formatted_string(&Locale::en)).cyan(),
+                    result.mint_url
+                ))?;
+                if !result.double_spent.is_empty() {
+                    term.write_line(&format!(
+                        "Error: {} (sat) in {} proofs were already spent by the sender",
+                        result.double_spent.total_amount(),
+                        result.double_spent.len()
+                    ))?;
+                }
+                if !result.unverified.is_empty() {
+                    term.write_line(&format!(
+                        "{} (sat) in {} proofs could not be redeemed yet, run 'moksha-cli sync' to retry",
+                        result.unverified.total_amount(),
+                        result.unverified.len()
+                    ))?;
+                }
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Refresh => {
+            for (mint_url, result) in wallet.refresh_mints().await? {
+                match result {
+                    Ok(result) => term.write_line(&format!(
+                        "{mint_url}: {} new keysets, {} deactivated keysets, moved {} (sat)",
+                        result.new_keysets,
+                        result.deactivated_keysets,
+                        style(result.swapped_amount.to_formatted_string(&Locale::en)).cyan()
+                    ))?,
+                    Err(err) => {
+                        term.write_line(&format!("Error: could not refresh {mint_url}: {err}"))?
+                    }
+                }
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Restore { mint } => {
+            let amount = wallet.restore(&mint).await?;
+            term.write_line(&format!(
+                "Restored {} (sat) from {mint}",
+                style(amount.to_formatted_string(&Locale::en)).cyan()
+            ))?;
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Backup {
+            command: BackupCommand::Export { file },
+        } => {
+            let backup_password = Password::new()
+                .with_prompt("Backup password")
+                .with_confirmation("Confirm password", "Error: the passwords don't match")
+                .interact()?;
+            let backup = wallet.export_backup(&backup_password).await?;
+            std::fs::write(&file, backup)?;
+            term.write_line(&format!("Backup written to {}", file.display()))?;
+        }
+        Command::Backup {
+            command: BackupCommand::Import { file },
+        } => {
+            let backup = std::fs::read_to_string(&file)?;
+            let backup_password = Password::new().with_prompt("Backup password").interact()?;
+            let result = wallet.import_backup(&backup, &backup_password).await?;
+            term.write_line(&format!(
+                "Imported {} proofs ({} sat), {} keysets, {} transactions, {} trusted mints and {} mint quotes",
+                result.proofs,
+                style(result.amount.to_formatted_string(&Locale::en)).cyan(),
+                result.keysets,
+                result.transactions,
+                result.trusted_mints,
+                result.mint_quotes
+            ))?;
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Password => {
+            let new_password = Password::new()
+                .with_prompt("New password (leave empty to remove the password)")
+                .with_confirmation("Confirm password", "Error: the passwords don't match")
+                .allow_empty_password(true)
+                .interact()?;
+            let new_password = Some(new_password).filter(|p| !p.is_empty());
+
+            wallet
+                .change_password(password.as_deref(), new_password.as_deref())
+                .await?;
+            if new_password.is_some() {
+                term.write_line("Seed is encrypted with the new password")?;
+            } else {
+                term.write_line("Password removed. The seed is stored unencrypted")?;
+            }
+        }
         Command::Info => {
             let wallet_version = style(env!("CARGO_PKG_VERSION")).cyan();
             let mint_urls = wallet.get_mint_urls().await?;
@@ -113,10 +447,24 @@ async fn main() -> anyhow::Result<()> {
                     term.write_line(&format!(" - {}", mint))?;
                 }
             }
+
+            term.write_line(&format!(
+                "Public key for offline payments: {}",
+                style(wallet.p2pk_pubkey()?).cyan()
+            ))?;
+
+            let trusted_mints = wallet.get_trusted_mints().await?;
+            if !trusted_mints.is_empty() {
+                term.write_line("Trusted mints:")?;
+                for mint in trusted_mints {
+                    let preferred = if mint.preferred { " (preferred)" } else { "" };
+                    term.write_line(&format!(" - {}{preferred}", mint.mint_url))?;
+                }
+            }
         }
         // checks if the mints keyset is already in the wallet, if not it adds it and then imports the tokens
-        Command::Receive { token } => {
-            let token: TokenV3 = TokenV3::from_str(&token)?;
+        Command::Receive { token, offline } => {
+            let token: TokenV3 = TokenV3::from_str(&cli::read_token(token)?)?;
             let mint_urls = wallet.get_mint_urls().await?;
             let currency = match &token.currency_unit {
                 Some(currency) => currency,
@@ -131,6 +479,47 @@ async fn main() -> anyhow::Result<()> {
                 }
             };
 
+            if offline {
+                let amount = wallet.receive_offline(&token).await?;
+                term.write_line(&format!(
+                    "Received {} (sat) offline from {token_mint_url}. Run sync when online to redeem the tokens",
+                    style(amount.to_formatted_string(&Locale::en)).cyan()
+                ))?;
+                return Ok(());
+            }
+
+            if !wallet.is_trusted_mint(&token_mint_url).await? {
+                let preferred_mint = match wallet.get_preferred_mint().await? {
+                    Some(mint_url) => mint_url,
+                    None => {
+                        term.write_line(&format!(
+                            "Error: {token_mint_url} is not trusted. Set a preferred mint with trust-mint --preferred"
+                        ))?;
+                        return Ok(());
+                    }
+                };
+                term.write_line(&format!(
+                    "Mint {token_mint_url} is not trusted, moving tokens to {preferred_mint}"
+                ))?;
+                let result = match wallet.receive_into_preferred_mint(&token).await {
+                    Err(MokshaWalletError::SweepFailed(mint_url, amount, err)) => {
+                        term.write_line(&format!(
+                            "Error: Received {} (sat) at {mint_url}, but could not move them ({err}). Run 'moksha-cli transfer {amount} --to {preferred_mint}' to retry",
+                            amount.to_formatted_string(&Locale::en)
+                        ))?;
+                        return Ok(());
+                    }
+                    result => result?,
+                };
+                term.write_line(&format!(
+                    "Received {} (sat) at {preferred_mint}, fees {} (sat)",
+                    style(result.amount.to_formatted_string(&Locale::en)).cyan(),
+                    result.fee.to_formatted_string(&Locale::en)
+                ))?;
+                cli::show_total_balance(&wallet).await?;
+                return Ok(());
+            }
+
             if !mint_urls.contains(&token_mint_url) {
                 let add_mint = Confirm::new()
                     .with_prompt(format!(
@@ -162,7 +551,7 @@ async fn main() -> anyhow::Result<()> {
             wallet.receive_tokens(wallet_keyset, &token).await?;
             cli::show_total_balance(&wallet).await?;
         }
-        Command::Send { amount } => {
+        Command::Send { amount, v3 } => {
             let currency_unit = CurrencyUnit::Sat;
             let mint_url = choose_mint(&wallet, &currency_unit).await?;
 
@@ -180,15 +569,175 @@ async fn main() -> anyhow::Result<()> {
 
             term.write_line(&format!("Using tokens from mint: {mint_url}"))?;
             let result = wallet.send_tokens(wallet_keyset, amount).await?;
-            let tokens: String = result.try_into()?;
+            // the proofs are reserved already, so a token that can't be encoded as v4 (e.g. legacy keyset ids)
+            // is sent as v3 instead of failing
+            let tokens: String = if v3 {
+                result.try_into()?
+            } else {
+                match TokenV4::try_from(result.clone()).and_then(String::try_from) {
+                    Ok(tokens) => tokens,
+                    Err(_) => {
+                        term.write_line("Tokens can't be encoded as v4, using v3 instead")?;
+                        result.try_into()?
+                    }
+                }
+            };
 
+            cli::show_token_qr_code(&term, &tokens).await?;
             term.write_line(&format!("Result {amount} (sat):\n{tokens}"))?;
             cli::show_total_balance(&wallet).await?;
         }
+        Command::Request {
+            post_url,
+            amount,
+            description,
+            id,
+            mints,
+        } => {
+            let mints = if mints.is_empty() {
+                wallet.get_mint_urls().await?
+            } else {
+                mints
+            };
+            let request = PaymentRequest {
+                payment_id: id,
+                amount,
+                currency_unit: Some(CurrencyUnit::Sat),
+                mints,
+                description,
+                transports: vec![Transport::http_post(&post_url)],
+                ..Default::default()
+            };
+            let request = request.serialize()?;
+            cli::show_qr_code(&term, &request)?;
+            term.write_line(&format!("Payment request:\n{request}"))?;
+        }
+        Command::PayRequest { request } => {
+            let request = PaymentRequest::from_str(&request)?;
+            let Some(transport) = request.transport(TransportKind::Post) else {
+                term.write_line("Error: Payment request has no supported transport (HTTP POST)")?;
+                return Ok(());
+            };
+            let post_url = Url::parse(&transport.target)?;
+
+            if let Some(description) = &request.description {
+                term.write_line(description)?;
+            }
+            let amount = match request.amount {
+                Some(amount) => amount,
+                None => Input::<u64>::new()
+                    .with_prompt("Amount in sats")
+                    .interact_text()?,
+            };
+
+            let currency_unit = CurrencyUnit::Sat;
+            let Some(mint_url) = cli::choose_accepted_mint(&wallet, &request, amount).await? else {
+                term.write_line("Error: No accepted mint with enough tokens")?;
+                return Ok(());
+            };
+
+            let pay_confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Send {} (sat) from {mint_url} to {post_url}?",
+                    amount.to_formatted_string(&Locale::en)
+                ))
+                .interact()?;
+            if !pay_confirmed {
+                return Ok(());
+            }
+
+            let wallet_keysets = wallet.get_wallet_keysets().await?;
+            let wallet_keyset = wallet_keysets
+                .get_active(&mint_url, &currency_unit)
+                .expect("no active keyset found");
+            let payload = wallet
+                .pay_payment_request(wallet_keyset, &request, Some(amount))
+                .await?;
+
+            let client = CrossPlatformHttpClient::new();
+            if let Err(err) = client.post_json(&post_url, &payload).await {
+                term.write_line(&format!(
+                    "Error: Could not deliver the tokens ({err}). Run 'moksha-cli reclaim' to get them back"
+                ))?;
+                return Ok(());
+            }
+            term.write_line("\nPayment request has been paid")?;
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Reclaim => {
+            let result = wallet.reclaim_unclaimed_tokens().await?;
+            term.write_line(&format!(
+                "Reclaimed {} (sat)",
+                style(result.reclaimed.to_formatted_string(&Locale::en)).cyan()
+            ))?;
+            if result.claimed > 0 {
+                term.write_line(&format!(
+                    "{} (sat) were claimed by the receivers",
+                    result.claimed.to_formatted_string(&Locale::en)
+                ))?;
+            }
+            for (token, err) in result.errors {
+                term.write_line(&format!(
+                    "Error: Could not reclaim {} (sat) ({err}), retrying next time",
+                    token.total_amount().to_formatted_string(&Locale::en)
+                ))?;
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::History {
+            kind,
+            mint_url,
+            limit,
+            offset,
+        } => {
+            let transactions = wallet
+                .list_transactions(&TransactionFilter {
+                    kind,
+                    mint_url,
+                    limit: Some(limit),
+                    offset,
+                    ..Default::default()
+                })
+                .await?;
+
+            if transactions.is_empty() {
+                term.write_line("No transactions found.")?;
+            }
+
+            for transaction in transactions {
+                let time = chrono::DateTime::from_timestamp(transaction.timestamp as i64, 0)
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                let amount = match transaction.kind {
+                    TransactionKind::Melt | TransactionKind::Send => {
+                        format!("-{}", transaction.amount.to_formatted_string(&Locale::en))
+                    }
+                    _ => format!("+{}", transaction.amount.to_formatted_string(&Locale::en)),
+                };
+                let fee = match transaction.fee {
+                    0 => String::new(),
+                    fee => format!(" (fee {fee})"),
+                };
+                term.write_line(&format!(
+                    "{time} {:<8} {} {}{fee} {}",
+                    transaction.kind,
+                    style(amount).cyan(),
+                    transaction.currency_unit,
+                    transaction.mint_url
+                ))?;
+                if let Some(memo) = transaction.memo {
+                    term.write_line(&format!("    memo: {memo}"))?;
+                }
+            }
+        }
         Command::Balance => {
-            let total_balance = wallet.get_balance().await?;
-            if total_balance > 0 {
-                let mints = get_mints_with_balance(&wallet, &CurrencyUnit::Sat).await?;
+            let balances = wallet.get_balances().await?;
+            let mints = balances
+                .mints
+                .iter()
+                .filter(|b| b.amount > 0)
+                .collect::<Vec<_>>();
+            if !mints.is_empty() {
                 term.write_line(&format!(
                     "You have balances in {} mints",
                     style(mints.len()).cyan()
@@ -196,15 +745,78 @@ async fn main() -> anyhow::Result<()> {
 
                 for mint in mints {
                     term.write_line(&format!(
-                        " - {} {} (sat)",
-                        mint.0,
-                        style(mint.1.to_formatted_string(&Locale::en)).cyan()
+                        " - {} {} ({})",
+                        mint.mint_url,
+                        style(mint.amount.to_formatted_string(&Locale::en)).cyan(),
+                        mint.currency_unit
                     ))?;
                 }
             }
+            let unverified = wallet.get_unverified_proofs().await?.total_amount();
+            if unverified > 0 {
+                term.write_line(&format!(
+                    "{} (sat) received offline are not redeemed yet",
+                    unverified.to_formatted_string(&Locale::en)
+                ))?;
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Pay { invoice, mpp: true } => {
+            let invoice = cli::read_invoice(invoice).await?;
+            let pay_confirmed = Confirm::new()
+                .with_prompt("Pay lightning invoice from multiple mints?")
+                .interact()?;
+            if !pay_confirmed {
+                return Ok(());
+            }
+
+            let parts = wallet.pay_invoice_mpp(invoice, CurrencyUnit::Sat).await?;
+            let mut returned_fees = 0;
+            let mut all_paid = true;
+            for part in &parts {
+                let amount = part.amount.to_formatted_string(&Locale::en);
+                match &part.result {
+                    Ok((response, change)) if response.paid => {
+                        returned_fees += change;
+                        term.write_line(&format!("Paid {amount} (sat) from {}", part.mint_url))?;
+                    }
+                    Ok(_) => {
+                        all_paid = false;
+                        term.write_line(&format!(
+                            "Error: {amount} (sat) from {} not paid",
+                            part.mint_url
+                        ))?;
+                    }
+                    Err(err) => {
+                        all_paid = false;
+                        term.write_line(&format!(
+                            "Error: {amount} (sat) from {} failed ({err})",
+                            part.mint_url
+                        ))?;
+                    }
+                }
+            }
+            if returned_fees > 0 {
+                term.write_line(&format!(
+                    "Returned fees {} (sat)",
+                    returned_fees.to_formatted_string(&Locale::en)
+                ))?;
+            }
+            if all_paid {
+                term.write_line(&format!(
+                    "\nInvoice has been paid from {} mints: Tokens melted successfully",
+                    parts.len()
+                ))?;
+            } else {
+                term.write_line("\nInvoice has not been paid completely")?;
+            }
             cli::show_total_balance(&wallet).await?;
         }
-        Command::Pay { invoice } => {
+        Command::Pay {
+            invoice,
+            mpp: false,
+        } => {
+            let invoice = cli::read_invoice(invoice).await?;
             let currency_unit = CurrencyUnit::Sat;
             let mint_url = choose_mint(&wallet, &currency_unit).await?.0;
             let wallet_keysets = wallet.get_wallet_keysets().await?;
@@ -246,6 +858,55 @@ async fn main() -> anyhow::Result<()> {
                 term.write_line("Error: Tokens not melted")?;
             }
         }
+        Command::Transfer { amount, to } => {
+            let (from_mint, balance) = choose_mint(&wallet, &CurrencyUnit::Sat).await?;
+            if balance < amount {
+                term.write_line("Error: Not enough tokens in selected mint")?;
+                return Ok(());
+            }
+
+            let to_mint = match to {
+                Some(to_mint) => to_mint,
+                None => {
+                    let mint_urls = wallet
+                        .get_mint_urls()
+                        .await?
+                        .into_iter()
+                        .filter(|url| url != &from_mint)
+                        .collect::<Vec<Url>>();
+                    if mint_urls.is_empty() {
+                        term.write_line(
+                            "Error: No other mint found. Set the destination with --to <mint-url>",
+                        )?;
+                        return Ok(());
+                    }
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt("Choose the destination mint:")
+                        .default(0)
+                        .items(&mint_urls)
+                        .interact()?;
+                    mint_urls[selection].clone()
+                }
+            };
+
+            let transfer_confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Transfer {} (sat) from {from_mint} to {to_mint}? Lightning fees are paid by the source mint",
+                    amount.to_formatted_string(&Locale::en)
+                ))
+                .interact()?;
+            if !transfer_confirmed {
+                return Ok(());
+            }
+
+            let result = wallet.transfer(&from_mint, &to_mint, amount).await?;
+            term.write_line(&format!(
+                "Transferred {} (sat) to {to_mint}, fees {} (sat)",
+                style(result.amount.to_formatted_string(&Locale::en)).cyan(),
+                result.fee.to_formatted_string(&Locale::en)
+            ))?;
+            cli::show_total_balance(&wallet).await?;
+        }
         Command::PayOnchain { address, amount } => {
             // FIXME remove redundant code
             let currency = CurrencyUnit::Sat;
@@ -296,7 +957,7 @@ async fn main() -> anyhow::Result<()> {
             }
 
             let PostMeltBtcOnchainResponse { paid, txid } =
-                wallet.pay_onchain(wallet_keyset, quote).await?;
+                wallet.pay_onchain(wallet_keyset, quote, address).await?;
             term.write_line(&format!("Created transaction: {}\n", &txid))?;
 
             let progress_bar = cli::progress_bar()?;
@@ -314,7 +975,33 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
-        Command::Mint { amount } => {
+        Command::Mint { resume: true, .. } => {
+            let results = wallet.check_pending_mint_quotes().await?;
+            if results.is_empty() {
+                term.write_line("No pending mint quotes found")?;
+                return Ok(());
+            }
+            for (quote, result) in results {
+                let message = match result {
+                    MintQuoteResult::Minted(amount) => {
+                        format!("minted {} (sat)", amount.to_formatted_string(&Locale::en))
+                    }
+                    MintQuoteResult::Unpaid => "not paid yet".to_owned(),
+                    MintQuoteResult::Expired => "expired".to_owned(),
+                    MintQuoteResult::Failed(reason) => format!("failed: {reason}"),
+                    MintQuoteResult::Pending => "mint not reachable, retrying next time".to_owned(),
+                };
+                term.write_line(&format!(
+                    "Quote for {} (sat) at {}: {}",
+                    quote.amount.to_formatted_string(&Locale::en),
+                    quote.mint_url,
+                    style(message).cyan()
+                ))?;
+            }
+            cli::show_total_balance(&wallet).await?;
+        }
+        Command::Mint { amount, .. } => {
+            let amount = amount.expect("amount is required");
             let currency = CurrencyUnit::Sat;
             let mint_url = choose_mint(&wallet, &currency).await?.0;
 
@@ -373,11 +1060,7 @@ async fn main() -> anyhow::Result<()> {
 
                     let amount_btc = amount as f64 / 100_000_000.0;
                     let bip21_code = format!("bitcoin:{}?amount={}", address, amount_btc);
-                    let image = QrCode::new(bip21_code)?
-                        .render::<unicode::Dense1x2>()
-                        .quiet_zone(true)
-                        .build();
-                    term.write_line(&image)?;
+                    cli::show_qr_code(&term, &bip21_code)?;
                     quote
                 }
                 PaymentMethod::Bolt11 => {
@@ -391,11 +1074,7 @@ async fn main() -> anyhow::Result<()> {
                         "Pay lightning invoice to mint tokens:\n\n{payment_request}"
                     ))?;
 
-                    let image = QrCode::new(payment_request)?
-                        .render::<unicode::Dense1x2>()
-                        .quiet_zone(true)
-                        .build();
-                    term.write_line(&image)?;
+                    cli::show_qr_code(&term, &payment_request)?;
 
                     quote
                 }
@@ -406,6 +1085,7 @@ async fn main() -> anyhow::Result<()> {
                 .get_active(&mint_url, &currency)
                 .expect("Keyset not found");
 
+            term.write_line("The quote is stored. If the wallet is closed, mint the tokens later with: moksha-cli mint --resume")?;
             let progress_bar = cli::progress_bar()?;
             progress_bar.set_message("Waiting for payment ...");
 
@@ -422,8 +1102,6 @@ async fn main() -> anyhow::Result<()> {
                     continue;
                 }
 
-                // FIXME store quote in db and add option to retry minting later
-
                 let mint_result = wallet
                     .mint_tokens(wallet_keyset, &payment_method, amount.into(), quote.clone())
                     .await;
@@ -1,18 +1,36 @@
-use std::{process::exit, time::Duration};
+use std::{
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use console::{style, Term};
-use dialoguer::{theme::ColorfulTheme, Select};
+use dialoguer::{theme::ColorfulTheme, Input, Select};
 use indicatif::{ProgressBar, ProgressStyle};
 
-use moksha_core::primitives::CurrencyUnit;
+use moksha_core::{
+    payment_request::PaymentRequest,
+    primitives::CurrencyUnit,
+    ur::{UrDecoder, UrEncoder},
+};
 use moksha_wallet::{
-    error::MokshaWalletError, http::CrossPlatformHttpClient, localstore::sqlite::SqliteLocalStore,
-    wallet::Wallet,
+    error::MokshaWalletError, http::CrossPlatformHttpClient, lnurl,
+    localstore::sqlite::SqliteLocalStore, wallet::Wallet,
 };
 use num_format::Locale;
 use num_format::ToFormattedString;
+use qrcode::{render::unicode, QrCode};
 use url::Url;
 
+/// Tokens longer than this are shown as animated QR code
+const QR_FRAGMENT_THRESHOLD: usize = 400;
+/// max bytes of a fragment. Bytewords need two chars per byte
+const QR_FRAGMENT_LEN: usize = 100;
+const QR_FRAME_INTERVAL: Duration = Duration::from_millis(400);
+
 pub fn progress_bar() -> anyhow::Result<ProgressBar> {
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(100));
@@ -55,26 +73,56 @@ pub async fn choose_mint(
     Ok(mints[selection].clone())
 }
 
+/// Returns the mint the payment request accepts or lets the user choose one, if several mints have enough tokens
+pub async fn choose_accepted_mint(
+    wallet: &Wallet<SqliteLocalStore, CrossPlatformHttpClient>,
+    request: &PaymentRequest,
+    amount: u64,
+) -> Result<Option<Url>, MokshaWalletError> {
+    let mints = get_mints_with_balance(wallet, &CurrencyUnit::Sat)
+        .await?
+        .into_iter()
+        .filter(|(url, balance)| request.accepts_mint(url) && *balance >= amount)
+        .collect::<Vec<_>>();
+
+    if mints.len() <= 1 {
+        return Ok(mints.into_iter().next().map(|(url, _)| url));
+    }
+
+    let mints_display = mints
+        .iter()
+        .map(|(url, balance)| {
+            format!(
+                "{} - {} (sat)",
+                url,
+                balance.to_formatted_string(&Locale::en)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Choose a mint:")
+        .default(0)
+        .items(&mints_display[..])
+        .interact()
+        .unwrap();
+    Ok(Some(mints[selection].0.clone()))
+}
+
 pub async fn get_mints_with_balance(
     wallet: &Wallet<SqliteLocalStore, CrossPlatformHttpClient>,
     currency_unit: &CurrencyUnit,
 ) -> Result<Vec<(Url, u64)>, MokshaWalletError> {
-    let all_proofs = wallet.get_proofs().await?;
-
-    let keysets = wallet.get_wallet_keysets().await?;
-    if keysets.is_empty() {
+    let balances = wallet.get_balances().await?;
+    if balances.mints.is_empty() {
         println!("No mints found. Add a mint first with 'moksha-cli add-mint <mint-url>'");
         exit(0)
     }
-    Ok(keysets
+    Ok(balances
+        .mints
         .into_iter()
-        .filter(|k| &k.currency_unit == currency_unit && k.active)
-        .map(|k| {
-            (
-                k.mint_url,
-                all_proofs.proofs_by_keyset(&k.keyset_id).total_amount(),
-            )
-        })
+        .filter(|b| &b.currency_unit == currency_unit)
+        .map(|b| (b.mint_url, b.amount))
         .collect::<Vec<(Url, u64)>>())
 }
 
@@ -88,3 +136,87 @@ pub async fn show_total_balance(
     ))?;
     Ok(())
 }
+
+pub fn render_qr_code(data: &str) -> anyhow::Result<String> {
+    Ok(QrCode::new(data)?
+        .render::<unicode::Dense1x2>()
+        .quiet_zone(true)
+        .build())
+}
+
+pub fn show_qr_code(term: &Term, data: &str) -> anyhow::Result<()> {
+    term.write_line(&render_qr_code(data)?)?;
+    Ok(())
+}
+
+/// Shows small tokens as a single QR code. Larger tokens are encoded as BC-UR parts, that are shown in a loop
+/// until enter is pressed.
+pub async fn show_token_qr_code(term: &Term, token: &str) -> anyhow::Result<()> {
+    if token.len() <= QR_FRAGMENT_THRESHOLD {
+        return show_qr_code(term, token);
+    }
+
+    let mut encoder = UrEncoder::new(token, QR_FRAGMENT_LEN)?;
+    let count = encoder.fragment_count();
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stop = stopped.clone();
+    std::thread::spawn(move || {
+        let _ = std::io::stdin().read_line(&mut String::new());
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    term.write_line("Showing animated QR code. Press enter to continue")?;
+    // after the first round the encoder creates fountain coded parts, so a scanner can make up for missed parts
+    loop {
+        let frame = render_qr_code(&encoder.next_part()?.to_uppercase())?;
+        term.write_line(&format!("{frame}\n{count} fragments"))?;
+        tokio::time::sleep(QR_FRAME_INTERVAL).await;
+        term.clear_last_lines(frame.lines().count() + 1)?;
+        if stopped.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the invoice or resolves a Lightning address or LNURL-pay link to an invoice for an amount the user enters
+pub async fn read_invoice(input: String) -> anyhow::Result<String> {
+    if !lnurl::is_lnurl(&input) {
+        return Ok(input);
+    }
+
+    let client = CrossPlatformHttpClient::new();
+    let pay_request = client.get_lnurl_pay_request(&input).await?;
+    if let Some(description) = pay_request.description() {
+        println!("{description}");
+    }
+    let amount = Input::<u64>::new()
+        .with_prompt(format!(
+            "Amount in sats ({} - {})",
+            pay_request.min_amount().to_formatted_string(&Locale::en),
+            pay_request.max_amount().to_formatted_string(&Locale::en)
+        ))
+        .interact_text()?;
+    Ok(client.get_lnurl_invoice(&pay_request, amount).await?)
+}
+
+/// Returns the token or prompts for the remaining parts, if the input is the first part of a UR encoded token
+pub fn read_token(input: String) -> anyhow::Result<String> {
+    if !input.to_lowercase().starts_with("ur:") {
+        return Ok(input);
+    }
+
+    let mut decoder = UrDecoder::new();
+    decoder.receive(&input)?;
+    loop {
+        if let Some(token) = decoder.message()? {
+            return Ok(token);
+        }
+        let (received, total) = decoder.progress();
+        let part: String = Input::new()
+            .with_prompt(format!("Scanned {received}/{total} fragments. Next part"))
+            .interact_text()?;
+        decoder.receive(part.trim())?;
+    }
+}
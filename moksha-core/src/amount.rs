@@ -4,8 +4,14 @@
 //!
 //! The `SplitAmount` struct represents a split amount, with a `Vec<u64>` field for the split amounts. The struct provides a `create_secrets` method that generates a vector of random strings for use as secrets in the split transaction. The struct also implements the `IntoIterator` trait, which allows it to be iterated over as a vector of `u64` values.
 //!
+//! The `SplitStrategy` enum selects the denominations that are used for splitting an amount into new outputs.
+//!
 //! Both the `Amount` and `SplitAmount` structs are serializable and deserializable using serde.
 
+use std::{fmt, str::FromStr};
+
+use crate::error::MokshaCoreError;
+
 #[derive(Debug, Clone)]
 pub struct Amount(pub u64);
 
@@ -13,6 +19,71 @@ impl Amount {
     pub fn split(&self) -> SplitAmount {
         split_amount(self.0).into()
     }
+
+    /// Splits the amount so that every denomination is available `target_count` times, starting with the
+    /// smallest one. The rest of the amount is split into powers of 2.
+    pub fn split_with_target(&self, target_count: usize) -> SplitAmount {
+        split_amount_with_target(self.0, target_count).into()
+    }
+
+    pub fn split_with_strategy(&self, strategy: &SplitStrategy) -> SplitAmount {
+        match strategy {
+            SplitStrategy::Minimal => self.split(),
+            SplitStrategy::TargetCount(target_count) => self.split_with_target(*target_count),
+            SplitStrategy::Custom(amounts) => split_amount_with_preferred(self.0, amounts).into(),
+        }
+    }
+}
+
+/// Strategy for choosing the denominations of new outputs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SplitStrategy {
+    /// Powers of 2 with the least number of outputs
+    #[default]
+    Minimal,
+    /// Up to the given number of outputs per denomination, see [`Amount::split_with_target`]
+    TargetCount(usize),
+    /// The given amounts are used first, as long as they fit into the amount. The rest is split minimal.
+    Custom(Vec<u64>),
+}
+
+impl fmt::Display for SplitStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Minimal => write!(f, "minimal"),
+            Self::TargetCount(target_count) => write!(f, "target:{target_count}"),
+            Self::Custom(amounts) => write!(
+                f,
+                "custom:{}",
+                amounts
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
+/// Parses `minimal`, `target:<count>` or `custom:<amount>,<amount>,...`
+impl FromStr for SplitStrategy {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MokshaCoreError::InvalidSplitStrategy(s.to_owned());
+        match s.split_once(':') {
+            None if s == "minimal" => Ok(Self::Minimal),
+            Some(("target", count)) => Ok(Self::TargetCount(count.parse().map_err(|_| invalid())?)),
+            Some(("custom", amounts)) => Ok(Self::Custom(
+                amounts
+                    .split(',')
+                    .map(|amount| amount.trim().parse::<u64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -64,8 +135,46 @@ fn split_amount(amount: u64) -> Vec<u64> {
         .collect::<Vec<u64>>()
 }
 
+/// split a decimal amount into powers of 2 with up to `target_count` proofs per denomination
+fn split_amount_with_target(amount: u64, target_count: usize) -> Vec<u64> {
+    let mut remaining = amount;
+    let mut result = vec![];
+    for denomination in (0..u64::BITS).map(|i| 2_u64.pow(i)) {
+        if denomination > remaining {
+            break;
+        }
+        for _ in 0..target_count {
+            if denomination > remaining {
+                break;
+            }
+            result.push(denomination);
+            remaining -= denomination;
+        }
+    }
+    result.extend(split_amount(remaining));
+    result.sort_unstable();
+    result
+}
+
+/// split a decimal amount into the preferred amounts first and the rest into powers of 2
+fn split_amount_with_preferred(amount: u64, preferred: &[u64]) -> Vec<u64> {
+    let mut remaining = amount;
+    let mut result = vec![];
+    for preferred_amount in preferred {
+        if *preferred_amount == 0 || *preferred_amount > remaining {
+            continue;
+        }
+        result.extend(split_amount(*preferred_amount));
+        remaining -= preferred_amount;
+    }
+    result.extend(split_amount(remaining));
+    result.sort_unstable();
+    result
+}
+
 #[cfg(test)]
 mod tests {
+    use super::SplitStrategy;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -80,4 +189,41 @@ mod tests {
         assert_eq!(bits, vec![64]);
         Ok(())
     }
+
+    #[test]
+    fn test_split_amount_with_target() -> anyhow::Result<()> {
+        let bits = super::split_amount_with_target(13, 2);
+        assert_eq!(bits, vec![1, 1, 1, 2, 2, 2, 4]);
+
+        let bits = super::split_amount_with_target(64, 1);
+        assert_eq!(bits, vec![1, 1, 2, 4, 8, 16, 32]);
+
+        let bits = super::split_amount_with_target(64, 0);
+        assert_eq!(bits, vec![64]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_amount_with_preferred() -> anyhow::Result<()> {
+        let bits = super::split_amount_with_preferred(13, &[2, 2, 10]);
+        assert_eq!(bits, vec![1, 2, 2, 8]);
+
+        let bits = super::split_amount_with_preferred(13, &[3]);
+        assert_eq!(bits, vec![1, 2, 2, 8]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_split_strategy() -> anyhow::Result<()> {
+        for strategy in [
+            SplitStrategy::Minimal,
+            SplitStrategy::TargetCount(3),
+            SplitStrategy::Custom(vec![1, 2, 2]),
+        ] {
+            assert_eq!(strategy, strategy.to_string().parse()?);
+        }
+        assert!("target:x".parse::<SplitStrategy>().is_err());
+        assert!("random".parse::<SplitStrategy>().is_err());
+        Ok(())
+    }
 }
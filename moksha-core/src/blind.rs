@@ -33,7 +33,7 @@ pub struct BlindedMessage {
     pub id: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlindingFactor(SecretKey);
 
 impl From<SecretKey> for BlindingFactor {
@@ -0,0 +1,471 @@
+//! This module defines the `CoinSelector`, which selects the proofs that are spent for a given amount.
+//!
+//! An exact match is preferred, because the selected proofs can be sent or melted without swapping them first.
+//! If no exact match exists, the fewest proofs are selected that cover the amount and their input fees.
+//! Input fees are charged per proof and calculated from the `input_fee_ppk` of the keyset as described in [Nut-02](https://github.com/cashubtc/nuts/blob/main/02.md)
+//!
+//! The selection can be constrained to a set of keysets, e.g. all keysets of a mint and unit.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    amount::Amount,
+    error::MokshaCoreError,
+    proof::{Proof, Proofs},
+};
+
+/// Upper bound for the exact match search. If it is reached, the best match found so far is used.
+const MAX_SEARCH_STEPS: usize = 100_000;
+
+#[derive(Clone, Debug, Default)]
+pub struct CoinSelector {
+    input_fee_ppk: HashMap<String, u64>,
+    keyset_ids: Option<HashSet<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoinSelection {
+    pub proofs: Proofs,
+    /// input fee the mint charges for spending the selected proofs
+    pub fee: u64,
+}
+
+impl CoinSelection {
+    /// Returns true if the proofs can be spent for `amount` without any change
+    pub fn is_exact(&self, amount: u64) -> bool {
+        self.proofs.total_amount() == amount + self.fee
+    }
+
+    pub fn change(&self, amount: u64) -> u64 {
+        self.proofs.total_amount().saturating_sub(amount + self.fee)
+    }
+}
+
+impl CoinSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_input_fee_ppk(mut self, keyset_id: impl Into<String>, input_fee_ppk: u64) -> Self {
+        self.input_fee_ppk.insert(keyset_id.into(), input_fee_ppk);
+        self
+    }
+
+    /// Only proofs of the given keysets will be selected
+    pub fn with_keysets<I, S>(mut self, keyset_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.keyset_ids = Some(keyset_ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn fee_ppk(&self, keyset_id: &str) -> u64 {
+        self.input_fee_ppk
+            .get(keyset_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the input fee for spending the given proofs
+    pub fn fee(&self, proofs: &Proofs) -> u64 {
+        fee_from_ppk(
+            proofs
+                .0
+                .iter()
+                .map(|proof| self.fee_ppk(&proof.keyset_id))
+                .sum(),
+        )
+    }
+
+    /// Returns the amount that has to be split into new proofs of `keyset_id`, so that spending them
+    /// covers `amount` and their own input fee.
+    pub fn amount_with_fee(&self, keyset_id: &str, amount: u64) -> u64 {
+        let fee_ppk = self.fee_ppk(keyset_id);
+        let mut target = amount;
+        while amount + fee_from_ppk(Amount(target).split().len() as u64 * fee_ppk) > target {
+            target += 1;
+        }
+        target
+    }
+
+    /// Selects proofs that are spent at the mint (e.g. in a swap or melt) for `amount` plus their input fee.
+    pub fn select(&self, proofs: &Proofs, amount: u64) -> Result<CoinSelection, MokshaCoreError> {
+        let candidates = self.candidates(proofs);
+        if let Some(selected) = self.find_exact(&candidates, amount, true) {
+            return Ok(self.selection(selected));
+        }
+        self.find_cover(&candidates, amount)
+            .map(|selected| self.selection(selected))
+            .ok_or(MokshaCoreError::NotEnoughTokens)
+    }
+
+    /// Selects proofs that are handed over in a token. If proofs with a total of exactly `amount` exist,
+    /// they can be sent as they are and no fee is due. Otherwise the selected proofs have to be swapped first.
+    pub fn select_for_send(
+        &self,
+        proofs: &Proofs,
+        amount: u64,
+    ) -> Result<CoinSelection, MokshaCoreError> {
+        let candidates = self.candidates(proofs);
+        match self.find_exact(&candidates, amount, false) {
+            Some(selected) => Ok(CoinSelection {
+                proofs: selected.into(),
+                fee: 0,
+            }),
+            None => self.select(proofs, amount),
+        }
+    }
+
+    fn selection(&self, selected: Vec<Proof>) -> CoinSelection {
+        let proofs: Proofs = selected.into();
+        CoinSelection {
+            fee: self.fee(&proofs),
+            proofs,
+        }
+    }
+
+    /// Returns the proofs of the allowed keysets, sorted by amount in descending order.
+    /// Proofs that cost more in fees than they are worth are skipped.
+    fn candidates(&self, proofs: &Proofs) -> Vec<Proof> {
+        let mut candidates = proofs
+            .0
+            .iter()
+            .filter(|proof| {
+                self.keyset_ids
+                    .as_ref()
+                    .map_or(true, |ids| ids.contains(&proof.keyset_id))
+            })
+            .filter(|proof| proof.amount * 1000 > self.fee_ppk(&proof.keyset_id))
+            .cloned()
+            .collect::<Vec<Proof>>();
+        candidates.sort_by(|a, b| b.amount.cmp(&a.amount));
+        candidates
+    }
+
+    /// Searches the subset with the fewest proofs whose total equals `amount` plus fees.
+    fn find_exact(&self, candidates: &[Proof], amount: u64, with_fees: bool) -> Option<Vec<Proof>> {
+        let fees = candidates
+            .iter()
+            .map(|proof| {
+                if with_fees {
+                    self.fee_ppk(&proof.keyset_id)
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<u64>>();
+        let mut remaining = vec![0; candidates.len() + 1];
+        for i in (0..candidates.len()).rev() {
+            remaining[i] = remaining[i + 1] + candidates[i].amount;
+        }
+
+        let mut search = ExactSearch {
+            candidates,
+            fees: &fees,
+            remaining: &remaining,
+            amount,
+            steps: 0,
+            current: vec![],
+            best: None,
+        };
+        search.run(0, 0, 0);
+        search.best.map(|best| {
+            best.into_iter()
+                .map(|i| candidates[i].clone())
+                .collect::<Vec<Proof>>()
+        })
+    }
+
+    /// Selects the fewest proofs that cover `amount` plus fees and then replaces each selected proof with the
+    /// smallest unselected one that still covers it, to keep the change small.
+    fn find_cover(&self, candidates: &[Proof], amount: u64) -> Option<Vec<Proof>> {
+        let mut selected = vec![];
+        let mut total = 0;
+        let mut fee_ppk = 0;
+        for (i, proof) in candidates.iter().enumerate() {
+            if total >= amount + fee_from_ppk(fee_ppk) {
+                break;
+            }
+            total += proof.amount;
+            fee_ppk += self.fee_ppk(&proof.keyset_id);
+            selected.push(i);
+        }
+        if total < amount + fee_from_ppk(fee_ppk) {
+            return None;
+        }
+
+        for pos in 0..selected.len() {
+            let current = &candidates[selected[pos]];
+            let replacement = (0..candidates.len())
+                .rev()
+                .filter(|i| !selected.contains(i))
+                .find(|i| {
+                    let proof = &candidates[*i];
+                    let new_fee_ppk =
+                        fee_ppk - self.fee_ppk(&current.keyset_id) + self.fee_ppk(&proof.keyset_id);
+                    proof.amount < current.amount
+                        && total - current.amount + proof.amount
+                            >= amount + fee_from_ppk(new_fee_ppk)
+                });
+            if let Some(i) = replacement {
+                let proof = &candidates[i];
+                total = total - current.amount + proof.amount;
+                fee_ppk =
+                    fee_ppk - self.fee_ppk(&current.keyset_id) + self.fee_ppk(&proof.keyset_id);
+                selected[pos] = i;
+            }
+        }
+
+        Some(
+            selected
+                .into_iter()
+                .map(|i| candidates[i].clone())
+                .collect(),
+        )
+    }
+}
+
+/// Depth first search over the candidates, which are sorted by amount in descending order.
+struct ExactSearch<'a> {
+    candidates: &'a [Proof],
+    fees: &'a [u64],
+    /// total amount of the candidates starting at an index
+    remaining: &'a [u64],
+    amount: u64,
+    steps: usize,
+    current: Vec<usize>,
+    best: Option<Vec<usize>>,
+}
+
+impl ExactSearch<'_> {
+    fn run(&mut self, index: usize, total: u64, fee_ppk: u64) {
+        self.steps += 1;
+        if self.steps > MAX_SEARCH_STEPS {
+            return;
+        }
+
+        let target = self.amount + fee_from_ppk(fee_ppk);
+        if total == target {
+            self.best = Some(self.current.clone());
+            return;
+        }
+        // every candidate is worth more than its fee, so the excess can only grow
+        if total > target || total + self.remaining[index] < target {
+            return;
+        }
+        if let Some(best) = &self.best {
+            if self.current.len() + 1 >= best.len() {
+                return;
+            }
+        }
+
+        for i in index..self.candidates.len() {
+            // skip candidates that would lead to the same combinations as their predecessor
+            if i > index
+                && self.candidates[i].amount == self.candidates[i - 1].amount
+                && self.fees[i] == self.fees[i - 1]
+            {
+                continue;
+            }
+            self.current.push(i);
+            self.run(
+                i + 1,
+                total + self.candidates[i].amount,
+                fee_ppk + self.fees[i],
+            );
+            self.current.pop();
+        }
+    }
+}
+
+fn fee_from_ppk(fee_ppk: u64) -> u64 {
+    (fee_ppk + 999) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::{
+        coin_selection::{fee_from_ppk, CoinSelector},
+        dhke::public_key_from_hex,
+        fixture::read_fixture,
+        proof::{Proof, Proofs},
+        token::TokenV3,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn proof(amount: u64, keyset_id: &str, index: usize) -> Proof {
+        Proof::new(
+            amount,
+            format!("secret_{index}"),
+            public_key_from_hex(
+                "02c020067db727d586bc3183aecf97fcb800c3f4cc4759f69c626c9db5d8f5b5d4",
+            ),
+            keyset_id.to_owned(),
+        )
+    }
+
+    fn proofs(amounts: &[u64]) -> Proofs {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| proof(*amount, "00f545318e4fad2b", i))
+            .collect::<Vec<Proof>>()
+            .into()
+    }
+
+    /// Returns all subsets that cover `amount` with fees as (is_exact, number of proofs)
+    fn covering_subsets(proofs: &Proofs, amount: u64, fee_ppk: u64) -> Vec<(bool, usize)> {
+        let proofs = proofs.proofs();
+        (0..1u32 << proofs.len())
+            .filter_map(|mask| {
+                let subset = (0..proofs.len())
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| proofs[i].amount)
+                    .collect::<Vec<u64>>();
+                let total = subset.iter().sum::<u64>();
+                let needed = amount + fee_from_ppk(subset.len() as u64 * fee_ppk);
+                (total >= needed).then_some((total == needed, subset.len()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_exact_match() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
+        let token: TokenV3 = fixture.try_into()?;
+
+        let result = CoinSelector::new().select(&token.proofs(), 24)?;
+        assert!(result.is_exact(24));
+        assert_eq!(2, result.proofs.len());
+        assert_eq!(0, result.fee);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_prefers_fewest_proofs() -> anyhow::Result<()> {
+        let result = CoinSelector::new().select(&proofs(&[1, 1, 2, 4, 8, 16, 8]), 16)?;
+        assert_eq!(1, result.proofs.len());
+        assert_eq!(16, result.proofs.total_amount());
+
+        let result = CoinSelector::new().select(&proofs(&[64, 32, 16, 8]), 10)?;
+        assert!(!result.is_exact(10));
+        assert_eq!(16, result.proofs.total_amount());
+        assert_eq!(6, result.change(10));
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_with_fees() -> anyhow::Result<()> {
+        let selector = CoinSelector::new().with_input_fee_ppk("00f545318e4fad2b", 100);
+
+        // 16 + 4 + 1 covers 20 and the fee of 3 * 100ppk
+        let result = selector.select(&proofs(&[16, 4, 2, 1]), 20)?;
+        assert!(result.is_exact(20));
+        assert_eq!(1, result.fee);
+        assert_eq!(3, result.proofs.len());
+
+        assert!(selector.select(&proofs(&[16, 4]), 20).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_for_send_without_fees() -> anyhow::Result<()> {
+        let selector = CoinSelector::new().with_input_fee_ppk("00f545318e4fad2b", 100);
+
+        let result = selector.select_for_send(&proofs(&[16, 4, 2, 1]), 20)?;
+        assert!(result.is_exact(20));
+        assert_eq!(0, result.fee);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_respects_keysets() -> anyhow::Result<()> {
+        let all_proofs: Proofs = vec![
+            proof(32, "00f545318e4fad2b", 0),
+            proof(8, "009a1f293253e41e", 1),
+            proof(16, "009a1f293253e41e", 2),
+        ]
+        .into();
+
+        let result = CoinSelector::new()
+            .with_keysets(["009a1f293253e41e"])
+            .select(&all_proofs, 20)?;
+        assert_eq!(24, result.proofs.total_amount());
+        assert!(result
+            .proofs
+            .proofs()
+            .iter()
+            .all(|p| p.keyset_id == "009a1f293253e41e"));
+
+        let result = CoinSelector::new()
+            .with_keysets(["009a1f293253e41e"])
+            .select(&all_proofs, 30);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_amount_with_fee() {
+        let selector = CoinSelector::new().with_input_fee_ppk("00f545318e4fad2b", 100);
+        assert_eq!(21, selector.amount_with_fee("00f545318e4fad2b", 20));
+        assert_eq!(
+            20,
+            CoinSelector::new().amount_with_fee("00f545318e4fad2b", 20)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_selection_covers_amount_and_fee(
+            exponents in prop::collection::vec(0u32..8, 0..10),
+            amount in 1u64..300,
+            fee_ppk in 0u64..1000,
+        ) {
+            let amounts = exponents.iter().map(|e| 2u64.pow(*e)).collect::<Vec<u64>>();
+            let all_proofs = proofs(&amounts);
+            let selector = CoinSelector::new().with_input_fee_ppk("00f545318e4fad2b", fee_ppk);
+            let subsets = covering_subsets(&all_proofs, amount, fee_ppk);
+
+            match selector.select(&all_proofs, amount) {
+                Ok(result) => {
+                    let selected = result.proofs.proofs();
+                    prop_assert!(selected.iter().all(|p| all_proofs.proofs().contains(p)));
+                    prop_assert_eq!(result.fee, fee_from_ppk(selected.len() as u64 * fee_ppk));
+                    prop_assert!(result.proofs.total_amount() >= amount + result.fee);
+
+                    let min_exact = subsets.iter().filter(|(exact, _)| *exact).map(|(_, len)| *len).min();
+                    match min_exact {
+                        Some(min_len) => {
+                            prop_assert!(result.is_exact(amount));
+                            prop_assert_eq!(min_len, selected.len());
+                        }
+                        None => {
+                            let min_len = subsets.iter().map(|(_, len)| *len).min();
+                            prop_assert_eq!(min_len, Some(selected.len()));
+                        }
+                    }
+                }
+                Err(_) => prop_assert!(subsets.is_empty()),
+            }
+        }
+
+        #[test]
+        fn prop_select_for_send_prefers_exact_total(
+            exponents in prop::collection::vec(0u32..8, 0..10),
+            amount in 1u64..300,
+        ) {
+            let amounts = exponents.iter().map(|e| 2u64.pow(*e)).collect::<Vec<u64>>();
+            let all_proofs = proofs(&amounts);
+            let selector = CoinSelector::new().with_input_fee_ppk("00f545318e4fad2b", 500);
+            let has_exact = covering_subsets(&all_proofs, amount, 0).iter().any(|(exact, _)| *exact);
+
+            if let Ok(result) = selector.select_for_send(&all_proofs, amount) {
+                prop_assert_eq!(has_exact, result.proofs.total_amount() == amount);
+            }
+        }
+    }
+}
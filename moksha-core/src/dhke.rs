@@ -41,7 +41,7 @@
 //! If true, C must have originated from Bob
 //!
 //!
-use crate::{blind::BlindingFactor, error::MokshaCoreError};
+use crate::{blind::BlindingFactor, error::MokshaCoreError, proof::Proof};
 use bitcoin_hashes::{sha256, Hash};
 use secp256k1::{All, PublicKey, Scalar, Secp256k1, SecretKey};
 use std::iter::once;
@@ -74,7 +74,7 @@ impl Dhke {
 
     /// The domain separator is b"Secp256k1_HashToCurve_Cashu_" or
     /// bytes.fromhex("536563703235366b315f48617368546f43757276655f43617368755f").
-    fn hash_to_curve(message: &[u8]) -> Result<PublicKey, MokshaCoreError> {
+    pub fn hash_to_curve(message: &[u8]) -> Result<PublicKey, MokshaCoreError> {
         let msg_to_hash = sha256::Hash::hash(&[b"Secp256k1_HashToCurve_Cashu_", message].concat());
         (0..2u32.pow(16))
             .map(|counter| sha256::Hash::hash(&[&msg_to_hash[..], &counter.to_le_bytes()].concat()))
@@ -106,6 +106,22 @@ impl Dhke {
             .map_err(MokshaCoreError::Secp256k1Error)
     }
 
+    /// Creates the DLEQ proof `(e, s)` for the blind signature `C_ = a*B_` as described in
+    /// [Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md). `p` is a random nonce.
+    pub fn step2_bob_dleq(
+        &self,
+        b_: PublicKey,
+        a: &SecretKey,
+        p: &SecretKey,
+    ) -> Result<(SecretKey, SecretKey), MokshaCoreError> {
+        let c_ = self.step2_bob(b_, a)?;
+        let r1 = PublicKey::from_secret_key(&self.secp, p);
+        let r2 = b_.mul_tweak(&self.secp, &Scalar::from(*p))?;
+        let e = SecretKey::from_slice(&Self::hash_e(&[r1, r2, a.public_key(&self.secp), c_]))?;
+        let s = p.add_tweak(&Scalar::from(e.mul_tweak(&Scalar::from(*a))?))?;
+        Ok((e, s))
+    }
+
     pub fn step3_alice(
         &self,
         c_: PublicKey,
@@ -131,6 +147,37 @@ impl Dhke {
             MokshaCoreError::Secp256k1Error(secp256k1::Error::InvalidPublicKey),
         )
     }
+
+    /// Verifies the DLEQ proof of a signature as described in [Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md).
+    /// `a` is the public key of the mint for the amount of the proof. Returns false if the proof has no DLEQ.
+    pub fn verify_dleq(&self, proof: &Proof, a: &PublicKey) -> Result<bool, MokshaCoreError> {
+        let Some(dleq) = &proof.dleq else {
+            return Ok(false);
+        };
+        let y = Self::hash_to_curve(proof.secret.as_bytes())?;
+        let c_ = proof
+            .c
+            .combine(&a.mul_tweak(&self.secp, &Scalar::from(dleq.r))?)?;
+        let b_ = y.combine(&PublicKey::from_secret_key(&self.secp, &dleq.r))?;
+        let r1 = PublicKey::from_secret_key(&self.secp, &dleq.s).combine(
+            &a.mul_tweak(&self.secp, &Scalar::from(dleq.e))?
+                .negate(&self.secp),
+        )?;
+        let r2 = b_.mul_tweak(&self.secp, &Scalar::from(dleq.s))?.combine(
+            &c_.mul_tweak(&self.secp, &Scalar::from(dleq.e))?
+                .negate(&self.secp),
+        )?;
+        Ok(Self::hash_e(&[r1, r2, *a, c_]) == dleq.e.secret_bytes())
+    }
+
+    /// Hash of the hex encoded uncompressed public keys, used as challenge of the DLEQ proof
+    fn hash_e(public_keys: &[PublicKey]) -> [u8; 32] {
+        let e = public_keys
+            .iter()
+            .map(|key| hex::encode(key.serialize_uncompressed()))
+            .collect::<String>();
+        sha256::Hash::hash(e.as_bytes()).to_byte_array()
+    }
 }
 
 pub fn public_key_from_hex(hex: &str) -> secp256k1::PublicKey {
@@ -146,6 +193,7 @@ mod tests {
     use crate::{
         blind::BlindingFactor,
         dhke::{public_key_from_hex, Dhke},
+        proof::{Proof, ProofDleq},
     };
     use anyhow::Ok;
     use pretty_assertions::assert_eq;
@@ -287,4 +335,54 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_verify_dleq() -> anyhow::Result<()> {
+        // test vector from Nut-12
+        let a = public_key_from_hex(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        );
+        let mut proof = serde_json::from_str::<Proof>(
+            r#"{
+                "amount": 1,
+                "id": "00882760bfa2eb41",
+                "secret": "daf4dd00a2b68a0858a80450f52c8a7d2ccf87d375e43e216e0c571f089f63e9",
+                "C": "024369d2d22a80ecf78f3937da9d5f30c1b9f74f0c32684d583cca0fa6a61cdcfc",
+                "dleq": {
+                    "e": "b31e58ac6527f34975ffab13e70a48b6d2b0d35abc4b03f0151f09ee1a9763d4",
+                    "s": "8fbae004c59e754d71df67e392b6ae4e29293113ddc2ec86592a0431d16306d8",
+                    "r": "a6d13fcd7a18442e6076f5e1e7c887ad5de40a019824bdfa9fe740d302e8d861"
+                }
+            }"#,
+        )?;
+
+        let dhke = Dhke::new();
+        assert!(dhke.verify_dleq(&proof, &a)?);
+
+        proof.secret = "another secret".to_owned();
+        assert!(!dhke.verify_dleq(&proof, &a)?);
+
+        proof.dleq = None;
+        assert!(!dhke.verify_dleq(&proof, &a)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_step2_bob_dleq() -> anyhow::Result<()> {
+        let dhke = Dhke::new();
+        let a = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000001");
+        let r = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000002");
+        let p = pk_from_hex("0000000000000000000000000000000000000000000000000000000000000003");
+        let secret = "test";
+
+        let b_ = dhke.step1_alice(secret, &r.into())?;
+        let c_ = dhke.step2_bob(b_, &a)?;
+        let c = dhke.step3_alice(c_, r.into(), a.public_key(&dhke.secp))?;
+        let (e, s) = dhke.step2_bob_dleq(b_, &a, &p)?;
+
+        let mut proof = Proof::new(1, secret.to_owned(), c, "00882760bfa2eb41".to_owned());
+        proof.dleq = Some(ProofDleq { e, s, r });
+        assert!(dhke.verify_dleq(&proof, &a.public_key(&dhke.secp))?);
+        Ok(())
+    }
 }
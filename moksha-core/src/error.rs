@@ -29,6 +29,15 @@ pub enum MokshaCoreError {
     #[error("Invalid token")]
     InvalidToken,
 
+    #[error("Invalid token v4: {0}")]
+    InvalidTokenV4(String),
+
+    #[error("CborError {0}")]
+    CborError(String),
+
+    #[error("Invalid UR: {0}")]
+    InvalidUr(String),
+
     #[error("No valid point on curve secp256k1 found")]
     NoValidPointFound,
 
@@ -37,4 +46,13 @@ pub enum MokshaCoreError {
 
     #[error("Invalid Keyset-ID")]
     Slice(#[from] std::array::TryFromSliceError),
+
+    #[error("Invalid split strategy: {0}")]
+    InvalidSplitStrategy(String),
+
+    #[error("Invalid P2PK secret")]
+    InvalidP2PKSecret,
+
+    #[error("Invalid payment request")]
+    InvalidPaymentRequest,
 }
@@ -1,9 +1,13 @@
 pub mod amount;
 pub mod blind;
+pub mod coin_selection;
 pub mod dhke;
 pub mod error;
 pub mod fixture;
 pub mod keyset;
+pub mod p2pk;
+pub mod payment_request;
 pub mod primitives;
 pub mod proof;
 pub mod token;
+pub mod ur;
@@ -0,0 +1,218 @@
+//! This module defines the `P2PKSecret` and `P2PKWitness` structs for proofs that are locked to a public key (Pay to Public Key).
+//!
+//! The secret of a locked proof is a well-known secret as described in [Nut-10](https://github.com/cashubtc/nuts/blob/main/10.md)
+//! of kind `P2PK`. To spend the proof the owner of the public key signs the secret and adds the signature as witness
+//! as described in [Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md).
+
+use std::{fmt, str::FromStr};
+
+use bitcoin_hashes::{sha256, Hash};
+use secp256k1::{schnorr::Signature, Keypair, Message, PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::MokshaCoreError;
+
+const P2PK_KIND: &str = "P2PK";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct P2PKSecret {
+    pub nonce: String,
+    /// public key the proof is locked to
+    pub pubkey: PublicKey,
+    /// additional conditions, e.g. `["locktime", "1689418329"]`
+    pub tags: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WellKnownSecretData {
+    nonce: String,
+    data: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<Vec<String>>,
+}
+
+impl P2PKSecret {
+    pub fn new(pubkey: PublicKey) -> Self {
+        Self {
+            nonce: Uuid::new_v4().simple().to_string(),
+            pubkey,
+            tags: vec![],
+        }
+    }
+
+    /// Returns the values of the first tag with the given name
+    pub fn tag(&self, name: &str) -> Option<&[String]> {
+        self.tags
+            .iter()
+            .find(|tag| tag.first().is_some_and(|key| key == name))
+            .map(|tag| &tag[1..])
+    }
+
+    /// Unix timestamp after which the proof can be spent with the refund keys or by anyone
+    pub fn locktime(&self) -> Option<u64> {
+        self.tag("locktime")
+            .and_then(|values| values.first())
+            .and_then(|locktime| locktime.parse().ok())
+    }
+
+    /// Number of signatures that are required to spend the proof
+    pub fn n_sigs(&self) -> u64 {
+        self.tag("n_sigs")
+            .and_then(|values| values.first())
+            .and_then(|n_sigs| n_sigs.parse().ok())
+            .unwrap_or(1)
+    }
+
+    /// Returns the public key in `data` and the additional keys of the `pubkeys` tag
+    pub fn pubkeys(&self) -> Vec<PublicKey> {
+        let additional_keys = self
+            .tag("pubkeys")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|key| key.parse().ok());
+        std::iter::once(self.pubkey)
+            .chain(additional_keys)
+            .collect()
+    }
+
+    /// Returns true if one of the signatures of the witness is valid for one of the public keys. `secret`
+    /// is the serialized secret of the proof.
+    pub fn verify_witness(&self, secret: &str, witness: &P2PKWitness) -> bool {
+        let secp = Secp256k1::verification_only();
+        let message = message(secret);
+        witness
+            .signatures
+            .iter()
+            .filter_map(|signature| Signature::from_str(signature).ok())
+            .any(|signature| {
+                self.pubkeys().iter().any(|pubkey| {
+                    secp.verify_schnorr(&signature, &message, &pubkey.x_only_public_key().0)
+                        .is_ok()
+                })
+            })
+    }
+}
+
+impl fmt::Display for P2PKSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = WellKnownSecretData {
+            nonce: self.nonce.clone(),
+            data: self.pubkey.to_string(),
+            tags: self.tags.clone(),
+        };
+        let secret = serde_json::to_string(&(P2PK_KIND, data)).map_err(|_| fmt::Error)?;
+        write!(f, "{secret}")
+    }
+}
+
+impl FromStr for P2PKSecret {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, data) = serde_json::from_str::<(String, WellKnownSecretData)>(s)
+            .map_err(|_| MokshaCoreError::InvalidP2PKSecret)?;
+        if kind != P2PK_KIND {
+            return Err(MokshaCoreError::InvalidP2PKSecret);
+        }
+        Ok(Self {
+            nonce: data.nonce,
+            pubkey: data
+                .data
+                .parse()
+                .map_err(|_| MokshaCoreError::InvalidP2PKSecret)?,
+            tags: data.tags,
+        })
+    }
+}
+
+/// Witness of a proof with a P2PK secret
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct P2PKWitness {
+    pub signatures: Vec<String>,
+}
+
+impl P2PKWitness {
+    /// Signs the secret of a proof with the key the proof is locked to
+    pub fn sign(secret: &str, secret_key: &SecretKey) -> Self {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_secret_key(&secp, secret_key);
+        let signature = secp.sign_schnorr_no_aux_rand(&message(secret), &keypair);
+        Self {
+            signatures: vec![signature.to_string()],
+        }
+    }
+}
+
+impl fmt::Display for P2PKWitness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let witness = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        write!(f, "{witness}")
+    }
+}
+
+impl FromStr for P2PKWitness {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+/// The signed message is the sha256 hash of the serialized secret
+fn message(secret: &str) -> Message {
+    Message::from_digest(sha256::Hash::hash(secret.as_bytes()).to_byte_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{P2PKSecret, P2PKWitness};
+    use crate::dhke::public_key_from_hex;
+    use pretty_assertions::assert_eq;
+    use secp256k1::{Secp256k1, SecretKey};
+
+    #[test]
+    fn test_parse_p2pk_secret() -> anyhow::Result<()> {
+        // example from Nut-11
+        let secret = r#"["P2PK",{"nonce":"859d4935c4907062a6297cf4e663e2835d90d97ecdd510745d32f6816323a41f","data":"0249098aa8b9d2fbec49ff8598feb17b592b986e62319a4fa488a3dc36387157a7","tags":[["sigflag","SIG_INPUTS"]]}]"#;
+        let p2pk_secret = secret.parse::<P2PKSecret>()?;
+        assert_eq!(
+            public_key_from_hex(
+                "0249098aa8b9d2fbec49ff8598feb17b592b986e62319a4fa488a3dc36387157a7"
+            ),
+            p2pk_secret.pubkey
+        );
+        assert_eq!(
+            Some(&["SIG_INPUTS".to_owned()][..]),
+            p2pk_secret.tag("sigflag")
+        );
+        assert_eq!(None, p2pk_secret.locktime());
+        assert_eq!(1, p2pk_secret.n_sigs());
+        assert_eq!(secret, p2pk_secret.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_random_secret() {
+        assert!(
+            "daf4dd00a2b68a0858a80450f52c8a7d2ccf87d375e43e216e0c571f089f63e9"
+                .parse::<P2PKSecret>()
+                .is_err()
+        );
+        assert!(r#"["HTLC",{"nonce":"1","data":"2"}]"#.parse::<P2PKSecret>().is_err());
+    }
+
+    #[test]
+    fn test_sign_and_verify_witness() -> anyhow::Result<()> {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1; 32])?;
+        let other_key = SecretKey::from_slice(&[2; 32])?;
+        let p2pk_secret = P2PKSecret::new(secret_key.public_key(&secp));
+        let secret = p2pk_secret.to_string();
+
+        let witness = P2PKWitness::sign(&secret, &secret_key);
+        assert!(p2pk_secret.verify_witness(&secret, &witness.to_string().parse()?));
+        assert!(!p2pk_secret.verify_witness(&secret, &P2PKWitness::sign(&secret, &other_key)));
+        Ok(())
+    }
+}
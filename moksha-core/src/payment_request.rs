@@ -0,0 +1,225 @@
+//! This module defines the `PaymentRequest` struct for requesting a payment in ecash as described in [Nut-18](https://github.com/cashubtc/nuts/blob/main/18.md)
+//!
+//! A `PaymentRequest` is serialized as base64 encoded CBOR with the prefix `creqA`. The sender pays it by sending a
+//! `PaymentRequestPayload` with proofs of one of the accepted mints over one of the transports of the request.
+
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+
+use crate::{
+    error::MokshaCoreError,
+    primitives::CurrencyUnit,
+    proof::Proofs,
+    token::{deserialize_mint_url, serialize_mint_url},
+};
+
+const PAYMENT_REQUEST_PREFIX: &str = "creqA";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaymentRequest {
+    /// id that the receiver uses to match the payload with the request
+    #[serde(rename = "i", default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<String>,
+    /// requested amount. If not set the sender chooses the amount
+    #[serde(rename = "a", default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+    #[serde(rename = "u", default, skip_serializing_if = "Option::is_none")]
+    pub currency_unit: Option<CurrencyUnit>,
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub single_use: Option<bool>,
+    /// mints the receiver accepts proofs from. If empty proofs of any mint are accepted
+    #[serde(
+        rename = "m",
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_mint_urls",
+        deserialize_with = "deserialize_mint_urls"
+    )]
+    pub mints: Vec<Url>,
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "t", default)]
+    pub transports: Vec<Transport>,
+}
+
+/// Describes how the sender delivers the `PaymentRequestPayload` to the receiver
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transport {
+    #[serde(rename = "t")]
+    pub kind: TransportKind,
+    /// target of the transport, e.g. the url for `post`
+    #[serde(rename = "a")]
+    pub target: String,
+    #[serde(rename = "g", default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<Vec<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    Nostr,
+    /// json payload is sent as HTTP POST request to the target url
+    Post,
+}
+
+impl Transport {
+    pub fn http_post(url: &Url) -> Self {
+        Self {
+            kind: TransportKind::Post,
+            target: url.to_string(),
+            tags: None,
+        }
+    }
+}
+
+/// Payload that the sender of a payment sends to the receiver
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaymentRequestPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(
+        serialize_with = "serialize_mint_url",
+        deserialize_with = "deserialize_mint_url"
+    )]
+    pub mint: Url,
+    pub unit: CurrencyUnit,
+    pub proofs: Proofs,
+}
+
+fn serialize_mint_urls<S>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    urls.iter()
+        .map(|url| url.as_str().trim_end_matches('/'))
+        .collect::<Vec<_>>()
+        .serialize(serializer)
+}
+
+fn deserialize_mint_urls<'de, D>(deserializer: D) -> Result<Vec<Url>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|url| Url::parse(url).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+impl PaymentRequest {
+    /// Returns true if the request accepts proofs of the mint
+    pub fn accepts_mint(&self, mint_url: &Url) -> bool {
+        self.mints.is_empty() || self.mints.contains(mint_url)
+    }
+
+    /// Returns the first transport of the given kind
+    pub fn transport(&self, kind: TransportKind) -> Option<&Transport> {
+        self.transports
+            .iter()
+            .find(|transport| transport.kind == kind)
+    }
+
+    pub fn serialize(&self) -> Result<String, MokshaCoreError> {
+        let mut cbor = vec![];
+        ciborium::into_writer(&self, &mut cbor)
+            .map_err(|e| MokshaCoreError::CborError(e.to_string()))?;
+        Ok(format!(
+            "{}{}",
+            PAYMENT_REQUEST_PREFIX,
+            general_purpose::URL_SAFE.encode(cbor)
+        ))
+    }
+
+    pub fn deserialize(data: impl Into<String>) -> Result<Self, MokshaCoreError> {
+        let data = data.into();
+        let request = data
+            .strip_prefix(PAYMENT_REQUEST_PREFIX)
+            .ok_or(MokshaCoreError::InvalidPaymentRequest)?;
+
+        let cbor = general_purpose::URL_SAFE_NO_PAD
+            .decode(request.as_bytes())
+            .or_else(|_| general_purpose::URL_SAFE.decode(request.as_bytes()))
+            .map_err(|_| MokshaCoreError::InvalidPaymentRequest)?;
+
+        ciborium::from_reader(cbor.as_slice())
+            .map_err(|e| MokshaCoreError::CborError(e.to_string()))
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let request = self.serialize().map_err(|_| fmt::Error)?;
+        write!(f, "{request}")
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use url::Url;
+
+    use super::{PaymentRequest, Transport, TransportKind};
+    use crate::primitives::CurrencyUnit;
+
+    #[test]
+    fn test_deserialize_payment_request() -> anyhow::Result<()> {
+        // example from Nut-18
+        let request: PaymentRequest = "creqApWF0gaNhdGVub3N0cmFheKlucHJvZmlsZTFxeTI4d3VtbjhnaGo3dW45ZDNzaGp0bnl2OWtoMnVld2Q5aHN6OW1od2RlbjV0ZTB3ZmprY2N0ZTljdXJ4dmVuOWVlaHFjdHJ2NWhzenJ0aHdkZW41dGUwZGVoaHh0bnZkYWtxcWd5ZGFxeTdjdXJrNDM5eWtwdGt5c3Y3dWRoZGh1NjhzdWNtMjk1YWtxZWZkZWhrZjBkNDk1Y3d1bmw1YWeBgmFuYjE3YWloYjdhOTAxNzZhYQphdWNzYXRhbYF4Imh0dHBzOi8vbm9mZWVzLnRlc3RudXQuY2FzaHUuc3BhY2U=".parse()?;
+        assert_eq!(Some("b7a90176".to_owned()), request.payment_id);
+        assert_eq!(Some(10), request.amount);
+        assert_eq!(Some(CurrencyUnit::Sat), request.currency_unit);
+        assert_eq!(
+            vec![Url::parse("https://nofees.testnut.cashu.space")?],
+            request.mints
+        );
+        assert_eq!(TransportKind::Nostr, request.transports[0].kind);
+        assert_eq!(
+            Some(vec![vec!["n".to_owned(), "17".to_owned()]]),
+            request.transports[0].tags
+        );
+        assert!(request.transport(TransportKind::Post).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_payment_request_roundtrip() -> anyhow::Result<()> {
+        let request = PaymentRequest {
+            payment_id: Some("4840f51e".to_owned()),
+            amount: Some(21),
+            currency_unit: Some(CurrencyUnit::Sat),
+            mints: vec![Url::parse("http://127.0.0.1:3338")?],
+            description: Some("coffee".to_owned()),
+            transports: vec![Transport::http_post(&Url::parse(
+                "https://example.com/pay",
+            )?)],
+            ..Default::default()
+        };
+        let serialized = request.to_string();
+        assert!(serialized.starts_with("creqA"));
+        assert_eq!(request, serialized.parse()?);
+
+        assert!(request.accepts_mint(&Url::parse("http://127.0.0.1:3338")?));
+        assert!(!request.accepts_mint(&Url::parse("http://127.0.0.1:3339")?));
+        assert!(PaymentRequest::default().accepts_mint(&Url::parse("http://127.0.0.1:3339")?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_invalid_payment_request() {
+        assert!("cashuBo2F0".parse::<PaymentRequest>().is_err());
+        assert!("creqA!!!".parse::<PaymentRequest>().is_err());
+    }
+}
@@ -42,6 +42,50 @@ pub struct PostSwapResponse {
     pub signatures: Vec<BlindedSignature>,
 }
 
+/// Request to restore the signatures of previously signed outputs as described in [Nut-09](https://github.com/cashubtc/nuts/blob/main/09.md)
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostRestoreRequest {
+    pub outputs: Vec<BlindedMessage>,
+}
+
+/// Contains only the outputs the mint has signed and their signatures in the same order
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostRestoreResponse {
+    pub outputs: Vec<BlindedMessage>,
+    pub signatures: Vec<BlindedSignature>,
+}
+
+/// Request to check the state of proofs as described in [Nut-07](https://github.com/cashubtc/nuts/blob/main/07.md)
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PostCheckStateRequest {
+    #[serde(rename = "Ys")]
+    #[schema(value_type = Vec<String>)]
+    pub ys: Vec<PublicKey>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, ToSchema)]
+pub struct PostCheckStateResponse {
+    pub states: Vec<ProofStateResponse>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct ProofStateResponse {
+    #[serde(rename = "Y")]
+    #[schema(value_type = String)]
+    pub y: PublicKey,
+    pub state: SpentState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SpentState {
+    Unspent,
+    Pending,
+    Spent,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct CashuErrorResponse {
     pub code: u64,
@@ -156,6 +200,31 @@ pub struct PostMeltQuoteBolt11Request {
     /// payment request
     pub request: String,
     pub unit: CurrencyUnit,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<MeltQuoteBolt11Options>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct MeltQuoteBolt11Options {
+    /// Multi-path payment (NUT-15)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mpp: Option<Mpp>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct Mpp {
+    /// amount of the partial payment in millisatoshis
+    pub amount: u64,
+}
+
+impl MeltQuoteBolt11Options {
+    pub fn mpp(amount_msat: u64) -> Self {
+        Self {
+            mpp: Some(Mpp {
+                amount: amount_msat,
+            }),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
@@ -183,6 +252,8 @@ pub struct Bolt11MeltQuote {
     pub payment_request: String,
     pub expiry: u64,
     pub paid: bool,
+    /// amount in msat of a partial payment (NUT-15), `None` if the whole invoice is paid
+    pub partial_amount_msat: Option<u64>,
 }
 
 impl From<Bolt11MeltQuote> for PostMeltQuoteBolt11Response {
@@ -220,11 +291,51 @@ pub struct MintInfoResponse {
     pub version: Option<String>,
     pub description: Option<String>,
     pub description_long: Option<String>,
-    pub contact: Option<Vec<Vec<String>>>,
+    pub contact: Option<Vec<ContactInfo>>,
     pub motd: Option<String>,
+    pub icon_url: Option<String>,
+    pub urls: Option<Vec<String>>,
+    /// unix timestamp of the mint
+    pub time: Option<u64>,
     pub nuts: Nuts,
 }
 
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ContactInfo {
+    pub method: String,
+    pub info: String,
+}
+
+impl ContactInfo {
+    pub fn new(method: impl Into<String>, info: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            info: info.into(),
+        }
+    }
+}
+
+/// Accepts the legacy `["email", "contact@me.com"]` format of older mints as well
+impl<'de> Deserialize<'de> for ContactInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawContactInfo {
+            Pair { method: String, info: String },
+            Legacy(String, String),
+        }
+
+        Ok(match RawContactInfo::deserialize(deserializer)? {
+            RawContactInfo::Pair { method, info } | RawContactInfo::Legacy(method, info) => {
+                Self { method, info }
+            }
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BtcOnchainMintQuote {
     pub quote_id: Uuid,
@@ -365,6 +476,10 @@ pub struct Nuts {
     /// DLEQ proofs
     pub nut12: Option<Nut12>,
 
+    #[serde(rename = "15", skip_serializing_if = "Option::is_none")]
+    /// Partial multi-path payments
+    pub nut15: Option<Nut15>,
+
     #[serde(rename = "17", skip_serializing_if = "Option::is_none")]
     /// minting tokens btc onchain
     pub nut17: Option<Nut17>,
@@ -385,6 +500,7 @@ impl Default for Nuts {
             nut10: Some(Nut10 { supported: false }),
             nut11: Some(Nut11 { supported: false }),
             nut12: Some(Nut12 { supported: false }),
+            nut15: None,
             nut17: Some(Nut17::default()),
             nut18: Some(Nut18::default()),
         }
@@ -469,6 +585,37 @@ pub struct Nut12 {
     pub supported: bool,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct Nut15 {
+    pub methods: Vec<MppMethodConfig>,
+}
+
+impl Nut15 {
+    pub fn supports(&self, payment_method: &PaymentMethod, unit: &CurrencyUnit) -> bool {
+        self.methods
+            .iter()
+            .any(|m| &m.payment_method == payment_method && &m.unit == unit)
+    }
+}
+
+impl Default for Nut15 {
+    fn default() -> Self {
+        Self {
+            methods: vec![MppMethodConfig {
+                payment_method: PaymentMethod::Bolt11,
+                unit: CurrencyUnit::Sat,
+            }],
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct MppMethodConfig {
+    #[serde(rename = "method")]
+    pub payment_method: PaymentMethod,
+    pub unit: CurrencyUnit,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
 pub struct Nut17 {
     pub supported: bool,
@@ -536,7 +683,11 @@ mod tests {
     use crate::{
         dhke::public_key_from_hex,
         fixture::read_fixture,
-        primitives::{KeyResponse, MintInfoResponse, Nuts, PostSwapResponse},
+        primitives::{
+            ContactInfo, CurrencyUnit, KeyResponse, MeltQuoteBolt11Options, MintInfoResponse,
+            Nut15, Nuts, PaymentMethod, PostCheckStateResponse, PostMeltQuoteBolt11Request,
+            PostSwapResponse, SpentState,
+        },
     };
 
     #[test]
@@ -547,6 +698,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_deserialize_check_state_response() -> anyhow::Result<()> {
+        let response = serde_json::from_str::<PostCheckStateResponse>(
+            r#"{"states": [
+                {"Y": "02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee", "state": "SPENT", "witness": null},
+                {"Y": "0286a3d9cb3e49a6dc2f8b4df5d8b7ea8b1fbbd1bd79e5e24b9d2ac4a9b9e3c1a4", "state": "UNSPENT"}
+            ]}"#,
+        )?;
+        assert_eq!(2, response.states.len());
+        assert_eq!(SpentState::Spent, response.states[0].state);
+        assert_eq!(SpentState::Unspent, response.states[1].state);
+        assert_eq!(None, response.states[1].witness);
+        Ok(())
+    }
+
     #[test]
     fn test_serialize_keyresponse() -> anyhow::Result<()> {
         let response = KeyResponse {
@@ -570,16 +736,36 @@ mod tests {
             description: Some("The short mint description".to_string()),
             description_long: Some("A description that can be a long piece of text.".to_string()),
             contact: Some(vec![
-                vec!["email".to_string(), "contact@me.com".to_string()],
-                vec!["twitter".to_string(), "@me".to_string()],
-                vec!["nostr".to_string(), "npub...".to_string()],
+                ContactInfo::new("email", "contact@me.com"),
+                ContactInfo::new("twitter", "@me"),
+                ContactInfo::new("nostr", "npub..."),
             ]),
             nuts: Nuts::default(),
             motd: Some("Message to display to users.".to_string()),
+            icon_url: Some("https://mint.host/icon.jpg".to_string()),
+            urls: Some(vec!["https://mint.host".to_string()]),
+            time: Some(1_725_304_480),
         };
         let out = serde_json::to_string_pretty(&mint_info)?;
         assert!(!out.is_empty());
         assert!(out.contains("02a9acc1e48c25eeeb9289b5031cc57da9fe72f3fe2861d264bdc074209b107ba2"));
+        assert!(out.contains("\"method\": \"email\""));
+        assert!(out.contains("\"time\": 1725304480"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_contact_info() -> anyhow::Result<()> {
+        let contacts = serde_json::from_str::<Vec<ContactInfo>>(
+            r#"[{"method": "email", "info": "contact@me.com"}, ["nostr", "npub..."]]"#,
+        )?;
+        assert_eq!(
+            contacts,
+            vec![
+                ContactInfo::new("email", "contact@me.com"),
+                ContactInfo::new("nostr", "npub...")
+            ]
+        );
         Ok(())
     }
 
@@ -590,6 +776,10 @@ mod tests {
         assert!(info.is_ok());
         let info = info?;
         assert_eq!("Nutshell/0.15.0", info.version.unwrap());
+        assert_eq!(
+            Some(ContactInfo::new("email", "contact@me.com")),
+            info.contact.and_then(|contact| contact.first().cloned())
+        );
         Ok(())
     }
 
@@ -608,4 +798,44 @@ mod tests {
         assert!(info.nuts.nut12.is_none());
         Ok(())
     }
+
+    #[test]
+    fn test_serialize_melt_quote_request_mpp() -> anyhow::Result<()> {
+        let request = PostMeltQuoteBolt11Request {
+            request: "lnbc".to_owned(),
+            unit: CurrencyUnit::Sat,
+            options: None,
+        };
+        assert_eq!(
+            "{\"request\":\"lnbc\",\"unit\":\"sat\"}",
+            serde_json::to_string(&request)?
+        );
+
+        let request = PostMeltQuoteBolt11Request {
+            options: Some(MeltQuoteBolt11Options::mpp(50_000)),
+            ..request
+        };
+        let serialized = serde_json::to_string(&request)?;
+        assert_eq!(
+            "{\"request\":\"lnbc\",\"unit\":\"sat\",\"options\":{\"mpp\":{\"amount\":50000}}}",
+            serialized
+        );
+        let deserialized = serde_json::from_str::<PostMeltQuoteBolt11Request>(&serialized)?;
+        assert_eq!(
+            Some(MeltQuoteBolt11Options::mpp(50_000)),
+            deserialized.options
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_nut15() -> anyhow::Result<()> {
+        let nut15 = serde_json::from_str::<Nut15>(
+            "{\"methods\":[{\"method\":\"bolt11\",\"unit\":\"sat\"}]}",
+        )?;
+        assert_eq!(Nut15::default(), nut15);
+        assert!(nut15.supports(&PaymentMethod::Bolt11, &CurrencyUnit::Sat));
+        assert!(!nut15.supports(&PaymentMethod::BtcOnchain, &CurrencyUnit::Sat));
+        Ok(())
+    }
 }
@@ -10,12 +10,12 @@
 //!
 //! Both the `Proof` and `Proofs` structs are serializable and deserializable using serde.
 
-use secp256k1::PublicKey;
+use secp256k1::{PublicKey, SecretKey};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use utoipa::ToSchema;
 
-use crate::{error::MokshaCoreError, keyset::KeysetId};
+use crate::{coin_selection::CoinSelector, dhke::Dhke, error::MokshaCoreError, keyset::KeysetId};
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
@@ -28,6 +28,9 @@ pub struct Proof {
     #[schema(value_type = String)]
     pub c: PublicKey,
     pub script: Option<P2SHScript>,
+    pub dleq: Option<ProofDleq>,
+    /// witness for spending conditions as described in [Nut-11](https://github.com/cashubtc/nuts/blob/main/11.md)
+    pub witness: Option<String>,
 }
 
 impl Proof {
@@ -38,8 +41,27 @@ impl Proof {
             c,
             keyset_id: id,
             script: None,
+            dleq: None,
+            witness: None,
         }
     }
+
+    /// Returns `Y = hash_to_curve(secret)`, which identifies the proof when checking its state ([Nut-07](https://github.com/cashubtc/nuts/blob/main/07.md))
+    pub fn y(&self) -> Result<PublicKey, MokshaCoreError> {
+        Dhke::hash_to_curve(self.secret.as_bytes())
+    }
+}
+
+/// DLEQ proof of a signature, as described in [Nut-12](https://github.com/cashubtc/nuts/blob/main/12.md)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct ProofDleq {
+    #[schema(value_type = String)]
+    pub e: SecretKey,
+    #[schema(value_type = String)]
+    pub s: SecretKey,
+    /// blinding factor of the signature
+    #[schema(value_type = String)]
+    pub r: SecretKey,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
@@ -86,28 +108,9 @@ impl Proofs {
             .into()
     }
 
+    /// Selects proofs for `amount` without input fees, see [`CoinSelector`] for details
     pub fn proofs_for_amount(&self, amount: u64) -> Result<Self, MokshaCoreError> {
-        let mut all_proofs = self.0.clone();
-        if amount > self.total_amount() {
-            return Err(MokshaCoreError::NotEnoughTokens);
-        }
-
-        all_proofs.sort_by(|a, b| a.amount.cmp(&b.amount));
-
-        let mut selected_proofs = vec![];
-        let mut selected_amount = 0;
-
-        while selected_amount < amount {
-            if all_proofs.is_empty() {
-                break;
-            }
-
-            let proof = all_proofs.pop().expect("proofs is empty");
-            selected_amount += proof.amount;
-            selected_proofs.push(proof);
-        }
-
-        Ok(selected_proofs.into())
+        Ok(CoinSelector::new().select(self, amount)?.proofs)
     }
 }
 
@@ -155,7 +158,7 @@ mod tests {
         let token: TokenV3 = fixture.try_into()?;
 
         let result = token.proofs().proofs_for_amount(10)?;
-        assert_eq!(32, result.total_amount());
+        assert_eq!(16, result.total_amount());
         assert_eq!(1, result.len());
         Ok(())
     }
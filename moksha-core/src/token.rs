@@ -1,6 +1,8 @@
 //! This module defines the `Token` struct, which is used for representing tokens in Cashu as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
 //!
 //! The `Token` struct represents a token, with an optional `mint` field for the URL of the Mint and a `proofs` field for the proofs associated with the token.
+//!
+//! `TokenV3` is serialized as base64 encoded JSON with the prefix `cashuA`, `TokenV4` as base64 encoded CBOR with the prefix `cashuB`.
 
 use std::str::FromStr;
 
@@ -9,9 +11,16 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::skip_serializing_none;
 use url::Url;
 
-use crate::{error::MokshaCoreError, primitives::CurrencyUnit, proof::Proofs};
+use secp256k1::{PublicKey, SecretKey};
+
+use crate::{
+    error::MokshaCoreError,
+    primitives::CurrencyUnit,
+    proof::{Proof, ProofDleq, Proofs},
+};
 
 const TOKEN_PREFIX_V3: &str = "cashuA";
+const TOKEN_PREFIX_V4: &str = "cashuB";
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -107,8 +116,13 @@ impl TokenV3 {
         ))
     }
 
+    /// Deserializes a `cashuA` token. `cashuB` tokens are converted to V3.
     pub fn deserialize(data: impl Into<String>) -> Result<Self, MokshaCoreError> {
         let data = data.into();
+        if data.starts_with(TOKEN_PREFIX_V4) {
+            return TokenV4::deserialize(data)?.try_into();
+        }
+
         let token = data
             .strip_prefix(TOKEN_PREFIX_V3)
             .ok_or(MokshaCoreError::InvalidTokenPrefix)?;
@@ -179,6 +193,230 @@ impl From<(Url, CurrencyUnit, Proofs)> for TokenV3 {
     }
 }
 
+/// Compact token of a single mint, that is serialized as CBOR as described in [Nut-00](https://github.com/cashubtc/nuts/blob/main/00.md)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenV4 {
+    #[serde(rename = "t")]
+    pub tokens: Vec<TokenV4Keyset>,
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(
+        rename = "m",
+        serialize_with = "serialize_mint_url",
+        deserialize_with = "deserialize_mint_url"
+    )]
+    pub mint: Url,
+    #[serde(rename = "u")]
+    pub currency_unit: CurrencyUnit,
+}
+
+/// Proofs of a single keyset in a `TokenV4`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenV4Keyset {
+    #[serde(rename = "i", with = "serde_bytes")]
+    pub keyset_id: Vec<u8>,
+    #[serde(rename = "p")]
+    pub proofs: Vec<ProofV4>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofV4 {
+    #[serde(rename = "a")]
+    pub amount: u64,
+    #[serde(rename = "s")]
+    pub secret: String,
+    #[serde(rename = "c", with = "serde_bytes")]
+    pub c: Vec<u8>,
+    #[serde(rename = "d", default, skip_serializing_if = "Option::is_none")]
+    pub dleq: Option<ProofDleqV4>,
+    #[serde(rename = "w", default, skip_serializing_if = "Option::is_none")]
+    pub witness: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProofDleqV4 {
+    #[serde(with = "serde_bytes")]
+    pub e: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub s: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub r: Vec<u8>,
+}
+
+pub(crate) fn deserialize_mint_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let url_str = String::deserialize(deserializer)?;
+    Url::parse(&url_str).map_err(serde::de::Error::custom)
+}
+
+pub(crate) fn serialize_mint_url<S>(url: &Url, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serialize_url(&Some(url.to_owned()), serializer)
+}
+
+impl TokenV4 {
+    pub fn total_amount(&self) -> u64 {
+        self.tokens
+            .iter()
+            .flat_map(|token| token.proofs.iter())
+            .map(|proof| proof.amount)
+            .sum()
+    }
+
+    pub fn serialize(&self) -> Result<String, MokshaCoreError> {
+        let mut cbor = vec![];
+        ciborium::into_writer(&self, &mut cbor)
+            .map_err(|e| MokshaCoreError::CborError(e.to_string()))?;
+        Ok(format!(
+            "{}{}",
+            TOKEN_PREFIX_V4,
+            general_purpose::URL_SAFE.encode(cbor)
+        ))
+    }
+
+    /// Deserializes a `cashuB` token. `cashuA` tokens are converted to V4.
+    pub fn deserialize(data: impl Into<String>) -> Result<Self, MokshaCoreError> {
+        let data = data.into();
+        if data.starts_with(TOKEN_PREFIX_V3) {
+            return TokenV3::deserialize(data)?.try_into();
+        }
+
+        let token = data
+            .strip_prefix(TOKEN_PREFIX_V4)
+            .ok_or(MokshaCoreError::InvalidTokenPrefix)?;
+
+        let cbor = general_purpose::URL_SAFE_NO_PAD
+            .decode(token.as_bytes())
+            .or_else(|_| general_purpose::URL_SAFE.decode(token.as_bytes()))
+            .map_err(|_| MokshaCoreError::InvalidToken)?;
+
+        ciborium::from_reader(cbor.as_slice())
+            .map_err(|e| MokshaCoreError::CborError(e.to_string()))
+    }
+}
+
+impl TryFrom<TokenV3> for TokenV4 {
+    type Error = MokshaCoreError;
+
+    fn try_from(token: TokenV3) -> Result<Self, Self::Error> {
+        let mint = token.mint().ok_or(MokshaCoreError::InvalidTokenV4(
+            "token has no mint".to_owned(),
+        ))?;
+        if token.tokens.iter().any(|t| t.mint.as_ref() != Some(&mint)) {
+            return Err(MokshaCoreError::InvalidTokenV4(
+                "token has proofs of multiple mints".to_owned(),
+            ));
+        }
+
+        let mut tokens: Vec<TokenV4Keyset> = vec![];
+        for proof in token.proofs().proofs() {
+            // only keyset ids in hex format are supported
+            let keyset_id =
+                hex::decode(&proof.keyset_id).map_err(|_| MokshaCoreError::InvalidKeysetid)?;
+            let proof = ProofV4::from(proof);
+            match tokens.iter_mut().find(|t| t.keyset_id == keyset_id) {
+                Some(keyset) => keyset.proofs.push(proof),
+                None => tokens.push(TokenV4Keyset {
+                    keyset_id,
+                    proofs: vec![proof],
+                }),
+            }
+        }
+
+        Ok(Self {
+            tokens,
+            memo: token.memo,
+            mint,
+            currency_unit: token.currency_unit.unwrap_or(CurrencyUnit::Sat),
+        })
+    }
+}
+
+impl TryFrom<TokenV4> for TokenV3 {
+    type Error = MokshaCoreError;
+
+    fn try_from(token: TokenV4) -> Result<Self, Self::Error> {
+        let proofs = token
+            .tokens
+            .iter()
+            .flat_map(|keyset| {
+                let keyset_id = hex::encode(&keyset.keyset_id);
+                keyset
+                    .proofs
+                    .iter()
+                    .map(move |proof| proof.to_proof(keyset_id.clone()))
+            })
+            .collect::<Result<Vec<Proof>, MokshaCoreError>>()?;
+
+        Ok(Self {
+            tokens: vec![Token {
+                mint: Some(token.mint),
+                proofs: proofs.into(),
+            }],
+            memo: token.memo,
+            currency_unit: Some(token.currency_unit),
+        })
+    }
+}
+
+impl From<Proof> for ProofV4 {
+    fn from(proof: Proof) -> Self {
+        Self {
+            amount: proof.amount,
+            secret: proof.secret,
+            c: proof.c.serialize().to_vec(),
+            dleq: proof.dleq.map(|dleq| ProofDleqV4 {
+                e: dleq.e.secret_bytes().to_vec(),
+                s: dleq.s.secret_bytes().to_vec(),
+                r: dleq.r.secret_bytes().to_vec(),
+            }),
+            witness: proof.witness,
+        }
+    }
+}
+
+impl ProofV4 {
+    fn to_proof(&self, keyset_id: String) -> Result<Proof, MokshaCoreError> {
+        let dleq = match &self.dleq {
+            Some(dleq) => Some(ProofDleq {
+                e: SecretKey::from_slice(&dleq.e)?,
+                s: SecretKey::from_slice(&dleq.s)?,
+                r: SecretKey::from_slice(&dleq.r)?,
+            }),
+            None => None,
+        };
+        Ok(Proof {
+            amount: self.amount,
+            keyset_id,
+            secret: self.secret.clone(),
+            c: PublicKey::from_slice(&self.c)?,
+            script: None,
+            dleq,
+            witness: self.witness.clone(),
+        })
+    }
+}
+
+impl TryFrom<TokenV4> for String {
+    type Error = MokshaCoreError;
+
+    fn try_from(token: TokenV4) -> Result<Self, Self::Error> {
+        token.serialize()
+    }
+}
+
+impl FromStr for TokenV4 {
+    type Err = MokshaCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -190,10 +428,11 @@ mod tests {
         dhke,
         fixture::read_fixture,
         primitives::CurrencyUnit,
-        proof::Proof,
-        token::{Token, TokenV3},
+        proof::{Proof, ProofDleq},
+        token::{Token, TokenV3, TokenV4},
     };
     use pretty_assertions::assert_eq;
+    use secp256k1::SecretKey;
 
     #[test]
     fn test_token_v3() -> anyhow::Result<()> {
@@ -281,6 +520,8 @@ mod tests {
                 ),
                 keyset_id: "someid".to_string(),
                 script: None,
+                dleq: None,
+                witness: None,
             }
             .into(),
         };
@@ -336,6 +577,72 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_token_v4_deserialize() -> anyhow::Result<()> {
+        // example from Nut-00
+        let input = "cashuBpGF0gaJhaUgArSaMTR9YJmFwgaNhYQFhc3hAOWE2ZGJiODQ3YmQyMzJiYTc2ZGIwZGYxOTcyMTZiMjlkM2I4Y2MxNDU1M2NkMjc4MjdmYzFjYzk0MmZlZGI0ZWFjWCEDhhhUP_trhpXfStS6vN6So0qWvc2X3O4NfM-Y1HISZ5JhZGlUaGFuayB5b3VhbXVodHRwOi8vbG9jYWxob3N0OjMzMzhhdWNzYXQ=";
+        let token = TokenV4::from_str(input)?;
+        assert_eq!(token.mint, Url::parse("http://localhost:3338")?);
+        assert_eq!(token.currency_unit, CurrencyUnit::Sat);
+        assert_eq!(token.memo, Some("Thank you".to_string()));
+        assert_eq!(token.total_amount(), 1);
+        assert_eq!(input, token.serialize()?);
+
+        let v3 = TokenV3::from_str(input)?;
+        let proof = &v3.proofs().proofs()[0];
+        assert_eq!(proof.keyset_id, "00ad268c4d1f5826");
+        assert_eq!(
+            proof.secret,
+            "9a6dbb847bd232ba76db0df197216b29d3b8cc14553cd27827fc1cc942fedb4e"
+        );
+        assert_eq!(
+            proof.c.to_string(),
+            "038618543ffb6b8695df4ad4babcde92a34a96bdcd97dcee0d7ccf98d472126792"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_v4_from_v3() -> anyhow::Result<()> {
+        let v3 = TokenV3::from_str(&read_fixture("token_nut_example.cashu")?)?;
+        let v4: TokenV4 = v3.clone().try_into()?;
+        assert_eq!(v4.tokens.len(), 1);
+        assert_eq!(v4.tokens[0].proofs.len(), 2);
+        assert_eq!(hex::encode(&v4.tokens[0].keyset_id), "009a1f293253e41e");
+
+        let serialized = v4.serialize()?;
+        assert!(serialized.starts_with("cashuB"));
+        assert_eq!(v3, TokenV3::from_str(&serialized)?);
+        assert_eq!(v4, TokenV4::from_str(&v3.serialize()?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_v4_with_dleq_and_witness() -> anyhow::Result<()> {
+        let mut v3 = TokenV3::from_str(&read_fixture("token_nut_example.cashu")?)?;
+        let mut proof = v3.tokens[0].proofs.proofs()[0].clone();
+        let scalar = SecretKey::from_slice(&[1; 32])?;
+        proof.dleq = Some(ProofDleq {
+            e: scalar,
+            s: scalar,
+            r: scalar,
+        });
+        proof.witness = Some("{\"signatures\":[]}".to_string());
+        v3.tokens[0].proofs = proof.into();
+
+        let v4: TokenV4 = v3.clone().try_into()?;
+        assert_eq!(v3, TokenV3::deserialize(v4.serialize()?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_v4_legacy_keyset_id() -> anyhow::Result<()> {
+        let v3 = TokenV3::deserialize(read_fixture("token_60.cashu")?)?;
+        let result: Result<TokenV4, _> = v3.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_empty_token() -> anyhow::Result<()> {
         let tokens = TokenV3::empty();
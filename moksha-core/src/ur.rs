@@ -0,0 +1,580 @@
+//! This module defines the `UrEncoder` and `UrDecoder` for [BC-UR](https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md),
+//! which splits large messages (e.g. tokens) into multiple parts that can be shown as an animated QR code.
+//!
+//! A token is encoded as CBOR byte string with the UR type `bytes`, the same way other cashu wallets encode their
+//! animated QR codes. Each part `ur:bytes/<seq>-<count>/<bytewords>` contains a CBOR encoded fragment of the message
+//! in [Bytewords](https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-012-bytewords.md).
+//! After the first `count` parts, the encoder emits fountain coded parts that combine several fragments, so a
+//! decoder can reassemble the message even if it missed some parts.
+
+use bitcoin_hashes::{sha256, Hash};
+use ciborium::value::Value;
+
+use crate::error::MokshaCoreError;
+
+const UR_PREFIX: &str = "ur:";
+const UR_TYPE: &str = "bytes";
+
+const BYTEWORDS: &str = "ableacidalsoapexaquaarchatomauntawayaxisbackbaldbarnbeltbetabiasbluebodybragbrewbulbbuzzcalmcashcatschefcityclawcodecolacookcostcruxcurlcuspcyandarkdatadaysdelidicedietdoordowndrawdropdrumdulldutyeacheasyechoedgeepicevenexamexiteyesfactfairfernfigsfilmfishfizzflapflewfluxfoxyfreefrogfuelfundgalagamegeargemsgiftgirlglowgoodgraygrimgurugushgyrohalfhanghardhawkheathelphighhillholyhopehornhutsicedideaidleinchinkyintoirisironitemjadejazzjoinjoltjowljudojugsjumpjunkjurykeepkenokeptkeyskickkilnkingkitekiwiknoblamblavalazyleaflegsliarlimplionlistlogoloudloveluaulucklungmainmanymathmazememomenumeowmildmintmissmonknailnavyneednewsnextnoonnotenumbobeyoboeomitonyxopenovalowlspaidpartpeckplaypluspoempoolposepuffpumapurrquadquizraceramprealredorichroadrockroofrubyruinrunsrustsafesagascarsetssilkskewslotsoapsolosongstubsurfswantacotasktaxitenttiedtimetinytoiltombtoystriptunatwinuglyundouniturgeuservastveryvetovialvibeviewvisavoidvowswallwandwarmwaspwavewaxywebswhatwhenwhizwolfworkyankyawnyellyogayurtzapszerozestzinczonezoom";
+
+/// Creates the parts of a UR encoded message. The first `fragment_count` parts contain one fragment each, the
+/// parts after that are fountain coded.
+#[derive(Debug, Clone)]
+pub struct UrEncoder {
+    fragments: Vec<Vec<u8>>,
+    message_len: usize,
+    checksum: u32,
+    seq: u32,
+}
+
+impl UrEncoder {
+    /// Splits the message into fragments of at most `max_fragment_len` bytes
+    pub fn new(message: &str, max_fragment_len: usize) -> Result<Self, MokshaCoreError> {
+        let cbor = to_cbor(&Value::Bytes(message.as_bytes().to_vec()))?;
+        let fragment_count = cbor.len().div_ceil(max_fragment_len.max(1));
+        let fragment_len = cbor.len().div_ceil(fragment_count);
+
+        let fragments = cbor
+            .chunks(fragment_len)
+            .map(|chunk| {
+                let mut fragment = chunk.to_vec();
+                fragment.resize(fragment_len, 0);
+                fragment
+            })
+            .collect();
+
+        Ok(Self {
+            fragments,
+            message_len: cbor.len(),
+            checksum: crc32(&cbor),
+            seq: 0,
+        })
+    }
+
+    pub fn fragment_count(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// Returns the next part. There is no last part, the encoder keeps creating fountain coded parts.
+    pub fn next_part(&mut self) -> Result<String, MokshaCoreError> {
+        self.seq = self.seq.wrapping_add(1).max(1);
+        let fragment = choose_fragments(self.seq, self.fragments.len(), self.checksum)
+            .into_iter()
+            .fold(vec![0; self.fragments[0].len()], |mixed, index| {
+                xor(&mixed, &self.fragments[index])
+            });
+
+        let part = to_cbor(&Value::Array(vec![
+            self.seq.into(),
+            (self.fragments.len() as u64).into(),
+            (self.message_len as u64).into(),
+            self.checksum.into(),
+            Value::Bytes(fragment),
+        ]))?;
+        Ok(format!(
+            "{UR_PREFIX}{UR_TYPE}/{}-{}/{}",
+            self.seq,
+            self.fragments.len(),
+            encode_bytewords(&part)
+        ))
+    }
+}
+
+/// Reassembles a message from scanned parts. Parts can be received in any order and more than once.
+#[derive(Debug, Clone, Default)]
+pub struct UrDecoder {
+    header: Option<(usize, usize, u32)>,
+    fragments: Vec<Option<Vec<u8>>>,
+    /// fountain coded parts that still contain more than one missing fragment
+    mixed: Vec<(Vec<usize>, Vec<u8>)>,
+    message: Option<Vec<u8>>,
+}
+
+impl UrDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn receive(&mut self, part: &str) -> Result<(), MokshaCoreError> {
+        let (sequence, payload) = parse_part(part)?;
+        let cbor = decode_bytewords(payload)?;
+
+        let Some(sequence) = sequence else {
+            self.message = Some(cbor);
+            return Ok(());
+        };
+
+        let (seq, fragment_count, message_len, checksum, fragment) = parse_fragment(&cbor)?;
+        if sequence != (seq, fragment_count) || fragment_count == 0 || seq == 0 {
+            return Err(invalid_part(part));
+        }
+
+        match self.header {
+            Some(header) if header != (fragment_count, message_len, checksum) => {
+                return Err(MokshaCoreError::InvalidUr(
+                    "part belongs to a different message".to_owned(),
+                ));
+            }
+            Some(_) => {}
+            None => {
+                if message_len > fragment_count * fragment.len() {
+                    return Err(invalid_part(part));
+                }
+                self.header = Some((fragment_count, message_len, checksum));
+                self.fragments = vec![None; fragment_count];
+            }
+        }
+        if self
+            .fragments
+            .iter()
+            .flatten()
+            .any(|f| f.len() != fragment.len())
+        {
+            return Err(invalid_part(part));
+        }
+
+        let indexes = choose_fragments(seq, fragment_count, checksum);
+        self.add_part(indexes, fragment);
+        self.join_fragments()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// Returns the number of received and expected fragments
+    pub fn progress(&self) -> (usize, usize) {
+        if self.fragments.is_empty() && self.message.is_some() {
+            return (1, 1);
+        }
+        (
+            self.fragments.iter().filter(|f| f.is_some()).count(),
+            self.fragments.len(),
+        )
+    }
+
+    /// Returns the message once all fragments are received
+    pub fn message(&self) -> Result<Option<String>, MokshaCoreError> {
+        let Some(cbor) = &self.message else {
+            return Ok(None);
+        };
+
+        let value: Value = ciborium::from_reader(cbor.as_slice())
+            .map_err(|e| MokshaCoreError::InvalidUr(e.to_string()))?;
+        match value {
+            Value::Bytes(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|e| MokshaCoreError::InvalidUr(e.to_string())),
+            _ => Err(MokshaCoreError::InvalidUr(
+                "message is not a byte string".to_owned(),
+            )),
+        }
+    }
+
+    /// Removes the known fragments from the part. Parts that contain a single fragment afterwards reduce the
+    /// other mixed parts in turn.
+    fn add_part(&mut self, indexes: Vec<usize>, fragment: Vec<u8>) {
+        let mut queue = vec![(indexes, fragment)];
+        while let Some((indexes, fragment)) = queue.pop() {
+            let (indexes, fragment) = self.reduce(indexes, fragment);
+            match indexes.as_slice() {
+                [] => {}
+                [index] => {
+                    self.fragments[*index] = Some(fragment);
+                    let mixed = std::mem::take(&mut self.mixed);
+                    queue.extend(mixed);
+                }
+                _ => {
+                    if !self.mixed.iter().any(|(known, _)| known == &indexes) {
+                        self.mixed.push((indexes, fragment));
+                    }
+                }
+            }
+        }
+    }
+
+    fn reduce(&self, indexes: Vec<usize>, fragment: Vec<u8>) -> (Vec<usize>, Vec<u8>) {
+        indexes.into_iter().fold(
+            (vec![], fragment),
+            |(mut remaining, fragment), index| match &self.fragments[index] {
+                Some(known) => (remaining, xor(&fragment, known)),
+                None => {
+                    remaining.push(index);
+                    (remaining, fragment)
+                }
+            },
+        )
+    }
+
+    fn join_fragments(&mut self) -> Result<(), MokshaCoreError> {
+        let Some((_, message_len, checksum)) = self.header else {
+            return Ok(());
+        };
+        if self.message.is_some() || self.fragments.iter().any(Option::is_none) {
+            return Ok(());
+        }
+
+        let mut message = self
+            .fragments
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        message.truncate(message_len);
+        if crc32(&message) != checksum {
+            return Err(MokshaCoreError::InvalidUr("invalid checksum".to_owned()));
+        }
+        self.message = Some(message);
+        Ok(())
+    }
+}
+
+fn invalid_part(part: &str) -> MokshaCoreError {
+    MokshaCoreError::InvalidUr(format!("invalid part {part}"))
+}
+
+/// sequence number and count of a part
+type Sequence = (u32, usize);
+
+/// Returns the sequence of a multipart UR and the bytewords payload
+fn parse_part(part: &str) -> Result<(Option<Sequence>, &str), MokshaCoreError> {
+    // QR code scanners return the UR in uppercase, because it fits into the alphanumeric mode of QR codes
+    let ur_type = format!("{UR_PREFIX}{UR_TYPE}/");
+    if !part
+        .get(..ur_type.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&ur_type))
+    {
+        return Err(invalid_part(part));
+    }
+
+    match part[ur_type.len()..].split_once('/') {
+        None => Ok((None, &part[ur_type.len()..])),
+        Some((sequence, payload)) => {
+            let (seq, count) = sequence
+                .split_once('-')
+                .and_then(|(seq, count)| Some((seq.parse().ok()?, count.parse().ok()?)))
+                .ok_or_else(|| invalid_part(part))?;
+            Ok((Some((seq, count)), payload))
+        }
+    }
+}
+
+/// Parses `[seq, fragment count, message length, checksum, fragment]`
+fn parse_fragment(cbor: &[u8]) -> Result<(u32, usize, usize, u32, Vec<u8>), MokshaCoreError> {
+    let invalid = || MokshaCoreError::InvalidUr("invalid fragment".to_owned());
+    let value: Value = ciborium::from_reader(cbor).map_err(|_| invalid())?;
+    let integer = |value: &Value| {
+        value
+            .as_integer()
+            .and_then(|i| u64::try_from(i).ok())
+            .ok_or_else(invalid)
+    };
+
+    match value.as_array().map(Vec::as_slice) {
+        Some([seq, count, message_len, checksum, Value::Bytes(fragment)]) => Ok((
+            u32::try_from(integer(seq)?).map_err(|_| invalid())?,
+            usize::try_from(integer(count)?).map_err(|_| invalid())?,
+            usize::try_from(integer(message_len)?).map_err(|_| invalid())?,
+            u32::try_from(integer(checksum)?).map_err(|_| invalid())?,
+            fragment.clone(),
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+fn to_cbor(value: &Value) -> Result<Vec<u8>, MokshaCoreError> {
+    let mut cbor = vec![];
+    ciborium::into_writer(value, &mut cbor)
+        .map_err(|e| MokshaCoreError::CborError(e.to_string()))?;
+    Ok(cbor)
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+/// Encodes the data and its checksum as minimal bytewords (first and last letter of each word)
+fn encode_bytewords(data: &[u8]) -> String {
+    let words = BYTEWORDS.as_bytes();
+    data.iter()
+        .chain(crc32(data).to_be_bytes().iter())
+        .flat_map(|byte| {
+            let word = &words[*byte as usize * 4..*byte as usize * 4 + 4];
+            [word[0] as char, word[3] as char]
+        })
+        .collect()
+}
+
+fn decode_bytewords(encoded: &str) -> Result<Vec<u8>, MokshaCoreError> {
+    let invalid = || MokshaCoreError::InvalidUr("invalid bytewords".to_owned());
+    let words = BYTEWORDS.as_bytes();
+    let encoded = encoded.to_ascii_lowercase();
+    if encoded.len() % 2 != 0 {
+        return Err(invalid());
+    }
+
+    let bytes = encoded
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            words
+                .chunks(4)
+                .position(|word| word[0] == pair[0] && word[3] == pair[1])
+                .map(|byte| byte as u8)
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    if bytes.len() < 4 {
+        return Err(invalid());
+    }
+    let (data, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32(data).to_be_bytes() != checksum {
+        return Err(MokshaCoreError::InvalidUr("invalid checksum".to_owned()));
+    }
+    Ok(data.to_vec())
+}
+
+/// Returns the indexes of the fragments that are combined in the part with the sequence number `seq`
+fn choose_fragments(seq: u32, fragment_count: usize, checksum: u32) -> Vec<usize> {
+    if seq as usize <= fragment_count {
+        return vec![seq as usize - 1];
+    }
+
+    let mut rng = Xoshiro256::from_seed(&[seq.to_be_bytes(), checksum.to_be_bytes()].concat());
+    let degree = choose_degree(fragment_count, &mut rng);
+    let mut remaining = (0..fragment_count).collect::<Vec<_>>();
+    let mut shuffled = Vec::with_capacity(fragment_count);
+    while !remaining.is_empty() {
+        let index = rng.next_int(0, remaining.len() as u64 - 1) as usize;
+        shuffled.push(remaining.remove(index));
+    }
+    shuffled.truncate(degree);
+    shuffled
+}
+
+/// Chooses how many fragments are combined. Lower degrees are more likely (1/degree).
+fn choose_degree(fragment_count: usize, rng: &mut Xoshiro256) -> usize {
+    let weights = (1..=fragment_count)
+        .map(|i| 1.0 / i as f64)
+        .collect::<Vec<_>>();
+    WeightedSampler::new(&weights).next(rng) + 1
+}
+
+/// Walker-Vose alias method, as used by the reference implementation
+struct WeightedSampler {
+    probs: Vec<f64>,
+    aliases: Vec<usize>,
+}
+
+impl WeightedSampler {
+    fn new(weights: &[f64]) -> Self {
+        let count = weights.len();
+        let sum = weights.iter().sum::<f64>();
+        let mut weights = weights
+            .iter()
+            .map(|w| w * count as f64 / sum)
+            .collect::<Vec<_>>();
+
+        let (mut small, mut large) = (vec![], vec![]);
+        for (i, weight) in weights.iter().enumerate().rev() {
+            if *weight < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        let mut probs = vec![0.0; count];
+        let mut aliases = vec![0; count];
+        while !small.is_empty() && !large.is_empty() {
+            let (a, g) = (
+                small.pop().unwrap_or_default(),
+                large.pop().unwrap_or_default(),
+            );
+            probs[a] = weights[a];
+            aliases[a] = g;
+            weights[g] += weights[a] - 1.0;
+            if weights[g] < 1.0 {
+                small.push(g);
+            } else {
+                large.push(g);
+            }
+        }
+        for i in large.into_iter().chain(small) {
+            probs[i] = 1.0;
+        }
+        Self { probs, aliases }
+    }
+
+    fn next(&self, rng: &mut Xoshiro256) -> usize {
+        let r1 = rng.next_double();
+        let r2 = rng.next_double();
+        let i = (self.probs.len() as f64 * r1) as usize;
+        if r2 < self.probs[i] {
+            i
+        } else {
+            self.aliases[i]
+        }
+    }
+}
+
+/// xoshiro256** seeded with the SHA-256 hash of the seed
+struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    fn from_seed(seed: &[u8]) -> Self {
+        let digest = sha256::Hash::hash(seed).to_byte_array();
+        let mut s = [0u64; 4];
+        for (i, chunk) in digest.chunks(8).enumerate() {
+            s[i] = u64::from_be_bytes(chunk.try_into().unwrap_or_default());
+        }
+        Self { s }
+    }
+
+    fn next(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    fn next_double(&mut self) -> f64 {
+        self.next() as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    fn next_int(&mut self, low: u64, high: u64) -> u64 {
+        (self.next_double() * (high - low + 1) as f64) as u64 + low
+    }
+}
+
+/// CRC-32 (IEEE) as used by BC-UR
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixture::read_fixture,
+        ur::{crc32, decode_bytewords, encode_bytewords, UrDecoder, UrEncoder, Xoshiro256},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn test_bytewords() -> anyhow::Result<()> {
+        // example from bcr-2020-012
+        let encoded = encode_bytewords(&[0, 1, 2, 128, 255]);
+        assert_eq!("aeadaolazmjendeoti", encoded);
+        assert_eq!(vec![0, 1, 2, 128, 255], decode_bytewords(&encoded)?);
+        assert_eq!(
+            vec![0, 1, 2, 128, 255],
+            decode_bytewords(&encoded.to_uppercase())?
+        );
+        assert!(decode_bytewords("aeadaolazmjendeotu").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xoshiro() {
+        // test vector of the reference implementation
+        let mut rng = Xoshiro256::from_seed(b"Wolf");
+        let numbers = (0..10).map(|_| rng.next() % 100).collect::<Vec<_>>();
+        assert_eq!(vec![42, 81, 85, 8, 82, 84, 76, 73, 70, 88], numbers);
+    }
+
+    #[test]
+    fn test_encode_decode_single_part() -> anyhow::Result<()> {
+        let mut encoder = UrEncoder::new("cashuAabc", 100)?;
+        assert_eq!(1, encoder.fragment_count());
+        let part = encoder.next_part()?;
+        assert!(part.starts_with("ur:bytes/1-1/"));
+
+        let mut decoder = UrDecoder::new();
+        decoder.receive(&part.to_uppercase())?;
+        assert_eq!(Some("cashuAabc".to_owned()), decoder.message()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_decode_fragments() -> anyhow::Result<()> {
+        let token = read_fixture("token_60.cashu")?;
+        let mut encoder = UrEncoder::new(&token, 50)?;
+        let count = encoder.fragment_count();
+        assert!(count > 1);
+        let parts = (0..count * 3)
+            .map(|_| encoder.next_part())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(parts[0].starts_with(&format!("ur:bytes/1-{count}/")));
+
+        let mut decoder = UrDecoder::new();
+        for part in parts[..count].iter().rev() {
+            assert_eq!(None, decoder.message()?);
+            decoder.receive(part)?;
+        }
+        // duplicates are ignored
+        decoder.receive(&parts[0])?;
+        assert!(decoder.is_complete());
+        assert_eq!((count, count), decoder.progress());
+        assert_eq!(Some(token), decoder.message()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_with_missing_parts() -> anyhow::Result<()> {
+        let token = read_fixture("token_60.cashu")?;
+        let mut encoder = UrEncoder::new(&token, 30)?;
+        let count = encoder.fragment_count();
+
+        // the first parts are lost, the decoder recovers them from the fountain coded parts
+        let mut decoder = UrDecoder::new();
+        for _ in 0..count * 10 {
+            let part = encoder.next_part()?;
+            if part.starts_with("ur:bytes/1-") || part.starts_with("ur:bytes/2-") {
+                continue;
+            }
+            decoder.receive(&part)?;
+            if decoder.is_complete() {
+                break;
+            }
+        }
+        assert_eq!(Some(token), decoder.message()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_invalid_parts() -> anyhow::Result<()> {
+        let mut decoder = UrDecoder::new();
+        assert!(decoder.receive("cashuAabc").is_err());
+        assert!(decoder.receive("ur:bytes/3-2/aeadaolazmjendeoti").is_err());
+
+        let mut first = UrEncoder::new("first message", 5)?;
+        let mut other = UrEncoder::new("other message", 5)?;
+        decoder.receive(&first.next_part()?)?;
+        other.next_part()?;
+        assert!(decoder.receive(&other.next_part()?).is_err());
+        assert_eq!((1, first.fragment_count()), decoder.progress());
+        Ok(())
+    }
+}
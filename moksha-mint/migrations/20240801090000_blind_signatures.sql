@@ -0,0 +1,16 @@
+-- every signature the mint issues, used to compute the outstanding ecash
+CREATE TABLE blind_signatures (
+    b_ TEXT NOT NULL PRIMARY KEY,
+    c_ TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    keyset_id TEXT NOT NULL
+);
+
+-- the signatures of the proofs spent before this migration are unknown, so these proofs don't count against
+-- the outstanding ecash
+CREATE TABLE outstanding_ecash_baseline (
+    spent_amount BIGINT NOT NULL
+);
+
+INSERT INTO outstanding_ecash_baseline (spent_amount)
+SELECT COALESCE(SUM(amount), 0) FROM used_proofs;
@@ -0,0 +1,3 @@
+-- amount in msat of a partial payment (NUT-15). NULL if the quote pays the whole invoice
+ALTER TABLE bolt11_melt_quotes
+ADD COLUMN partial_amount_msat BIGINT;
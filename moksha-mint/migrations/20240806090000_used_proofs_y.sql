@@ -0,0 +1,17 @@
+-- Y = hash_to_curve(secret) of a proof to look up its state (Nut-07). The Ys of proofs used before this
+-- migration are set by the mint on startup
+ALTER TABLE used_proofs
+ADD COLUMN y TEXT,
+ADD COLUMN witness TEXT;
+
+CREATE UNIQUE INDEX used_proofs_y ON used_proofs (y);
+
+-- inputs of melts whose payment is in flight
+CREATE TABLE pending_proofs (
+    y TEXT NOT NULL PRIMARY KEY,
+    amount BIGINT NOT NULL,
+    secret TEXT NOT NULL,
+    c TEXT NOT NULL,
+    keyset_id TEXT NOT NULL,
+    witness TEXT
+);
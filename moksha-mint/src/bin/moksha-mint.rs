@@ -35,6 +35,8 @@ pub async fn main() -> anyhow::Result<()> {
         lightning_backend,
         tracing,
         database,
+        metrics,
+        limits,
     } = MintConfig::read_config_with_defaults();
 
     init_tracing(tracing.clone())?;
@@ -49,6 +51,8 @@ pub async fn main() -> anyhow::Result<()> {
         .with_btc_onchain(btconchain_backend)
         .with_fee(Some(lightning_fee))
         .with_tracing(tracing)
+        .with_metrics(Some(metrics))
+        .with_limits(Some(limits))
         .build()
         .await;
 
@@ -2,7 +2,7 @@ use std::{env, net::SocketAddr, path::PathBuf, str::FromStr};
 
 use clap::Parser;
 use moksha_core::primitives::{
-    CurrencyUnit, Nut17, Nut18, PaymentMethod, PaymentMethodConfigBtcOnchain,
+    CurrencyUnit, PaymentMethod, PaymentMethodConfig, PaymentMethodConfigBtcOnchain,
 };
 use serde::{Deserialize, Serialize};
 
@@ -26,6 +26,8 @@ pub struct Opts {
     pub server: ServerConfig,
     #[clap(flatten)]
     pub database: DatabaseConfig,
+    #[clap(flatten)]
+    pub limits: AmountLimitsConfig,
 
     #[clap(long, env = "MINT_LIGHTNING_BACKEND")]
     pub lightning_backend: LightningTypeVariant,
@@ -35,6 +37,9 @@ pub struct Opts {
 
     #[clap(flatten)]
     pub tracing: Option<TracingConfig>,
+
+    #[clap(flatten)]
+    pub metrics: MetricsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +72,13 @@ pub struct TracingConfig {
     pub endpoint: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, Parser)]
+pub struct MetricsConfig {
+    /// serve prometheus metrics on /metrics
+    #[clap(long, default_value_t = false, env = "MINT_METRICS_ENABLED")]
+    pub enabled: bool,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MintConfig {
     pub privatekey: String,
@@ -78,6 +90,8 @@ pub struct MintConfig {
     pub lightning_backend: Option<LightningType>,
     pub tracing: Option<TracingConfig>,
     pub database: DatabaseConfig,
+    pub metrics: MetricsConfig,
+    pub limits: AmountLimitsConfig,
 }
 
 impl From<(Opts, LightningType, Option<BtcOnchainConfig>)> for MintConfig {
@@ -92,6 +106,8 @@ impl From<(Opts, LightningType, Option<BtcOnchainConfig>)> for MintConfig {
             lightning_backend: Some(ln),
             tracing: opts.tracing,
             database: opts.database,
+            metrics: opts.metrics,
+            limits: opts.limits,
         }
     }
 }
@@ -135,6 +151,8 @@ impl MintConfig {
         btconchain_backend: Option<BtcOnchainConfig>,
         lightning_backend: Option<LightningType>,
         tracing: Option<TracingConfig>,
+        metrics: MetricsConfig,
+        limits: AmountLimitsConfig,
     ) -> Self {
         Self {
             privatekey: private_key,
@@ -146,6 +164,8 @@ impl MintConfig {
             lightning_backend,
             tracing,
             database,
+            metrics,
+            limits,
         }
     }
 }
@@ -161,20 +181,6 @@ pub struct BtcOnchainConfig {
         env = "MINT_BTC_ONCHAIN_BACKEND_MIN_CONFIRMATIONS"
     )]
     pub min_confirmations: u8,
-
-    #[clap(
-        long,
-        default_value_t = 10_000,
-        env = "MINT_BTC_ONCHAIN_BACKEND_MIN_AMOUNT"
-    )]
-    pub min_amount: u64,
-
-    #[clap(
-        long,
-        default_value_t = 1_000_000,
-        env = "MINT_BTC_ONCHAIN_BACKEND_MAX_AMOUNT"
-    )]
-    pub max_amount: u64,
 }
 
 impl Default for BtcOnchainConfig {
@@ -182,8 +188,6 @@ impl Default for BtcOnchainConfig {
         Self {
             onchain_type: None,
             min_confirmations: 1,
-            min_amount: 10_000,
-            max_amount: 1_000_000,
         }
     }
 }
@@ -209,30 +213,102 @@ impl FromStr for BtcOnchainTypeVariant {
     }
 }
 
-impl From<BtcOnchainConfig> for Nut17 {
-    fn from(settings: BtcOnchainConfig) -> Self {
+/// Amount limits of a payment method and unit, parsed from `method:unit:min:max` e.g. `bolt11:sat:1:10000000`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmountLimit {
+    pub payment_method: PaymentMethod,
+    pub unit: CurrencyUnit,
+    pub min_amount: u64,
+    pub max_amount: u64,
+}
+
+impl FromStr for AmountLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let [payment_method, unit, min_amount, max_amount] = parts[..] else {
+            return Err(format!(
+                "invalid amount limit {s}. Expected method:unit:min:max"
+            ));
+        };
+
+        let payment_method = match payment_method {
+            "bolt11" => PaymentMethod::Bolt11,
+            "btconchain" => PaymentMethod::BtcOnchain,
+            _ => return Err(format!("unknown payment method {payment_method}")),
+        };
+        let unit = match unit {
+            "sat" => CurrencyUnit::Sat,
+            "msat" => CurrencyUnit::MSat,
+            "usd" => CurrencyUnit::Usd,
+            _ => return Err(format!("unknown unit {unit}")),
+        };
+        let min_amount = min_amount
+            .parse()
+            .map_err(|_| format!("invalid min amount {min_amount}"))?;
+        let max_amount = max_amount
+            .parse()
+            .map_err(|_| format!("invalid max amount {max_amount}"))?;
+        if min_amount > max_amount {
+            return Err(format!(
+                "min amount {min_amount} is above max amount {max_amount}"
+            ));
+        }
+
+        Ok(Self {
+            payment_method,
+            unit,
+            min_amount,
+            max_amount,
+        })
+    }
+}
+
+impl From<&AmountLimit> for PaymentMethodConfig {
+    fn from(limit: &AmountLimit) -> Self {
         Self {
-            supported: true,
-            payment_methods: vec![PaymentMethodConfigBtcOnchain {
-                payment_method: PaymentMethod::BtcOnchain,
-                unit: CurrencyUnit::Sat,
-                min_amount: settings.min_amount,
-                max_amount: settings.max_amount,
-            }],
+            payment_method: limit.payment_method.clone(),
+            unit: limit.unit.clone(),
+            min_amount: Some(limit.min_amount),
+            max_amount: Some(limit.max_amount),
         }
     }
 }
 
-impl From<BtcOnchainConfig> for Nut18 {
-    fn from(settings: BtcOnchainConfig) -> Self {
+impl From<&AmountLimit> for PaymentMethodConfigBtcOnchain {
+    fn from(limit: &AmountLimit) -> Self {
         Self {
-            supported: true,
-            payment_methods: vec![PaymentMethodConfigBtcOnchain {
-                payment_method: PaymentMethod::BtcOnchain,
-                unit: CurrencyUnit::Sat,
-                min_amount: settings.min_amount,
-                max_amount: settings.max_amount,
-            }],
+            payment_method: limit.payment_method.clone(),
+            unit: limit.unit.clone(),
+            min_amount: limit.min_amount,
+            max_amount: limit.max_amount,
+        }
+    }
+}
+
+const DEFAULT_AMOUNT_LIMITS: &str = "bolt11:sat:1:10000000,btconchain:sat:10000:1000000";
+
+/// Amount limits for minting and melting. Payment methods and units without a limit are not supported
+#[derive(Debug, Clone, Parser)]
+pub struct AmountLimitsConfig {
+    /// comma separated list of mint limits as method:unit:min:max
+    #[clap(long, value_delimiter = ',', default_value = DEFAULT_AMOUNT_LIMITS, env = "MINT_MINT_LIMITS")]
+    pub mint_limits: Vec<AmountLimit>,
+    /// comma separated list of melt limits as method:unit:min:max
+    #[clap(long, value_delimiter = ',', default_value = DEFAULT_AMOUNT_LIMITS, env = "MINT_MELT_LIMITS")]
+    pub melt_limits: Vec<AmountLimit>,
+}
+
+impl Default for AmountLimitsConfig {
+    fn default() -> Self {
+        let limits = DEFAULT_AMOUNT_LIMITS
+            .split(',')
+            .map(|limit| limit.parse().expect("invalid default amount limit"))
+            .collect::<Vec<AmountLimit>>();
+        Self {
+            mint_limits: limits.clone(),
+            melt_limits: limits,
         }
     }
 }
@@ -263,6 +339,18 @@ pub struct ServerConfig {
     pub serve_wallet_path: Option<PathBuf>,
     #[clap(long, env = "MINT_API_PREFIX")]
     pub api_prefix: Option<String>,
+    /// max requests per minute and client ip. Rate limiting is disabled if not set
+    #[clap(long, env = "MINT_RATE_LIMIT_PER_MINUTE")]
+    pub rate_limit_per_minute: Option<u32>,
+    /// max size of a request body in bytes
+    #[clap(long, default_value_t = 1_048_576, env = "MINT_MAX_REQUEST_SIZE")]
+    pub max_request_size: usize,
+    /// max number of proofs in a swap or melt request
+    #[clap(long, default_value_t = 1_000, env = "MINT_MAX_INPUTS")]
+    pub max_inputs: usize,
+    /// max number of blinded messages in a swap, mint or melt request
+    #[clap(long, default_value_t = 1_000, env = "MINT_MAX_OUTPUTS")]
+    pub max_outputs: usize,
 }
 
 impl Default for ServerConfig {
@@ -271,6 +359,10 @@ impl Default for ServerConfig {
             host_port: "[::]:3338".to_string().parse().expect("invalid host port"),
             serve_wallet_path: None,
             api_prefix: None,
+            rate_limit_per_minute: None,
+            max_request_size: 1_048_576,
+            max_inputs: 1_000,
+            max_outputs: 1_000,
         }
     }
 }
@@ -299,7 +391,13 @@ pub struct MintInfoConfig {
 
     #[clap(long, env = "MINT_INFO_MOTD")]
     pub motd: Option<String>,
-    // FIXME add missing fields for v1/info endpoint nut4/nut5 payment_methods, nut4 disabled flag
+
+    #[clap(long, env = "MINT_INFO_ICON_URL")]
+    pub icon_url: Option<String>,
+
+    /// comma separated list of urls the mint is reachable at
+    #[clap(long, env = "MINT_INFO_URLS", value_delimiter = ',')]
+    pub urls: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -364,3 +462,27 @@ impl Default for LightningFeeConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::primitives::{CurrencyUnit, PaymentMethod};
+
+    use super::{AmountLimit, AmountLimitsConfig};
+
+    #[test]
+    fn test_parse_amount_limit() -> anyhow::Result<()> {
+        let limit = "btconchain:usd:10:20"
+            .parse::<AmountLimit>()
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(PaymentMethod::BtcOnchain, limit.payment_method);
+        assert_eq!(CurrencyUnit::Usd, limit.unit);
+        assert_eq!((10, 20), (limit.min_amount, limit.max_amount));
+
+        assert!("bolt11:sat:1".parse::<AmountLimit>().is_err());
+        assert!("bolt12:sat:1:2".parse::<AmountLimit>().is_err());
+        assert!("bolt11:eur:1:2".parse::<AmountLimit>().is_err());
+        assert!("bolt11:sat:2:1".parse::<AmountLimit>().is_err());
+        assert_eq!(2, AmountLimitsConfig::default().mint_limits.len());
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use async_trait::async_trait;
 use moksha_core::{
+    blind::{BlindedMessage, BlindedSignature},
     primitives::{Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote, BtcOnchainMintQuote},
     proof::Proofs,
 };
+use secp256k1::PublicKey;
 use uuid::Uuid;
 
 use crate::{error::MokshaMintError, model::Invoice};
@@ -13,9 +15,12 @@ pub mod postgres;
 pub trait Database {
     type DB: sqlx::Database;
     async fn begin_tx(&self) -> Result<sqlx::Transaction<Self::DB>, sqlx::Error>;
+
+    /// Returns the used proofs with the given Ys
     async fn get_used_proofs(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
     ) -> Result<Proofs, MokshaMintError>;
     async fn add_used_proofs(
         &self,
@@ -23,6 +28,48 @@ pub trait Database {
         proofs: &Proofs,
     ) -> Result<(), MokshaMintError>;
 
+    /// Returns the pending proofs with the given Ys
+    async fn get_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError>;
+
+    /// Marks the proofs as pending while a payment with them is in flight
+    async fn add_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError>;
+    async fn delete_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError>;
+
+    /// Stores the signatures the mint issued for the blinded messages
+    async fn add_blind_signatures(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        blinded_messages: &[BlindedMessage],
+        signatures: &[BlindedSignature],
+    ) -> Result<(), MokshaMintError>;
+
+    /// Returns the stored signatures of the blinded messages together with the `B_` of each message
+    async fn get_blind_signatures(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<Vec<(PublicKey, BlindedSignature)>, MokshaMintError>;
+
+    /// Returns the amount of all issued signatures minus the amount of all used proofs. Signatures issued before
+    /// they were stored are unknown, so proofs spent before that are ignored and the amount is a lower bound that is
+    /// never negative.
+    async fn get_outstanding_amount(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<i64, MokshaMintError>;
+
     async fn get_pending_invoice(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
@@ -1,13 +1,15 @@
 #![allow(clippy::blocks_in_conditions)]
 use async_trait::async_trait;
 use moksha_core::{
-    dhke,
+    blind::{BlindedMessage, BlindedSignature},
+    dhke::{self, Dhke},
     primitives::{
         Bolt11MeltQuote, Bolt11MintQuote, BtcOnchainMeltQuote, BtcOnchainMintQuote, CurrencyUnit,
     },
     proof::{Proof, Proofs},
 };
 
+use secp256k1::PublicKey;
 use sqlx::postgres::PgPoolOptions;
 use tracing::instrument;
 use uuid::Uuid;
@@ -31,11 +33,43 @@ impl PostgresDB {
         })
     }
 
+    /// Returns the current number of connections, idle connections and the maximum pool size.
+    pub fn pool_stats(&self) -> (u32, usize, u32) {
+        (
+            self.pool.size(),
+            self.pool.num_idle(),
+            self.pool.options().get_max_connections(),
+        )
+    }
+
     pub async fn migrate(&self) {
         sqlx::migrate!("./migrations")
             .run(&self.pool)
             .await
             .expect("Could not run migrations");
+        self.set_missing_used_proof_ys()
+            .await
+            .expect("Could not set the Ys of used proofs");
+    }
+
+    /// Sets Y of the proofs that were used before Ys were stored
+    async fn set_missing_used_proof_ys(&self) -> Result<(), MokshaMintError> {
+        let mut tx = self.pool.begin().await?;
+        let secrets = sqlx::query!("SELECT secret FROM used_proofs WHERE y IS NULL")
+            .fetch_all(&mut *tx)
+            .await?;
+        for row in secrets {
+            let y = Dhke::hash_to_curve(row.secret.as_bytes())?;
+            sqlx::query!(
+                "UPDATE used_proofs SET y = $1 WHERE secret = $2",
+                y.to_string(),
+                row.secret
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
     }
 }
 
@@ -51,19 +85,26 @@ impl Database for PostgresDB {
     async fn get_used_proofs(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
     ) -> Result<Proofs, MokshaMintError> {
-        let proofs = sqlx::query!("SELECT * FROM used_proofs")
-            .fetch_all(&mut **tx)
-            .await?
-            .into_iter()
-            .map(|row| Proof {
-                amount: row.amount as u64,
-                secret: row.secret,
-                c: dhke::public_key_from_hex(&row.c).to_owned(),
-                keyset_id: row.keyset_id,
-                script: None,
-            })
-            .collect::<Vec<Proof>>();
+        let ys = ys.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id, witness FROM used_proofs WHERE y = ANY($1)",
+            &ys
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            script: None,
+            dleq: None,
+            witness: row.witness,
+        })
+        .collect::<Vec<Proof>>();
 
         Ok(proofs.into())
     }
@@ -76,11 +117,13 @@ impl Database for PostgresDB {
     ) -> Result<(), MokshaMintError> {
         for proof in proofs.proofs() {
             sqlx::query!(
-                "INSERT INTO used_proofs (amount, secret, c, keyset_id) VALUES ($1, $2, $3, $4)",
+                "INSERT INTO used_proofs (amount, secret, c, keyset_id, y, witness) VALUES ($1, $2, $3, $4, $5, $6)",
                 proof.amount as i64,
                 proof.secret,
                 proof.c.to_string(),
-                proof.keyset_id.to_string()
+                proof.keyset_id.to_string(),
+                proof.y()?.to_string(),
+                proof.witness
             )
             .execute(&mut **tx)
             .await?;
@@ -89,6 +132,146 @@ impl Database for PostgresDB {
         Ok(())
     }
 
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        ys: &[PublicKey],
+    ) -> Result<Proofs, MokshaMintError> {
+        let ys = ys.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let proofs = sqlx::query!(
+            "SELECT amount, secret, c, keyset_id, witness FROM pending_proofs WHERE y = ANY($1)",
+            &ys
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| Proof {
+            amount: row.amount as u64,
+            secret: row.secret,
+            c: dhke::public_key_from_hex(&row.c).to_owned(),
+            keyset_id: row.keyset_id,
+            script: None,
+            dleq: None,
+            witness: row.witness,
+        })
+        .collect::<Vec<Proof>>();
+
+        Ok(proofs.into())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn add_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        for proof in proofs.proofs() {
+            sqlx::query!(
+                "INSERT INTO pending_proofs (y, amount, secret, c, keyset_id, witness) VALUES ($1, $2, $3, $4, $5, $6)",
+                proof.y()?.to_string(),
+                proof.amount as i64,
+                proof.secret,
+                proof.c.to_string(),
+                proof.keyset_id.to_string(),
+                proof.witness
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn delete_pending_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaMintError> {
+        let ys = proofs
+            .proofs()
+            .iter()
+            .map(|proof| proof.y().map(|y| y.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        sqlx::query!("DELETE FROM pending_proofs WHERE y = ANY($1)", &ys)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip_all, err)]
+    async fn add_blind_signatures(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        blinded_messages: &[BlindedMessage],
+        signatures: &[BlindedSignature],
+    ) -> Result<(), MokshaMintError> {
+        for (message, signature) in blinded_messages.iter().zip(signatures) {
+            sqlx::query!(
+                "INSERT INTO blind_signatures (b_, c_, amount, keyset_id) VALUES ($1, $2, $3, $4)",
+                message.b_.to_string(),
+                signature.c_.to_string(),
+                signature.amount as i64,
+                signature.id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, blinded_messages), err)]
+    async fn get_blind_signatures(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        blinded_messages: &[BlindedMessage],
+    ) -> Result<Vec<(PublicKey, BlindedSignature)>, MokshaMintError> {
+        let b_ = blinded_messages
+            .iter()
+            .map(|message| message.b_.to_string())
+            .collect::<Vec<_>>();
+        let signatures = sqlx::query!(
+            "SELECT b_, c_, amount, keyset_id FROM blind_signatures WHERE b_ = ANY($1)",
+            &b_
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                dhke::public_key_from_hex(&row.b_),
+                BlindedSignature {
+                    amount: row.amount as u64,
+                    c_: dhke::public_key_from_hex(&row.c_),
+                    id: row.keyset_id,
+                },
+            )
+        })
+        .collect();
+
+        Ok(signatures)
+    }
+
+    #[instrument(level = "debug", skip(self), err)]
+    async fn get_outstanding_amount(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<i64, MokshaMintError> {
+        let outstanding = sqlx::query_scalar!(
+            r#"SELECT GREATEST(
+                (SELECT COALESCE(SUM(amount), 0) FROM blind_signatures)
+                - (SELECT COALESCE(SUM(amount), 0) FROM used_proofs)
+                + (SELECT COALESCE(SUM(spent_amount), 0) FROM outstanding_ecash_baseline),
+                0)::BIGINT AS "outstanding!""#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(outstanding)
+    }
+
     #[instrument(level = "debug", skip(self))]
     async fn get_pending_invoice(
         &self,
@@ -217,7 +400,7 @@ impl Database for PostgresDB {
         key: &Uuid,
     ) -> Result<Bolt11MeltQuote, MokshaMintError> {
         let quote: Bolt11MeltQuote = sqlx::query!(
-            "SELECT id, payment_request, expiry, paid, amount, fee_reserve FROM bolt11_melt_quotes WHERE id = $1",
+            "SELECT id, payment_request, expiry, paid, amount, fee_reserve, partial_amount_msat FROM bolt11_melt_quotes WHERE id = $1",
             key
         )
         .map(|row| Bolt11MeltQuote {
@@ -227,6 +410,7 @@ impl Database for PostgresDB {
             paid: row.paid,
             amount: row.amount as u64,
             fee_reserve: row.fee_reserve as u64,
+            partial_amount_msat: row.partial_amount_msat.map(|amount| amount as u64),
         })
         .fetch_one(&mut **tx)
         .await?;
@@ -241,13 +425,14 @@ impl Database for PostgresDB {
         quote: &Bolt11MeltQuote,
     ) -> Result<(), MokshaMintError> {
         sqlx::query!(
-            "INSERT INTO bolt11_melt_quotes (id, payment_request, expiry, paid, amount, fee_reserve) VALUES ($1, $2, $3, $4, $5, $6)",
+            "INSERT INTO bolt11_melt_quotes (id, payment_request, expiry, paid, amount, fee_reserve, partial_amount_msat) VALUES ($1, $2, $3, $4, $5, $6, $7)",
             quote.quote_id,
             quote.payment_request,
             quote.expiry as i64,
             quote.paid,
             quote.amount as i64,
-            quote.fee_reserve as i64
+            quote.fee_reserve as i64,
+            quote.partial_amount_msat.map(|amount| amount as i64)
         )
         .execute(&mut **tx)
         .await?;
@@ -1,7 +1,7 @@
 use std::string::FromUtf8Error;
 
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -53,6 +53,9 @@ pub enum MokshaMintError {
     #[error("Proof already used {0}")]
     ProofAlreadyUsed(String),
 
+    #[error("Proof is pending {0}")]
+    ProofPending(String),
+
     #[error("{0}")]
     SwapAmountMismatch(String),
 
@@ -62,6 +65,9 @@ pub enum MokshaMintError {
     #[error("Invalid amount: {0}")]
     InvalidAmount(String),
 
+    #[error("Multi-path payments are not supported by the lightning backend")]
+    MppNotSupported,
+
     #[error("Lightning Error {0}")]
     Lightning(#[from] LightningError),
 
@@ -88,17 +94,41 @@ pub enum MokshaMintError {
 
     #[error("MokshaCoreError: {0}")]
     MokshaCore(#[from] moksha_core::error::MokshaCoreError),
+
+    #[error("Too many inputs. Max allowed {0}")]
+    TooManyInputs(usize),
+
+    #[error("Too many outputs. Max allowed {0}")]
+    TooManyOutputs(usize),
+
+    #[error("Request body too large. Max allowed {0} bytes")]
+    RequestTooLarge(usize),
+
+    #[error("Rate limit exceeded. Try again in {0} seconds")]
+    RateLimitExceeded(u64),
+
+    #[error("Task failed {0}")]
+    Join(#[from] tokio::task::JoinError),
 }
 
 impl IntoResponse for MokshaMintError {
     fn into_response(self) -> Response {
         event!(Level::ERROR, "error in mint: {:?}", self);
 
+        let (status, retry_after) = match self {
+            Self::RequestTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, None),
+            Self::RateLimitExceeded(secs) => (StatusCode::TOO_MANY_REQUESTS, Some(secs)),
+            _ => (StatusCode::BAD_REQUEST, None),
+        };
+
         let body = Json(json!({
             "code": 0,
             "detail": self.to_string(),
         }));
 
-        (StatusCode::BAD_REQUEST, body).into_response()
+        match retry_after {
+            Some(secs) => (status, [(RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
@@ -3,6 +3,8 @@ pub mod config;
 pub mod database;
 pub mod error;
 pub mod lightning;
+pub mod limits;
+pub mod metrics;
 pub mod mint;
 pub mod model;
 mod routes;
@@ -78,6 +78,11 @@ impl Lightning for AlbyLightning {
             .await
             .map_err(|err| MokshaMintError::PayInvoice(payment_request, err))
     }
+
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.client.make_get("balance").await?;
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -12,7 +12,7 @@ use crate::{
 };
 use tonic::transport::{Certificate, ClientTlsConfig, Identity};
 
-use super::Lightning;
+use super::{error::LightningError, Lightning};
 
 use secp256k1::rand;
 use std::fs::read;
@@ -167,6 +167,99 @@ impl Lightning for ClnLightning {
             total_fees: payment.amount_sent_msat.unwrap().msat - payment.amount_msat.unwrap().msat, // FIXME check if this is correct
         })
     }
+
+    /// Sends a single part of the payment along a route for `amount_msat`
+    async fn pay_partial_invoice(
+        &self,
+        payment_request: String,
+        amount_msat: u64,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        let invoice = self.decode_invoice(payment_request.clone()).await?;
+        let total_amount_msat = invoice
+            .amount_milli_satoshis()
+            .ok_or_else(|| MokshaMintError::InvalidAmount("invoice has no amount".to_owned()))?;
+        let payment_hash: &[u8] = invoice.payment_hash().as_ref();
+
+        let mut client = self
+            .client_lock()
+            .await
+            .map_err(MokshaMintError::ClnConnectError)?;
+        let route = client
+            .get_route(cln_grpc::pb::GetrouteRequest {
+                id: invoice.recover_payee_pub_key().serialize().to_vec(),
+                amount_msat: Some(Amount { msat: amount_msat }),
+                riskfactor: 10,
+                cltv: Some(invoice.min_final_cltv_expiry_delta() as u32),
+                fromid: None,
+                fuzzpercent: None,
+                exclude: vec![],
+                maxhops: None,
+            })
+            .await
+            .map_err(|err| MokshaMintError::ClnConnectError(err.into()))?
+            .into_inner()
+            .route;
+
+        let amount_sent_msat = route
+            .first()
+            .and_then(|hop| hop.amount_msat.as_ref())
+            .ok_or(LightningError::PaymentFailed)?
+            .msat;
+
+        client
+            .send_pay(cln_grpc::pb::SendpayRequest {
+                route: route
+                    .into_iter()
+                    .map(|hop| cln_grpc::pb::SendpayRoute {
+                        amount_msat: hop.amount_msat,
+                        id: hop.id,
+                        delay: hop.delay,
+                        channel: hop.channel,
+                    })
+                    .collect(),
+                payment_hash: payment_hash.to_vec(),
+                label: None,
+                amount_msat: Some(Amount {
+                    msat: total_amount_msat,
+                }),
+                bolt11: Some(payment_request),
+                payment_secret: Some(invoice.payment_secret().0.to_vec()),
+                partid: Some(1),
+                localinvreqid: None,
+                groupid: None,
+            })
+            .await
+            .map_err(|err| MokshaMintError::ClnConnectError(err.into()))?;
+
+        client
+            .wait_send_pay(cln_grpc::pb::WaitsendpayRequest {
+                payment_hash: payment_hash.to_vec(),
+                timeout: None,
+                partid: Some(1),
+                groupid: None,
+            })
+            .await
+            .map_err(|_| LightningError::PaymentFailed)?;
+
+        Ok(PayInvoiceResult {
+            payment_hash: hex::encode(payment_hash),
+            total_fees: (amount_sent_msat - amount_msat) / 1_000,
+        })
+    }
+
+    fn supports_mpp(&self) -> bool {
+        true
+    }
+
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.client_lock()
+            .await
+            .map_err(MokshaMintError::ClnConnectError)?
+            .getinfo(cln_grpc::pb::GetinfoRequest {})
+            .await
+            .map_err(|err| MokshaMintError::ClnConnectError(err.into()))?;
+        Ok(())
+    }
 }
 
 // mod tests {
@@ -89,6 +89,11 @@ impl Lightning for LnbitsLightning {
             .await
             .map_err(|err| MokshaMintError::PayInvoice(payment_request, err))
     }
+
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.client.make_get("api/v1/wallet").await?;
+        Ok(())
+    }
 }
 #[derive(Clone)]
 pub struct LNBitsClient {
@@ -18,7 +18,7 @@ use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 use tracing::{debug, instrument};
 use url::Url;
 
-use super::Lightning;
+use super::{error::LightningError, Lightning};
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, Parser)]
 pub struct LndLightningSettings {
@@ -156,4 +156,85 @@ impl Lightning for LndLightning {
             total_fees,
         })
     }
+
+    /// Sends a single htlc for `amount_msat` with an mpp record for the total invoice amount
+    #[instrument(skip(self), err)]
+    async fn pay_partial_invoice(
+        &self,
+        payment_request: String,
+        amount_msat: u64,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        let invoice = self.decode_invoice(payment_request).await?;
+        let total_amt_msat = invoice
+            .amount_milli_satoshis()
+            .ok_or_else(|| MokshaMintError::InvalidAmount("invoice has no amount".to_owned()))?;
+
+        let mut client = self.client_lock().await?;
+        let routes = client
+            .query_routes(fedimint_tonic_lnd::tonic::Request::new(
+                fedimint_tonic_lnd::lnrpc::QueryRoutesRequest {
+                    pub_key: invoice.recover_payee_pub_key().to_string(),
+                    amt_msat: amount_msat as i64,
+                    final_cltv_delta: invoice.min_final_cltv_expiry_delta() as i32,
+                    ..Default::default()
+                },
+            ))
+            .await?
+            .into_inner();
+
+        let mut route = routes
+            .routes
+            .into_iter()
+            .next()
+            .ok_or(LightningError::PaymentFailed)?;
+        let last_hop = route.hops.last_mut().ok_or(LightningError::PaymentFailed)?;
+        last_hop.mpp_record = Some(fedimint_tonic_lnd::lnrpc::MppRecord {
+            payment_addr: invoice.payment_secret().0.to_vec(),
+            total_amt_msat: total_amt_msat as i64,
+        });
+
+        let payment_hash: &[u8] = invoice.payment_hash().as_ref();
+        let payment_response = client
+            .send_to_route_sync(fedimint_tonic_lnd::tonic::Request::new(
+                fedimint_tonic_lnd::lnrpc::SendToRouteRequest {
+                    payment_hash: payment_hash.to_vec(),
+                    route: Some(route),
+                    ..Default::default()
+                },
+            ))
+            .await?
+            .into_inner();
+
+        if !payment_response.payment_error.is_empty() {
+            debug!(
+                "lnd partial payment failed: {}",
+                payment_response.payment_error
+            );
+            return Err(LightningError::PaymentFailed.into());
+        }
+
+        let total_fees = payment_response
+            .payment_route
+            .map_or(0, |route| route.total_fees_msat / 1_000) as u64;
+
+        Ok(PayInvoiceResult {
+            payment_hash: hex::encode(payment_hash),
+            total_fees,
+        })
+    }
+
+    fn supports_mpp(&self) -> bool {
+        true
+    }
+
+    #[instrument(skip(self), err)]
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.client_lock()
+            .await?
+            .get_info(fedimint_tonic_lnd::tonic::Request::new(
+                fedimint_tonic_lnd::lnrpc::GetInfoRequest {},
+            ))
+            .await?;
+        Ok(())
+    }
 }
@@ -56,6 +56,23 @@ pub trait Lightning: Send + Sync {
         payment_request: String,
     ) -> Result<PayInvoiceResult, MokshaMintError>;
 
+    /// Pays `amount_msat` of the invoice as one part of a multi-path payment (NUT-15)
+    async fn pay_partial_invoice(
+        &self,
+        _payment_request: String,
+        _amount_msat: u64,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        Err(MokshaMintError::MppNotSupported)
+    }
+
+    /// Returns true if the backend can pay partial invoices
+    fn supports_mpp(&self) -> bool {
+        false
+    }
+
+    /// Checks that the backend is reachable and the credentials are valid
+    async fn health_check(&self) -> Result<(), MokshaMintError>;
+
     async fn decode_invoice(&self, payment_request: String) -> Result<LNInvoice, MokshaMintError> {
         LNInvoice::from_str(&payment_request)
             .map_err(|err| MokshaMintError::DecodeInvoice(payment_request, err))
@@ -121,6 +121,11 @@ impl Lightning for StrikeLightning {
             total_fees: 0, // FIXME return fees for strike
         })
     }
+
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.client.make_get("v1/balances").await?;
+        Ok(())
+    }
 }
 
 fn format_as_uuid_string(bytes: &[u8]) -> String {
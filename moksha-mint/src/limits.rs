@@ -0,0 +1,172 @@
+//! Request limits for the mint http server.
+//!
+//! Rate limiting uses a fixed window per client ip. The client ip is taken from the
+//! connection, so requests without `ConnectInfo` (e.g. in tests) are not limited.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::MokshaMintError;
+
+/// Stale clients are only evicted once the map grows beyond this size
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    clients: Arc<Mutex<HashMap<IpAddr, (Instant, u32)>>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
+
+    /// Counts a request for the given ip. Returns the seconds until the window resets if the limit is exceeded.
+    pub fn check(&self, ip: IpAddr, now: Instant) -> Result<(), u64> {
+        let mut clients = self.clients.lock().expect("rate limiter lock poisoned");
+
+        if clients.len() > MAX_TRACKED_CLIENTS {
+            let window = self.window;
+            clients.retain(|_, (start, _)| now.duration_since(*start) < window);
+        }
+
+        let (start, count) = clients.entry(ip).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count >= self.max_requests {
+            let retry_after = self.window.saturating_sub(now.duration_since(*start));
+            return Err(retry_after.as_secs().max(1));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let Some(ConnectInfo(addr)) = connect_info {
+        if let Err(retry_after) = limiter.check(addr.ip(), Instant::now()) {
+            return MokshaMintError::RateLimitExceeded(retry_after).into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// Rejects requests with a content-length above the limit. Bodies without content-length are capped by axum's `DefaultBodyLimit`.
+pub async fn limit_request_size(
+    State(max_request_size): State<usize>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let content_length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    match content_length {
+        Some(len) if len > max_request_size => {
+            MokshaMintError::RequestTooLarge(max_request_size).into_response()
+        }
+        _ => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        time::{Duration, Instant},
+    };
+
+    use axum::{
+        body::Body,
+        extract::connect_info::MockConnectInfo,
+        http::{header::CONTENT_LENGTH, Request, StatusCode},
+        middleware,
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::{limit_request_size, rate_limit, RateLimiter};
+
+    #[test]
+    fn test_rate_limiter_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let now = Instant::now();
+
+        assert!(limiter.check(ip, now).is_ok());
+        assert!(limiter.check(ip, now).is_ok());
+        assert_eq!(Err(50), limiter.check(ip, now + Duration::from_secs(10)));
+        assert!(limiter.check(other_ip, now).is_ok());
+        assert!(limiter.check(ip, now + Duration::from_secs(60)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_middleware() -> anyhow::Result<()> {
+        let app = Router::new()
+            .route("/v1/swap", post(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                RateLimiter::per_minute(1),
+                rate_limit,
+            ))
+            .layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+
+        let request = || Request::post("/v1/swap").body(Body::empty());
+        let response = app.clone().oneshot(request()?).await?;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = app.oneshot(request()?).await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert!(response.headers().contains_key("retry-after"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_limit_request_size() -> anyhow::Result<()> {
+        let app = Router::new()
+            .route("/v1/swap", post(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(16, limit_request_size));
+
+        let response = app
+            .oneshot(
+                Request::post("/v1/swap")
+                    .header(CONTENT_LENGTH, 17)
+                    .body(Body::from(vec![0u8; 17]))?,
+            )
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Prometheus metrics for the mint.
+//!
+//! The recorder is installed once by [`init_metrics`] and rendered by the `/metrics` route.
+//! Amounts are recorded in sats. The outstanding ecash gauge is read from the database when the
+//! metrics are scraped, so it survives restarts of the mint.
+
+use std::{future::Future, sync::Arc, sync::OnceLock, time::Instant};
+
+use async_trait::async_trait;
+use lightning_invoice::Bolt11Invoice as LNInvoice;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use moksha_core::primitives::PaymentMethod;
+
+use crate::{
+    error::MokshaMintError,
+    lightning::Lightning,
+    model::{CreateInvoiceResult, PayInvoiceResult},
+};
+
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs the global prometheus recorder. Calling it more than once is a no-op.
+pub fn init_metrics() -> Result<(), BuildError> {
+    if PROMETHEUS_HANDLE.get().is_some() {
+        return Ok(());
+    }
+    let handle = prometheus_builder()?.install_recorder()?;
+    let _ = PROMETHEUS_HANDLE.set(handle);
+    Ok(())
+}
+
+fn prometheus_builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new().set_buckets_for_metric(
+        Matcher::Suffix("duration_seconds".to_owned()),
+        LATENCY_BUCKETS,
+    )
+}
+
+/// Renders all metrics in the prometheus text format or `None` if metrics are disabled.
+pub fn render() -> Option<String> {
+    PROMETHEUS_HANDLE.get().map(|handle| handle.render())
+}
+
+pub fn record_mint_quote(payment_method: &PaymentMethod, amount: u64) {
+    let method = method_label(payment_method);
+    counter!("moksha_mint_quotes_created_total", "type" => "mint", "method" => method).increment(1);
+    counter!("moksha_mint_quoted_sats_total", "type" => "mint", "method" => method)
+        .increment(amount);
+}
+
+pub fn record_melt_quote(payment_method: &PaymentMethod, amount: u64) {
+    let method = method_label(payment_method);
+    counter!("moksha_mint_quotes_created_total", "type" => "melt", "method" => method).increment(1);
+    counter!("moksha_mint_quoted_sats_total", "type" => "melt", "method" => method)
+        .increment(amount);
+}
+
+pub fn record_mint(payment_method: &PaymentMethod, amount: u64) {
+    counter!("moksha_mint_minted_sats_total", "method" => method_label(payment_method))
+        .increment(amount);
+}
+
+/// `amount` is the value of the burned proofs, `change` the value of the returned signatures.
+pub fn record_melt(payment_method: &PaymentMethod, amount: u64, change: u64) {
+    counter!("moksha_mint_melted_sats_total", "method" => method_label(payment_method))
+        .increment(amount.saturating_sub(change));
+}
+
+/// Runs a melt and records its duration and whether it failed.
+pub async fn timed_melt<T>(
+    payment_method: PaymentMethod,
+    melt: impl Future<Output = Result<T, MokshaMintError>>,
+) -> Result<T, MokshaMintError> {
+    let method = method_label(&payment_method);
+    let start = Instant::now();
+    let result = melt.await;
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    histogram!("moksha_mint_melt_duration_seconds", "method" => method, "result" => outcome)
+        .record(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        counter!("moksha_mint_melt_errors_total", "method" => method).increment(1);
+    }
+    result
+}
+
+/// `amount` is the value of all issued signatures minus the value of all redeemed proofs.
+pub fn record_outstanding_ecash(amount: i64) {
+    gauge!("moksha_mint_outstanding_ecash_sats").set(amount as f64);
+}
+
+pub fn record_swap(amount: u64) {
+    counter!("moksha_mint_swaps_total").increment(1);
+    counter!("moksha_mint_swapped_sats_total").increment(amount);
+}
+
+pub fn record_db_pool(size: u32, idle: usize, max_connections: u32) {
+    gauge!("moksha_mint_db_pool_connections").set(size as f64);
+    gauge!("moksha_mint_db_pool_idle_connections").set(idle as f64);
+    gauge!("moksha_mint_db_pool_max_connections").set(max_connections as f64);
+}
+
+fn record_lightning_call<T>(
+    operation: &'static str,
+    start: Instant,
+    result: &Result<T, MokshaMintError>,
+) {
+    histogram!("moksha_mint_lightning_request_duration_seconds", "operation" => operation)
+        .record(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        counter!("moksha_mint_lightning_errors_total", "operation" => operation).increment(1);
+    }
+}
+
+const fn method_label(payment_method: &PaymentMethod) -> &'static str {
+    match payment_method {
+        PaymentMethod::Bolt11 => "bolt11",
+        PaymentMethod::BtcOnchain => "btconchain",
+    }
+}
+
+/// Wraps a lightning backend and records latency and errors of every call.
+pub struct MeteredLightning {
+    inner: Arc<dyn Lightning + Send + Sync>,
+}
+
+impl MeteredLightning {
+    pub fn new(inner: Arc<dyn Lightning + Send + Sync>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl Lightning for MeteredLightning {
+    async fn is_invoice_paid(&self, invoice: String) -> Result<bool, MokshaMintError> {
+        let start = Instant::now();
+        let result = self.inner.is_invoice_paid(invoice).await;
+        record_lightning_call("is_invoice_paid", start, &result);
+        result
+    }
+
+    async fn create_invoice(&self, amount: u64) -> Result<CreateInvoiceResult, MokshaMintError> {
+        let start = Instant::now();
+        let result = self.inner.create_invoice(amount).await;
+        record_lightning_call("create_invoice", start, &result);
+        result
+    }
+
+    async fn pay_invoice(
+        &self,
+        payment_request: String,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        let start = Instant::now();
+        let result = self.inner.pay_invoice(payment_request).await;
+        record_lightning_call("pay_invoice", start, &result);
+        result
+    }
+
+    async fn pay_partial_invoice(
+        &self,
+        payment_request: String,
+        amount_msat: u64,
+    ) -> Result<PayInvoiceResult, MokshaMintError> {
+        let start = Instant::now();
+        let result = self
+            .inner
+            .pay_partial_invoice(payment_request, amount_msat)
+            .await;
+        record_lightning_call("pay_partial_invoice", start, &result);
+        result
+    }
+
+    fn supports_mpp(&self) -> bool {
+        self.inner.supports_mpp()
+    }
+
+    async fn health_check(&self) -> Result<(), MokshaMintError> {
+        self.inner.health_check().await
+    }
+
+    async fn decode_invoice(&self, payment_request: String) -> Result<LNInvoice, MokshaMintError> {
+        self.inner.decode_invoice(payment_request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use moksha_core::primitives::PaymentMethod;
+
+    use crate::{
+        error::MokshaMintError,
+        lightning::{error::LightningError, Lightning, MockLightning},
+        metrics::MeteredLightning,
+    };
+
+    #[test]
+    fn test_render_volume_metrics() -> anyhow::Result<()> {
+        let recorder = super::prometheus_builder()?.build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            super::record_mint(&PaymentMethod::Bolt11, 64);
+            super::record_melt(&PaymentMethod::Bolt11, 40, 8);
+            super::record_swap(16);
+            super::record_outstanding_ecash(32);
+        });
+
+        let out = handle.render();
+        assert!(out.contains("moksha_mint_minted_sats_total{method=\"bolt11\"} 64"));
+        assert!(out.contains("moksha_mint_melted_sats_total{method=\"bolt11\"} 32"));
+        assert!(out.contains("moksha_mint_swapped_sats_total 16"));
+        assert!(out.contains("moksha_mint_outstanding_ecash_sats 32"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metered_lightning_counts_errors() -> anyhow::Result<()> {
+        let recorder = super::prometheus_builder()?.build_recorder();
+        let handle = recorder.handle();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_is_invoice_paid()
+            .returning(|_| Err(MokshaMintError::Lightning(LightningError::NotFound)));
+        let metered = MeteredLightning::new(Arc::new(lightning));
+
+        assert!(metered.is_invoice_paid("invoice".to_owned()).await.is_err());
+
+        let out = handle.render();
+        assert!(out.contains("moksha_mint_lightning_errors_total{operation=\"is_invoice_paid\"} 1"));
+        assert!(out.contains("moksha_mint_lightning_request_duration_seconds_bucket"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timed_melt_records_errors() -> anyhow::Result<()> {
+        let recorder = super::prometheus_builder()?.build_recorder();
+        let handle = recorder.handle();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let result: Result<(), MokshaMintError> = super::timed_melt(PaymentMethod::Bolt11, async {
+            Err(MokshaMintError::NotEnoughTokens(21))
+        })
+        .await;
+        assert!(result.is_err());
+        super::timed_melt(PaymentMethod::BtcOnchain, async { Ok(()) }).await?;
+
+        let out = handle.render();
+        assert!(out.contains("moksha_mint_melt_errors_total{method=\"bolt11\"} 1"));
+        assert!(out.contains(
+            "moksha_mint_melt_duration_seconds_count{method=\"bolt11\",result=\"error\"} 1"
+        ));
+        assert!(out.contains(
+            "moksha_mint_melt_duration_seconds_count{method=\"btconchain\",result=\"success\"} 1"
+        ));
+        Ok(())
+    }
+}
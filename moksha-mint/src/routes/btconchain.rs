@@ -3,16 +3,17 @@ use axum::{
     Json,
 };
 use moksha_core::primitives::{
-    BtcOnchainMeltQuote, BtcOnchainMintQuote, CurrencyUnit, GetMeltBtcOnchainResponse,
-    PaymentMethod, PostMeltBtcOnchainRequest, PostMeltBtcOnchainResponse,
-    PostMeltQuoteBtcOnchainRequest, PostMeltQuoteBtcOnchainResponse, PostMintBtcOnchainRequest,
-    PostMintBtcOnchainResponse, PostMintQuoteBtcOnchainRequest, PostMintQuoteBtcOnchainResponse,
+    BtcOnchainMeltQuote, BtcOnchainMintQuote, GetMeltBtcOnchainResponse, PaymentMethod,
+    PostMeltBtcOnchainRequest, PostMeltBtcOnchainResponse, PostMeltQuoteBtcOnchainRequest,
+    PostMeltQuoteBtcOnchainResponse, PostMintBtcOnchainRequest, PostMintBtcOnchainResponse,
+    PostMintQuoteBtcOnchainRequest, PostMintQuoteBtcOnchainResponse,
 };
-use tracing::{info, instrument};
+use tracing::{info, instrument, Instrument};
 use uuid::Uuid;
 
+use super::check_amount_limits;
 use crate::database::Database;
-use crate::{error::MokshaMintError, mint::Mint};
+use crate::{error::MokshaMintError, metrics, mint::Mint};
 use chrono::{Duration, Utc};
 use std::str::FromStr;
 
@@ -29,25 +30,12 @@ pub async fn post_mint_quote_btconchain(
     State(mint): State<Mint>,
     Json(request): Json<PostMintQuoteBtcOnchainRequest>,
 ) -> Result<Json<PostMintQuoteBtcOnchainResponse>, MokshaMintError> {
-    let onchain_config = mint.config.btconchain_backend.unwrap_or_default();
-
-    if request.unit != CurrencyUnit::Sat {
-        return Err(MokshaMintError::CurrencyNotSupported(request.unit));
-    }
-
-    if request.amount < onchain_config.min_amount {
-        return Err(MokshaMintError::InvalidAmount(format!(
-            "amount is too low. Min amount is {}",
-            onchain_config.min_amount
-        )));
-    }
-
-    if request.amount > onchain_config.max_amount {
-        return Err(MokshaMintError::InvalidAmount(format!(
-            "amount is too high. Max amount is {}",
-            onchain_config.max_amount
-        )));
-    }
+    check_amount_limits(
+        &mint.config.limits.mint_limits,
+        &PaymentMethod::BtcOnchain,
+        &request.unit,
+        request.amount,
+    )?;
 
     let quote_id = Uuid::new_v4();
     let address = mint
@@ -69,6 +57,7 @@ pub async fn post_mint_quote_btconchain(
     let mut tx = mint.db.begin_tx().await?;
     mint.db.add_onchain_mint_quote(&mut tx, &quote).await?;
     tx.commit().await?;
+    metrics::record_mint_quote(&PaymentMethod::BtcOnchain, quote.amount);
     Ok(Json(quote.into()))
 }
 
@@ -174,25 +163,12 @@ pub async fn post_melt_quote_btconchain(
         unit,
     } = melt_request;
 
-    let onchain_config = mint.config.btconchain_backend.unwrap_or_default();
-
-    if unit != CurrencyUnit::Sat {
-        return Err(MokshaMintError::CurrencyNotSupported(unit));
-    }
-
-    if amount < onchain_config.min_amount {
-        return Err(MokshaMintError::InvalidAmount(format!(
-            "amount is too low. Min amount is {}",
-            onchain_config.min_amount
-        )));
-    }
-
-    if amount > onchain_config.max_amount {
-        return Err(MokshaMintError::InvalidAmount(format!(
-            "amount is too high. Max amount is {}",
-            onchain_config.max_amount
-        )));
-    }
+    check_amount_limits(
+        &mint.config.limits.melt_limits,
+        &PaymentMethod::BtcOnchain,
+        &unit,
+        amount,
+    )?;
 
     let fee_response = mint
         .onchain
@@ -217,6 +193,7 @@ pub async fn post_melt_quote_btconchain(
     let mut tx = mint.db.begin_tx().await?;
     mint.db.add_onchain_melt_quote(&mut tx, &quote).await?;
     tx.commit().await?;
+    metrics::record_melt_quote(&PaymentMethod::BtcOnchain, quote.amount);
 
     Ok(Json(vec![quote.into()]))
 }
@@ -271,6 +248,18 @@ pub async fn get_melt_quote_btconchain(
 pub async fn post_melt_btconchain(
     State(mint): State<Mint>,
     Json(melt_request): Json<PostMeltBtcOnchainRequest>,
+) -> Result<Json<PostMeltBtcOnchainResponse>, MokshaMintError> {
+    let melt = metrics::timed_melt(
+        PaymentMethod::BtcOnchain,
+        melt_btconchain(mint.clone(), melt_request),
+    )
+    .in_current_span();
+    mint.inflight_melts.spawn(melt).await?
+}
+
+async fn melt_btconchain(
+    mint: Mint,
+    melt_request: PostMeltBtcOnchainRequest,
 ) -> Result<Json<PostMeltBtcOnchainResponse>, MokshaMintError> {
     let mut tx = mint.db.begin_tx().await?;
     let quote = mint
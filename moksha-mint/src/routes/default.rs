@@ -5,20 +5,24 @@ use axum::{
 use moksha_core::{
     keyset::Keysets,
     primitives::{
-        Bolt11MeltQuote, Bolt11MintQuote, CurrencyUnit, KeyResponse, KeysResponse,
-        MintInfoResponse, Nuts, PaymentMethod, PostMeltBolt11Request, PostMeltBolt11Response,
-        PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMintBolt11Request,
-        PostMintBolt11Response, PostMintQuoteBolt11Request, PostMintQuoteBolt11Response,
-        PostSwapRequest, PostSwapResponse,
+        Bolt11MeltQuote, Bolt11MintQuote, ContactInfo, CurrencyUnit, KeyResponse, KeysResponse,
+        MintInfoResponse, Nut15, Nut17, Nut18, Nut4, Nut5, Nut7, Nut9, Nuts, PaymentMethod,
+        PostCheckStateRequest, PostCheckStateResponse, PostMeltBolt11Request,
+        PostMeltBolt11Response, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
+        PostMintBolt11Request, PostMintBolt11Response, PostMintQuoteBolt11Request,
+        PostMintQuoteBolt11Response, PostRestoreRequest, PostRestoreResponse, PostSwapRequest,
+        PostSwapResponse,
     },
 };
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, Instrument};
 use uuid::Uuid;
 
+use super::check_amount_limits;
 use crate::database::Database;
 use crate::{
-    config::{BtcOnchainConfig, MintConfig},
+    config::{AmountLimit, MintConfig},
     error::MokshaMintError,
+    metrics,
     mint::Mint,
 };
 use chrono::{Duration, Utc};
@@ -46,6 +50,44 @@ pub async fn post_swap(
     }))
 }
 
+#[utoipa::path(
+        post,
+        path = "/v1/restore",
+        request_body = PostRestoreRequest,
+        responses(
+            (status = 200, description = "post restore", body = [PostRestoreResponse])
+        ),
+    )]
+#[instrument(name = "post_restore", skip(mint), err)]
+pub async fn post_restore(
+    State(mint): State<Mint>,
+    Json(restore_request): Json<PostRestoreRequest>,
+) -> Result<Json<PostRestoreResponse>, MokshaMintError> {
+    let (outputs, signatures) = mint.restore(&restore_request.outputs).await?;
+    Ok(Json(PostRestoreResponse {
+        outputs,
+        signatures,
+    }))
+}
+
+#[utoipa::path(
+        post,
+        path = "/v1/checkstate",
+        request_body = PostCheckStateRequest,
+        responses(
+            (status = 200, description = "post checkstate", body = [PostCheckStateResponse])
+        ),
+    )]
+#[instrument(name = "post_check_state", skip(mint), err)]
+pub async fn post_check_state(
+    State(mint): State<Mint>,
+    Json(check_state_request): Json<PostCheckStateRequest>,
+) -> Result<Json<PostCheckStateResponse>, MokshaMintError> {
+    Ok(Json(PostCheckStateResponse {
+        states: mint.check_state(&check_state_request.ys).await?,
+    }))
+}
+
 #[utoipa::path(
         get,
         path = "/v1/keys",
@@ -121,7 +163,13 @@ pub async fn post_mint_quote_bolt11(
     State(mint): State<Mint>,
     Json(request): Json<PostMintQuoteBolt11Request>,
 ) -> Result<Json<PostMintQuoteBolt11Response>, MokshaMintError> {
-    // FIXME check currency unit
+    check_amount_limits(
+        &mint.config.limits.mint_limits,
+        &PaymentMethod::Bolt11,
+        &request.unit,
+        request.amount,
+    )?;
+
     let key = Uuid::new_v4();
     let (pr, _hash) = mint.create_invoice(key.to_string(), request.amount).await?;
 
@@ -135,6 +183,7 @@ pub async fn post_mint_quote_bolt11(
     let mut tx = mint.db.begin_tx().await?;
     mint.db.add_bolt11_mint_quote(&mut tx, &quote).await?;
     tx.commit().await?;
+    metrics::record_mint_quote(&PaymentMethod::Bolt11, request.amount);
     Ok(Json(quote.into()))
 }
 
@@ -201,13 +250,29 @@ pub async fn post_melt_quote_bolt11(
         .lightning
         .decode_invoice(melt_request.request.clone())
         .await?;
-    let amount = invoice.amount_milli_satoshis().ok_or_else(|| {
+    let invoice_amount = invoice.amount_milli_satoshis().ok_or_else(|| {
         crate::error::MokshaMintError::InvalidAmount("invalid invoice".to_owned())
     })?;
+    let partial_amount_msat = match melt_request.options.and_then(|options| options.mpp) {
+        Some(mpp) => Some(partial_amount(
+            mint.lightning.supports_mpp(),
+            mpp.amount,
+            invoice_amount,
+        )?),
+        None => None,
+    };
+    let amount = partial_amount_msat.unwrap_or(invoice_amount);
     let fee_reserve = mint.fee_reserve(amount) / 1_000; // FIXME check if this is correct
     debug!("fee_reserve: {}", fee_reserve);
 
     let amount_sat = amount / 1_000;
+    check_amount_limits(
+        &mint.config.limits.melt_limits,
+        &PaymentMethod::Bolt11,
+        &melt_request.unit,
+        amount_sat,
+    )?;
+
     let key = Uuid::new_v4();
     let quote = Bolt11MeltQuote {
         quote_id: key,
@@ -216,14 +281,38 @@ pub async fn post_melt_quote_bolt11(
         expiry: quote_expiry(),
         payment_request: melt_request.request.clone(),
         paid: false,
+        partial_amount_msat,
     };
     let mut tx = mint.db.begin_tx().await?;
     mint.db.add_bolt11_melt_quote(&mut tx, &quote).await?;
     tx.commit().await?;
+    metrics::record_melt_quote(&PaymentMethod::Bolt11, amount_sat);
 
     Ok(Json(quote.into()))
 }
 
+/// Validates the amount of a partial melt quote (NUT-15)
+fn partial_amount(
+    mpp_enabled: bool,
+    amount_msat: u64,
+    invoice_amount_msat: u64,
+) -> Result<u64, MokshaMintError> {
+    if !mpp_enabled {
+        return Err(MokshaMintError::MppNotSupported);
+    }
+    if amount_msat == 0 || amount_msat > invoice_amount_msat {
+        return Err(MokshaMintError::InvalidAmount(format!(
+            "mpp amount {amount_msat} must be between 1 and the invoice amount {invoice_amount_msat} msat"
+        )));
+    }
+    if amount_msat % 1_000 != 0 {
+        return Err(MokshaMintError::InvalidAmount(format!(
+            "mpp amount {amount_msat} msat is not a whole sat amount"
+        )));
+    }
+    Ok(amount_msat)
+}
+
 fn quote_expiry() -> u64 {
     // FIXME add config option for expiry
     let now = Utc::now() + Duration::try_minutes(30).expect("invalid duration");
@@ -242,6 +331,18 @@ fn quote_expiry() -> u64 {
 pub async fn post_melt_bolt11(
     State(mint): State<Mint>,
     Json(melt_request): Json<PostMeltBolt11Request>,
+) -> Result<Json<PostMeltBolt11Response>, MokshaMintError> {
+    let melt = metrics::timed_melt(
+        PaymentMethod::Bolt11,
+        melt_bolt11(mint.clone(), melt_request),
+    )
+    .in_current_span();
+    mint.inflight_melts.spawn(melt).await?
+}
+
+async fn melt_bolt11(
+    mint: Mint,
+    melt_request: PostMeltBolt11Request,
 ) -> Result<Json<PostMeltBolt11Response>, MokshaMintError> {
     let mut tx = mint.db.begin_tx().await?;
     let quote = mint
@@ -256,6 +357,7 @@ pub async fn post_melt_bolt11(
             &mut tx,
             quote.payment_request.to_owned(),
             quote.fee_reserve,
+            quote.partial_amount_msat,
             &melt_request.inputs,
             &melt_request.outputs,
             &mint.keyset,
@@ -341,48 +443,151 @@ pub async fn get_melt_quote_bolt11(
     )]
 #[instrument(name = "get_info", skip(mint), err)]
 pub async fn get_info(State(mint): State<Mint>) -> Result<Json<MintInfoResponse>, MokshaMintError> {
-    // TODO implement From-trait
-
-    let mint_info = mint.config.info.clone();
-    let contact = Some(
-        vec![
-            mint_info
-                .contact_email
-                .map(|email| vec!["email".to_owned(), email]),
-            mint_info
-                .contact_twitter
-                .map(|twitter| vec!["twitter".to_owned(), twitter]),
-            mint_info
-                .contact_nostr
-                .map(|nostr| vec!["nostr".to_owned(), nostr]),
-        ]
-        .into_iter()
-        .flatten()
-        .collect::<Vec<Vec<String>>>(),
+    let nuts = get_nuts(
+        &mint.config,
+        mint.onchain.is_some(),
+        mint.lightning.supports_mpp(),
     );
+    let mint_info = mint.config.info;
+
+    let contact = [
+        ("email", mint_info.contact_email),
+        ("twitter", mint_info.contact_twitter),
+        ("nostr", mint_info.contact_nostr),
+    ]
+    .into_iter()
+    .filter_map(|(method, info)| info.map(|info| ContactInfo::new(method, info)))
+    .collect::<Vec<_>>();
 
     let mint_info = MintInfoResponse {
-        nuts: get_nuts(&mint.config),
-        name: mint.config.info.name,
+        nuts,
+        name: mint_info.name,
         pubkey: mint.keyset.mint_pubkey,
-        version: match mint.config.info.version {
+        version: match mint_info.version {
             true => Some(mint.build_params.full_version()),
             _ => None,
         },
-        description: mint.config.info.description,
-        description_long: mint.config.info.description_long,
-        contact,
-        motd: mint.config.info.motd,
+        description: mint_info.description,
+        description_long: mint_info.description_long,
+        contact: (!contact.is_empty()).then_some(contact),
+        motd: mint_info.motd,
+        icon_url: mint_info.icon_url,
+        urls: mint_info.urls,
+        time: Some(Utc::now().timestamp() as u64),
     };
     Ok(Json(mint_info))
 }
 
-fn get_nuts(cfg: &MintConfig) -> Nuts {
-    let default_config = BtcOnchainConfig::default();
-    let config = cfg.btconchain_backend.as_ref().unwrap_or(&default_config);
+/// NUT-17 and NUT-18 are only advertised if the mint has an onchain backend, NUT-15 if the lightning backend supports MPP
+fn get_nuts(cfg: &MintConfig, onchain_enabled: bool, mpp_enabled: bool) -> Nuts {
+    let enabled = |limits: &[AmountLimit]| {
+        limits
+            .iter()
+            .filter(|limit| onchain_enabled || limit.payment_method != PaymentMethod::BtcOnchain)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let mint_limits = enabled(&cfg.limits.mint_limits);
+    let melt_limits = enabled(&cfg.limits.melt_limits);
+    let onchain = |limits: &[AmountLimit]| {
+        limits
+            .iter()
+            .filter(|limit| limit.payment_method == PaymentMethod::BtcOnchain)
+            .map(Into::into)
+            .collect::<Vec<_>>()
+    };
+
     Nuts {
-        nut17: Some(config.to_owned().into()),
-        nut18: Some(config.to_owned().into()),
+        nut4: Nut4 {
+            payment_methods: mint_limits.iter().map(Into::into).collect(),
+            disabled: false,
+        },
+        nut5: Nut5 {
+            payment_methods: melt_limits.iter().map(Into::into).collect(),
+            disabled: false,
+        },
+        nut7: Some(Nut7 { supported: true }),
+        nut9: Some(Nut9 { supported: true }),
+        nut15: mpp_enabled.then(Nut15::default),
+        nut17: onchain_enabled.then(|| Nut17 {
+            supported: true,
+            payment_methods: onchain(&mint_limits),
+        }),
+        nut18: onchain_enabled.then(|| Nut18 {
+            supported: true,
+            payment_methods: onchain(&melt_limits),
+        }),
         ..Nuts::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::primitives::{CurrencyUnit, PaymentMethod};
+
+    use crate::config::{AmountLimit, AmountLimitsConfig, MintConfig};
+
+    use super::{get_nuts, partial_amount};
+    use crate::error::MokshaMintError;
+
+    #[test]
+    fn test_get_nuts_without_onchain() {
+        let nuts = get_nuts(&MintConfig::default(), false, false);
+        assert!(nuts.nut15.is_none());
+        assert!(nuts.nut17.is_none());
+        assert!(nuts.nut18.is_none());
+        assert!(nuts
+            .nut4
+            .payment_methods
+            .iter()
+            .all(|method| method.payment_method == PaymentMethod::Bolt11));
+    }
+
+    #[test]
+    fn test_get_nuts_with_limits() -> anyhow::Result<()> {
+        let limit = |limit: &str| limit.parse::<AmountLimit>().map_err(anyhow::Error::msg);
+        let config = MintConfig {
+            limits: AmountLimitsConfig {
+                mint_limits: vec![
+                    limit("bolt11:sat:1:21")?,
+                    limit("bolt11:usd:1:100")?,
+                    limit("btconchain:sat:5000:100000")?,
+                ],
+                melt_limits: vec![limit("bolt11:sat:1:1000")?],
+            },
+            ..Default::default()
+        };
+        let nuts = get_nuts(&config, true, true);
+
+        let mint_methods = nuts.nut4.payment_methods;
+        assert_eq!(3, mint_methods.len());
+        assert_eq!(Some(21), mint_methods[0].max_amount);
+        assert_eq!(CurrencyUnit::Usd, mint_methods[1].unit);
+        assert_eq!(PaymentMethod::BtcOnchain, mint_methods[2].payment_method);
+        assert_eq!(1, nuts.nut5.payment_methods.len());
+        assert_eq!(
+            5_000,
+            nuts.nut17.expect("nut17 not set").payment_methods[0].min_amount
+        );
+        assert!(nuts
+            .nut18
+            .expect("nut18 not set")
+            .payment_methods
+            .is_empty());
+        assert!(nuts.nut15.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_amount() -> anyhow::Result<()> {
+        assert_eq!(40_000, partial_amount(true, 40_000, 100_000)?);
+        assert!(matches!(
+            partial_amount(false, 40_000, 100_000),
+            Err(MokshaMintError::MppNotSupported)
+        ));
+        assert!(partial_amount(true, 0, 100_000).is_err());
+        assert!(partial_amount(true, 100_001, 100_000).is_err());
+        assert!(partial_amount(true, 40_500, 100_000).is_err());
+        Ok(())
+    }
+}
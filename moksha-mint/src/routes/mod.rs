@@ -1,2 +1,67 @@
+use moksha_core::primitives::{CurrencyUnit, PaymentMethod};
+
+use crate::{config::AmountLimit, error::MokshaMintError};
+
 pub mod btconchain;
 pub mod default;
+
+/// Checks that the amount is within the configured limits of the payment method and unit
+fn check_amount_limits(
+    limits: &[AmountLimit],
+    payment_method: &PaymentMethod,
+    unit: &CurrencyUnit,
+    amount: u64,
+) -> Result<(), MokshaMintError> {
+    let limit = limits
+        .iter()
+        .find(|limit| &limit.payment_method == payment_method && &limit.unit == unit)
+        .ok_or_else(|| MokshaMintError::CurrencyNotSupported(unit.clone()))?;
+
+    if amount < limit.min_amount {
+        return Err(MokshaMintError::InvalidAmount(format!(
+            "amount is too low. Min amount is {}",
+            limit.min_amount
+        )));
+    }
+
+    if amount > limit.max_amount {
+        return Err(MokshaMintError::InvalidAmount(format!(
+            "amount is too high. Max amount is {}",
+            limit.max_amount
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use moksha_core::primitives::{CurrencyUnit, PaymentMethod};
+
+    use super::check_amount_limits;
+    use crate::{config::AmountLimit, error::MokshaMintError};
+
+    #[test]
+    fn test_check_amount_limits() -> anyhow::Result<()> {
+        let limits = vec![
+            "bolt11:sat:1:100"
+                .parse::<AmountLimit>()
+                .map_err(anyhow::Error::msg)?,
+            "btconchain:sat:1000:2000"
+                .parse::<AmountLimit>()
+                .map_err(anyhow::Error::msg)?,
+        ];
+        let check = |method, unit, amount| check_amount_limits(&limits, &method, &unit, amount);
+
+        assert!(check(PaymentMethod::Bolt11, CurrencyUnit::Sat, 1).is_ok());
+        assert!(check(PaymentMethod::Bolt11, CurrencyUnit::Sat, 100).is_ok());
+        assert!(check(PaymentMethod::Bolt11, CurrencyUnit::Sat, 0).is_err());
+        assert!(check(PaymentMethod::Bolt11, CurrencyUnit::Sat, 101).is_err());
+        assert!(check(PaymentMethod::BtcOnchain, CurrencyUnit::Sat, 100).is_err());
+        assert!(check(PaymentMethod::BtcOnchain, CurrencyUnit::Sat, 1_500).is_ok());
+        assert!(matches!(
+            check(PaymentMethod::Bolt11, CurrencyUnit::Usd, 1),
+            Err(MokshaMintError::CurrencyNotSupported(CurrencyUnit::Usd))
+        ));
+        Ok(())
+    }
+}
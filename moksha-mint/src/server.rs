@@ -5,14 +5,15 @@ use crate::routes::btconchain::{
 };
 use crate::routes::default::{
     get_info, get_keys, get_keys_by_id, get_keysets, get_melt_quote_bolt11, get_mint_quote_bolt11,
-    post_melt_bolt11, post_melt_quote_bolt11, post_mint_bolt11, post_mint_quote_bolt11, post_swap,
+    post_check_state, post_melt_bolt11, post_melt_quote_bolt11, post_mint_bolt11,
+    post_mint_quote_bolt11, post_restore, post_swap,
 };
-use axum::extract::Request;
+use axum::extract::{DefaultBodyLimit, Request, State};
 use axum::http::{HeaderName, HeaderValue, StatusCode};
 use axum::middleware::Next;
 use axum::response::IntoResponse;
 use axum::routing::{get, get_service, post};
-use axum::{middleware, Router};
+use axum::{middleware, Json, Router};
 
 use moksha_core::keyset::{Keyset, Keysets};
 use moksha_core::proof::Proofs;
@@ -20,24 +21,34 @@ use moksha_core::proof::{P2SHScript, Proof};
 
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::database::Database;
+use crate::error::MokshaMintError;
+use crate::limits::{limit_request_size, rate_limit, RateLimiter};
+use crate::metrics;
 use crate::mint::Mint;
 
 use moksha_core::blind::BlindedMessage;
 use moksha_core::blind::BlindedSignature;
 use moksha_core::primitives::{
-    CurrencyUnit, GetMeltBtcOnchainResponse, KeyResponse, KeysResponse, MintInfoResponse, Nut10,
-    Nut11, Nut12, Nut17, Nut18, Nut4, Nut5, Nut7, Nut8, Nut9, Nuts, PaymentMethod,
-    PostMeltBolt11Request, PostMeltBolt11Response, PostMeltQuoteBolt11Request,
-    PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainRequest, PostMeltQuoteBtcOnchainResponse,
-    PostMintBolt11Request, PostMintBolt11Response, PostMintQuoteBolt11Request,
-    PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest, PostMintQuoteBtcOnchainResponse,
-    PostSwapRequest, PostSwapResponse,
+    ContactInfo, CurrencyUnit, GetMeltBtcOnchainResponse, KeyResponse, KeysResponse,
+    MeltQuoteBolt11Options, MintInfoResponse, Mpp, MppMethodConfig, Nut10, Nut11, Nut12, Nut15,
+    Nut17, Nut18, Nut4, Nut5, Nut7, Nut8, Nut9, Nuts, PaymentMethod, PostCheckStateRequest,
+    PostCheckStateResponse, PostMeltBolt11Request, PostMeltBolt11Response,
+    PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainRequest,
+    PostMeltQuoteBtcOnchainResponse, PostMintBolt11Request, PostMintBolt11Response,
+    PostMintQuoteBolt11Request, PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest,
+    PostMintQuoteBtcOnchainResponse, PostRestoreRequest, PostRestoreResponse, PostSwapRequest,
+    PostSwapResponse, ProofStateResponse, SpentState,
 };
 
+use serde_json::json;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
 use tower_http::services::ServeDir;
 
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{error, info};
 
 use utoipa::OpenApi;
 
@@ -55,6 +66,8 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
     info!("mint-info: {:?}", mint.config.info);
     info!("lightning fee-reserve: {:?}", mint.config.lightning_fee);
     info!("lightning-backend: {}", mint.lightning_type);
+    info!("mint-limits: {:?}", mint.config.limits.mint_limits);
+    info!("melt-limits: {:?}", mint.config.limits.melt_limits);
 
     if let Some(ref onchain) = mint.config.btconchain_backend {
         info!("onchain-type: {:?}", onchain.onchain_type);
@@ -62,15 +75,29 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
             "btconchain-min-confirmations: {}",
             onchain.min_confirmations
         );
-        info!("btconchain-min-amount: {}", onchain.min_amount);
-        info!("btconchain-max-amount: {}", onchain.max_amount);
     } else {
         info!("btconchain-backend is not configured");
     }
 
     info!("tracing jaeger-endpoint: {:?}", mint.config.tracing);
+    info!(
+        "rate-limit per minute: {:?}",
+        mint.config.server.rate_limit_per_minute
+    );
+    info!(
+        "max-request-size: {} max-inputs: {} max-outputs: {}",
+        mint.config.server.max_request_size,
+        mint.config.server.max_inputs,
+        mint.config.server.max_outputs
+    );
+
+    if mint.config.metrics.enabled {
+        metrics::init_metrics()?;
+        info!("serving prometheus metrics on /metrics");
+    }
 
     let listener = tokio::net::TcpListener::bind(&mint.config.server.host_port).await?;
+    let inflight_melts = mint.inflight_melts.clone();
 
     axum::serve(
         listener,
@@ -82,10 +109,18 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
                     .allow_methods(Any)
                     .expose_headers(Any),
             )
-            .into_make_service(),
+            .into_make_service_with_connect_info::<SocketAddr>(),
     )
+    .with_graceful_shutdown(shutdown_signal())
     .await?;
 
+    inflight_melts.close();
+    if !inflight_melts.is_empty() {
+        info!("waiting for {} in-flight melts", inflight_melts.len());
+    }
+    inflight_melts.wait().await;
+    info!("shutdown complete");
+
     Ok(())
 }
 
@@ -102,8 +137,12 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         crate::routes::default::post_melt_quote_bolt11,
         crate::routes::default::get_melt_quote_bolt11,
         crate::routes::default::post_swap,
+        crate::routes::default::post_restore,
+        crate::routes::default::post_check_state,
         crate::routes::default::get_info,
         get_health,
+        get_health_live,
+        get_health_ready,
         crate::routes::btconchain::post_mint_quote_btconchain,
         crate::routes::btconchain::get_mint_quote_btconchain,
         crate::routes::btconchain::post_mint_btconchain,
@@ -114,6 +153,7 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
     ),
     components(schemas(
         MintInfoResponse,
+        ContactInfo,
         Nuts,
         Nut4,
         Nut5,
@@ -123,6 +163,8 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         Nut10,
         Nut11,
         Nut12,
+        Nut15,
+        MppMethodConfig,
         CurrencyUnit,
         PaymentMethod,
         KeysResponse,
@@ -136,6 +178,8 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         PostMintQuoteBolt11Request,
         PostMintQuoteBolt11Response,
         PostMeltQuoteBolt11Request,
+        MeltQuoteBolt11Options,
+        Mpp,
         PostMeltQuoteBolt11Response,
         PostMeltBolt11Request,
         PostMeltBolt11Response,
@@ -143,6 +187,12 @@ pub async fn run_server(mint: Mint) -> anyhow::Result<()> {
         PostMintBolt11Response,
         PostSwapRequest,
         PostSwapResponse,
+        PostRestoreRequest,
+        PostRestoreResponse,
+        PostCheckStateRequest,
+        PostCheckStateResponse,
+        ProofStateResponse,
+        SpentState,
         P2SHScript,
         Nut17,
         Nut18,
@@ -168,6 +218,8 @@ fn app(mint: Mint) -> Router {
         .route("/v1/melt/quote/bolt11/:quote", get(get_melt_quote_bolt11))
         .route("/v1/melt/bolt11", post(post_melt_bolt11))
         .route("/v1/swap", post(post_swap))
+        .route("/v1/restore", post(post_restore))
+        .route("/v1/checkstate", post(post_check_state))
         .route("/v1/info", get(get_info));
 
     let btconchain_routes = if mint.onchain.is_some() {
@@ -195,14 +247,31 @@ fn app(mint: Mint) -> Router {
         Router::new()
     };
 
-    let general_routes = Router::new().route("/health", get(get_health));
+    let general_routes = if mint.config.metrics.enabled {
+        health_routes().route("/metrics", get(get_metrics))
+    } else {
+        health_routes()
+    };
 
     let server_config = mint.config.server.clone();
     let prefix = server_config.api_prefix.unwrap_or_else(|| "".to_owned());
 
+    let mut api_routes = default_routes
+        .merge(btconchain_routes)
+        .layer(DefaultBodyLimit::max(server_config.max_request_size))
+        .layer(middleware::from_fn_with_state(
+            server_config.max_request_size,
+            limit_request_size,
+        ));
+    if let Some(max_requests) = server_config.rate_limit_per_minute {
+        api_routes = api_routes.layer(middleware::from_fn_with_state(
+            RateLimiter::per_minute(max_requests),
+            rate_limit,
+        ));
+    }
+
     let router = Router::new()
-        .nest(&prefix, default_routes)
-        .nest(&prefix, btconchain_routes)
+        .nest(&prefix, api_routes)
         .nest("", general_routes)
         .with_state(mint);
 
@@ -216,6 +285,39 @@ fn app(mint: Mint) -> Router {
     router
 }
 
+fn health_routes() -> Router<Mint> {
+    Router::new()
+        .route("/health", get(get_health))
+        .route("/health/live", get(get_health_live))
+        .route("/health/ready", get(get_health_ready))
+}
+
+/// Resolves on ctrl-c or SIGTERM. The server stops accepting connections and waits for in-flight requests.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl-c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("shutdown signal received, draining in-flight requests");
+}
+
 /// This function adds response headers that are specific to Flutter web applications.
 ///
 /// It sets the `cross-origin-embedder-policy` header to `require-corp` and the
@@ -260,6 +362,77 @@ async fn get_health() -> impl IntoResponse {
     StatusCode::OK
 }
 
+#[utoipa::path(
+        get,
+        path = "/health/live",
+        responses(
+            (status = 200, description = "liveness check")
+        ),
+    )]
+async fn get_health_live() -> impl IntoResponse {
+    StatusCode::OK
+}
+
+const READINESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[utoipa::path(
+        get,
+        path = "/health/ready",
+        responses(
+            (status = 200, description = "database and lightning backend are reachable"),
+            (status = 503, description = "database or lightning backend is not reachable")
+        ),
+    )]
+async fn get_health_ready(State(mint): State<Mint>) -> impl IntoResponse {
+    let (database, lightning) = tokio::join!(
+        probe(async {
+            mint.db.begin_tx().await?.rollback().await?;
+            Ok(())
+        }),
+        probe(mint.lightning.health_check()),
+    );
+
+    let status = if database.is_ok() && lightning.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = json!({
+        "database": database.err().unwrap_or_else(|| "ok".to_owned()),
+        "lightning": lightning.err().unwrap_or_else(|| "ok".to_owned()),
+    });
+    (status, Json(body))
+}
+
+async fn probe(check: impl Future<Output = Result<(), MokshaMintError>>) -> Result<(), String> {
+    match tokio::time::timeout(READINESS_TIMEOUT, check).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err("timeout".to_owned()),
+    }
+}
+
+async fn outstanding_amount(mint: &Mint) -> Result<i64, MokshaMintError> {
+    let mut tx = mint.db.begin_tx().await?;
+    let amount = mint.db.get_outstanding_amount(&mut tx).await?;
+    tx.commit().await?;
+    Ok(amount)
+}
+
+async fn get_metrics(State(mint): State<Mint>) -> impl IntoResponse {
+    let (size, idle, max_connections) = mint.db.pool_stats();
+    metrics::record_db_pool(size, idle, max_connections);
+    match outstanding_amount(&mint).await {
+        Ok(amount) => metrics::record_outstanding_ecash(amount),
+        Err(err) => error!("could not read outstanding ecash: {err}"),
+    }
+
+    match metrics::render() {
+        Some(body) => (StatusCode::OK, body),
+        None => (StatusCode::NOT_FOUND, String::new()),
+    }
+}
+
 // ######################################################################################################
 
 #[cfg(test)]
@@ -288,7 +461,7 @@ mod tests {
 
     use crate::{
         config::MintInfoConfig,
-        lightning::{LightningType, MockLightning},
+        lightning::{error::LightningError, LightningType, MockLightning},
         mint::Mint,
     };
     use pretty_assertions::assert_eq;
@@ -351,10 +524,11 @@ mod tests {
 
     async fn create_mock_mint(info: MintInfoConfig, db_port: u16) -> anyhow::Result<Mint> {
         let db = create_mock_db_empty(db_port).await?;
-        let lightning = Arc::new(MockLightning::new());
+        let mut lightning = MockLightning::new();
+        lightning.expect_supports_mpp().returning(|| false);
 
         Ok(Mint::new(
-            lightning,
+            Arc::new(lightning),
             LightningType::Lnbits(Default::default()),
             db,
             MintConfig {
@@ -518,6 +692,44 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_health_ready_lightning_down() -> anyhow::Result<()> {
+        let docker = Cli::default();
+        let image = create_postgres_image();
+        let node = docker.run(image);
+
+        let mut lightning = MockLightning::new();
+        lightning
+            .expect_health_check()
+            .returning(|| Err(LightningError::NotFound.into()));
+        let mint = Mint::new(
+            Arc::new(lightning),
+            LightningType::Lnbits(Default::default()),
+            create_mock_db_empty(node.get_host_port_ipv4(5432)).await?,
+            MintConfig {
+                privatekey: "mytestsecret".to_string(),
+                ..Default::default()
+            },
+            Default::default(),
+            None,
+        );
+
+        let response = app(mint)
+            .oneshot(
+                Request::builder()
+                    .uri("/health/ready")
+                    .body(Body::empty())?,
+            )
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().collect().await?.to_bytes();
+        let body = serde_json::from_slice::<serde_json::Value>(&body)?;
+        assert_eq!(body["database"], "ok");
+        assert_eq!(body["lightning"], "Lightning Error Not found");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_info() -> anyhow::Result<()> {
         let docker = Cli::default();
@@ -545,6 +757,9 @@ mod tests {
             info.description_long,
             Some("A mint for testing long".to_string())
         );
+        assert!(info.time.is_some());
+        assert!(info.nuts.nut17.is_some());
+        assert!(info.nuts.nut15.is_none());
         Ok(())
     }
 }
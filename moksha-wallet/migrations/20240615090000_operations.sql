@@ -0,0 +1,11 @@
+-- journal of requests to the mint that have not been stored yet
+CREATE TABLE IF NOT EXISTS operations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                mint_url TEXT NOT NULL,
+                keyset_id TEXT NOT NULL,
+                quote_id TEXT,
+                inputs TEXT NOT NULL CHECK (json_valid(inputs)),
+                outputs TEXT NOT NULL CHECK (json_valid(outputs)),
+                time_created TIMESTAMP
+);
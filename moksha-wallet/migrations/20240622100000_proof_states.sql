@@ -0,0 +1,3 @@
+-- proofs that were sent are kept as RESERVED together with the serialized token until they are claimed
+ALTER TABLE proofs ADD COLUMN state TEXT NOT NULL DEFAULT 'UNSPENT';
+ALTER TABLE proofs ADD COLUMN token TEXT;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS transactions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                fee INTEGER NOT NULL DEFAULT 0,
+                mint_url TEXT NOT NULL,
+                currency_unit TEXT NOT NULL,
+                memo TEXT,
+                payment_request TEXT,
+                payment_proof TEXT,
+                timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS transactions_timestamp ON transactions (timestamp);
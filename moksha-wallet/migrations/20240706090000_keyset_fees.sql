@@ -0,0 +1,2 @@
+-- fee per input proof of the keyset in parts per thousand sats (NUT-02)
+ALTER TABLE keysets ADD COLUMN input_fee_ppk INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS trusted_mints (
+                mint_url TEXT PRIMARY KEY NOT NULL,
+                preferred BOOLEAN NOT NULL DEFAULT FALSE
+);
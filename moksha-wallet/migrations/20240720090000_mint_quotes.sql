@@ -0,0 +1,12 @@
+-- mint quotes created by the wallet, so paid quotes can be minted after a restart
+CREATE TABLE IF NOT EXISTS mint_quotes (
+                quote_id TEXT PRIMARY KEY NOT NULL,
+                mint_url TEXT NOT NULL,
+                payment_method TEXT NOT NULL,
+                currency_unit TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                payment_request TEXT NOT NULL,
+                expiry INTEGER,
+                state TEXT NOT NULL DEFAULT 'UNPAID',
+                time_created TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- DLEQ proof (NUT-12) as json and the witness (NUT-11) of a proof, so they can be sent along with the proof
+ALTER TABLE proofs ADD COLUMN dleq TEXT;
+ALTER TABLE proofs ADD COLUMN witness TEXT;
@@ -0,0 +1,89 @@
+//! Portable backup of the wallet data. The backup is serialized as json and encrypted with a password, so
+//! it can be imported into any localstore.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encryption,
+    error::MokshaWalletError,
+    localstore::{StoredProof, TrustedMint, WalletKeyset, WalletMintQuote, WalletTransaction},
+};
+
+/// Version of the backup format. Backups with a newer version can't be imported. Version 1 only contained
+/// unspent proofs and no mint quotes.
+pub const BACKUP_VERSION: u64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletBackup {
+    pub version: u64,
+    /// unix timestamp in seconds
+    pub created_at: u64,
+    /// keysets including the last index used for deriving secrets
+    pub keysets: Vec<WalletKeyset>,
+    /// proofs in all states. Reserved proofs include the token they were sent with
+    pub proofs: Vec<StoredProof>,
+    pub transactions: Vec<WalletTransaction>,
+    pub trusted_mints: Vec<TrustedMint>,
+    #[serde(default)]
+    pub mint_quotes: Vec<WalletMintQuote>,
+}
+
+impl WalletBackup {
+    pub fn encrypt(&self, password: &str) -> Result<String, MokshaWalletError> {
+        encryption::encrypt(password, &serde_json::to_vec(self)?)
+    }
+
+    pub fn decrypt(backup: &str, password: &str) -> Result<Self, MokshaWalletError> {
+        let json = encryption::decrypt(password, backup.trim())?;
+        let value = serde_json::from_slice::<serde_json::Value>(&json)?;
+        match value["version"].as_u64() {
+            Some(version) if version <= BACKUP_VERSION => Ok(serde_json::from_value(value)?),
+            version => Err(MokshaWalletError::UnsupportedBackupVersion(
+                version.unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Entries that were added to the wallet by an import. Entries that already existed are skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupImportResult {
+    pub keysets: usize,
+    pub proofs: usize,
+    /// total amount of the imported proofs
+    pub amount: u64,
+    pub transactions: usize,
+    pub trusted_mints: usize,
+    pub mint_quotes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WalletBackup;
+    use crate::{encryption, error::MokshaWalletError, localstore::ProofState};
+
+    #[test]
+    fn test_decrypt_version_1() -> anyhow::Result<()> {
+        // version 1 only contained unspent proofs
+        let json = r#"{"version": 1, "created_at": 0, "keysets": [], "transactions": [], "trusted_mints": [],
+            "proofs": [{"amount": 8, "id": "00d31cecf59d18c0", "secret": "secret_1",
+                "C": "02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee"}]}"#;
+        let backup = encryption::encrypt("secret", json.as_bytes())?;
+        let backup = WalletBackup::decrypt(&backup, "secret")?;
+        assert_eq!(1, backup.proofs.len());
+        assert_eq!(ProofState::Unspent, backup.proofs[0].state);
+        assert!(backup.mint_quotes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_unsupported_version() -> anyhow::Result<()> {
+        let backup = encryption::encrypt("secret", br#"{"version": 3}"#)?;
+        let result = WalletBackup::decrypt(&backup, "secret");
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::UnsupportedBackupVersion(3))
+        ));
+        Ok(())
+    }
+}
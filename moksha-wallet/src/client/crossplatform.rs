@@ -4,17 +4,20 @@ use moksha_core::{
     blind::BlindedMessage,
     keyset::Keysets,
     primitives::{
-        CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MintInfoResponse,
-        PostMeltBolt11Request, PostMeltBolt11Response, PostMeltBtcOnchainRequest,
-        PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response,
-        PostMeltQuoteBtcOnchainRequest, PostMeltQuoteBtcOnchainResponse, PostMintBolt11Request,
-        PostMintBolt11Response, PostMintBtcOnchainRequest, PostMintBtcOnchainResponse,
-        PostMintQuoteBolt11Request, PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest,
-        PostMintQuoteBtcOnchainResponse, PostSwapRequest, PostSwapResponse,
+        CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MeltQuoteBolt11Options,
+        MintInfoResponse, PostCheckStateRequest, PostCheckStateResponse, PostMeltBolt11Request,
+        PostMeltBolt11Response, PostMeltBtcOnchainRequest, PostMeltBtcOnchainResponse,
+        PostMeltQuoteBolt11Request, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainRequest,
+        PostMeltQuoteBtcOnchainResponse, PostMintBolt11Request, PostMintBolt11Response,
+        PostMintBtcOnchainRequest, PostMintBtcOnchainResponse, PostMintQuoteBolt11Request,
+        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainRequest,
+        PostMintQuoteBtcOnchainResponse, PostRestoreRequest, PostRestoreResponse, PostSwapRequest,
+        PostSwapResponse,
     },
     proof::Proofs,
 };
 
+use secp256k1::PublicKey;
 use url::Url;
 
 use crate::{error::MokshaWalletError, http::CrossPlatformHttpClient};
@@ -40,6 +43,24 @@ impl CashuClient for CrossPlatformHttpClient {
         self.do_get(&mint_url.join("v1/keysets")?).await
     }
 
+    async fn post_restore(
+        &self,
+        mint_url: &Url,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRestoreResponse, MokshaWalletError> {
+        let body = PostRestoreRequest { outputs };
+        self.do_post(&mint_url.join("v1/restore")?, &body).await
+    }
+
+    async fn post_check_state(
+        &self,
+        mint_url: &Url,
+        ys: Vec<PublicKey>,
+    ) -> Result<PostCheckStateResponse, MokshaWalletError> {
+        let body = PostCheckStateRequest { ys };
+        self.do_post(&mint_url.join("v1/checkstate")?, &body).await
+    }
+
     async fn post_swap(
         &self,
         mint_url: &Url,
@@ -72,10 +93,12 @@ impl CashuClient for CrossPlatformHttpClient {
         mint_url: &Url,
         payment_request: String,
         unit: CurrencyUnit,
+        options: Option<MeltQuoteBolt11Options>,
     ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
         let body = PostMeltQuoteBolt11Request {
             request: payment_request,
             unit,
+            options,
         };
 
         self.do_post(&mint_url.join("v1/melt/quote/bolt11")?, &body)
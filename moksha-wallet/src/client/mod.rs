@@ -3,14 +3,16 @@ use moksha_core::{
     blind::BlindedMessage,
     keyset::Keysets,
     primitives::{
-        CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MintInfoResponse,
-        PostMeltBolt11Response, PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response,
-        PostMeltQuoteBtcOnchainResponse, PostMintBolt11Response, PostMintBtcOnchainResponse,
-        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainResponse, PostSwapResponse,
+        CurrencyUnit, GetMeltBtcOnchainResponse, KeysResponse, MeltQuoteBolt11Options,
+        MintInfoResponse, PostCheckStateResponse, PostMeltBolt11Response,
+        PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainResponse,
+        PostMintBolt11Response, PostMintBtcOnchainResponse, PostMintQuoteBolt11Response,
+        PostMintQuoteBtcOnchainResponse, PostRestoreResponse, PostSwapResponse,
     },
     proof::Proofs,
 };
 
+use secp256k1::PublicKey;
 use url::Url;
 
 use crate::error::MokshaWalletError;
@@ -33,6 +35,20 @@ pub trait CashuClient {
 
     async fn get_keysets(&self, mint_url: &Url) -> Result<Keysets, MokshaWalletError>;
 
+    /// Returns the signatures of the outputs the mint has signed before ([Nut-09](https://github.com/cashubtc/nuts/blob/main/09.md))
+    async fn post_restore(
+        &self,
+        mint_url: &Url,
+        outputs: Vec<BlindedMessage>,
+    ) -> Result<PostRestoreResponse, MokshaWalletError>;
+
+    /// Returns the state of the proofs with the given `Y` values ([Nut-07](https://github.com/cashubtc/nuts/blob/main/07.md))
+    async fn post_check_state(
+        &self,
+        mint_url: &Url,
+        ys: Vec<PublicKey>,
+    ) -> Result<PostCheckStateResponse, MokshaWalletError>;
+
     async fn post_swap(
         &self,
         mint_url: &Url,
@@ -53,6 +69,7 @@ pub trait CashuClient {
         mint_url: &Url,
         payment_request: String,
         unit: CurrencyUnit,
+        options: Option<MeltQuoteBolt11Options>,
     ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError>;
 
     async fn get_melt_quote_bolt11(
@@ -0,0 +1,95 @@
+//! Passphrase based encryption for secrets that are stored in the localstore.
+//!
+//! The key is derived from the passphrase with Argon2id and the data is encrypted with ChaCha20-Poly1305.
+//! Encrypted values are stored as `argon2id$<salt>$<nonce>$<ciphertext>` with hex encoded parts.
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::Rng;
+
+use crate::error::MokshaWalletError;
+
+const PREFIX: &str = "argon2id";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Returns true if the value was created by [`encrypt`]
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(&format!("{PREFIX}$"))
+}
+
+pub fn encrypt(password: &str, plaintext: &[u8]) -> Result<String, MokshaWalletError> {
+    let mut rng = rand::thread_rng();
+    let salt: [u8; SALT_LENGTH] = rng.gen();
+    let nonce: [u8; NONCE_LENGTH] = rng.gen();
+
+    let cipher = cipher(password, &salt)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| MokshaWalletError::Encryption(e.to_string()))?;
+
+    Ok(format!(
+        "{PREFIX}${}${}${}",
+        hex::encode(salt),
+        hex::encode(nonce),
+        hex::encode(ciphertext)
+    ))
+}
+
+pub fn decrypt(password: &str, encrypted: &str) -> Result<Vec<u8>, MokshaWalletError> {
+    let parts = encrypted.split('$').collect::<Vec<_>>();
+    let [PREFIX, salt, nonce, ciphertext] = parts.as_slice() else {
+        return Err(MokshaWalletError::Encryption(
+            "invalid encrypted value".to_owned(),
+        ));
+    };
+    let nonce = hex::decode(nonce)?;
+    if nonce.len() != NONCE_LENGTH {
+        return Err(MokshaWalletError::Encryption("invalid nonce".to_owned()));
+    }
+
+    cipher(password, &hex::decode(salt)?)?
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            hex::decode(ciphertext)?.as_slice(),
+        )
+        .map_err(|_| MokshaWalletError::InvalidPassword)
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, MokshaWalletError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| MokshaWalletError::Encryption(e.to_string()))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt, is_encrypted};
+    use crate::error::MokshaWalletError;
+
+    #[test]
+    fn test_encrypt_decrypt() -> anyhow::Result<()> {
+        let encrypted = encrypt("secret", b"seed words")?;
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(b"seed words".to_vec(), decrypt("secret", &encrypted)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_wrong_password() -> anyhow::Result<()> {
+        let encrypted = encrypt("secret", b"seed words")?;
+        let result = decrypt("wrong", &encrypted);
+        assert!(matches!(result, Err(MokshaWalletError::InvalidPassword)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_encrypted_plaintext() {
+        assert!(!is_encrypted("abandon abandon abandon"));
+    }
+}
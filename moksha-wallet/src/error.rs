@@ -2,6 +2,7 @@ use std::string::FromUtf8Error;
 
 use lightning_invoice::ParseOrSemanticError;
 use thiserror::Error;
+use url::Url;
 
 #[derive(Error, Debug)]
 pub enum MokshaWalletError {
@@ -73,12 +74,15 @@ pub enum MokshaWalletError {
     #[error("Secp256k1 {0}")]
     Secp256k1(#[from] secp256k1::Error),
 
-    #[error("Primarykey not set for keyset")]
+    #[error("Primarykey not set")]
     IdNotSet,
 
     #[error("Found multiple seeds in the database. This is not supported.")]
     MultipleSeeds,
 
+    #[error("The wallet already contains a different seed")]
+    SeedAlreadyExists,
+
     #[error("Not valid hex string")]
     Hex(#[from] hex::FromHexError),
 
@@ -87,4 +91,67 @@ pub enum MokshaWalletError {
 
     #[error("Pubkey not found")]
     PubkeyNotFound,
+
+    #[error("Invalid operation kind {0}")]
+    InvalidOperationKind(String),
+
+    #[error("Invalid transaction kind {0}")]
+    InvalidTransactionKind(String),
+
+    #[error("Invalid mint quote state {0}")]
+    InvalidMintQuoteState(String),
+
+    #[error("Invalid proof state {0}")]
+    InvalidProofState(String),
+
+    #[error("Transfer failed: {0}")]
+    TransferFailed(String),
+
+    #[error("Received {1} (sat) at {0}, but moving them to the preferred mint failed: {2}")]
+    SweepFailed(Url, u64, String),
+
+    #[error("No active keyset found for {0}")]
+    NoActiveKeyset(Url),
+
+    #[error("Unknown keyset {0}")]
+    UnknownKeyset(String),
+
+    #[error("Invalid DLEQ proof")]
+    InvalidDleq,
+
+    #[error("Token is not locked to the public key of the wallet")]
+    NotLockedToWallet,
+
+    #[error("Proof has already been received")]
+    ProofAlreadyReceived,
+
+    #[error("Invalid LNURL {0}")]
+    InvalidLnurl(String),
+
+    #[error("LNURL error: {0}")]
+    Lnurl(String),
+
+    #[error("Invalid invoice from LNURL service: {0}")]
+    InvalidLnurlInvoice(String),
+
+    #[error("Payment request doesn't accept tokens of mint {0}")]
+    MintNotAccepted(Url),
+
+    #[error("Can't pay payment request: {0}")]
+    PaymentRequest(String),
+
+    #[error("No preferred mint set")]
+    NoPreferredMint,
+
+    #[error("Encryption Error {0}")]
+    Encryption(String),
+
+    #[error("Invalid password")]
+    InvalidPassword,
+
+    #[error("Wallet is locked. A password is required to unlock the seed")]
+    WalletLocked,
+
+    #[error("Unsupported backup version {0}")]
+    UnsupportedBackupVersion(u64),
 }
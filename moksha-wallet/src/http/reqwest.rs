@@ -83,6 +83,37 @@ impl CrossPlatformHttpClient {
         Self::extract_response_data::<T>(resp).await
     }
 
+    /// Fetches a json document from a service that is not a mint, e.g. a LNURL endpoint
+    pub async fn get_json(&self, url: &Url) -> Result<Value, MokshaWalletError> {
+        let resp = self.client.get(url.clone()).send().await?;
+        let status = resp.status();
+        let response_text = resp.text().await?;
+        if status != StatusCode::OK {
+            return Err(MokshaWalletError::UnexpectedResponse(response_text));
+        }
+        serde_json::from_str(&response_text)
+            .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
+    }
+
+    /// Posts a json document to a service that is not a mint, e.g. the transport of a payment request
+    pub async fn post_json<B: serde::Serialize>(
+        &self,
+        url: &Url,
+        body: &B,
+    ) -> Result<(), MokshaWalletError> {
+        let resp = self
+            .client
+            .post(url.clone())
+            .header(CONTENT_TYPE, HeaderValue::from_str("application/json")?)
+            .body(serde_json::to_string(body)?)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(MokshaWalletError::UnexpectedResponse(resp.text().await?));
+        }
+        Ok(())
+    }
+
     pub async fn get_status(&self, url: &Url) -> Result<u16, MokshaWalletError> {
         let resp = self.client.get(url.to_owned()).send().await?;
         Ok(resp.status().as_u16())
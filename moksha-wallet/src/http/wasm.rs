@@ -32,6 +32,35 @@ impl CrossPlatformHttpClient {
         Self::extract_response_data::<T>(resp).await
     }
 
+    /// Fetches a json document from a service that is not a mint, e.g. a LNURL endpoint
+    pub async fn get_json(&self, url: &Url) -> Result<serde_json::Value, MokshaWalletError> {
+        let resp = Request::get(url.as_str()).send().await?;
+        let status = resp.status();
+        let response_text = resp.text().await?;
+        if status != 200 {
+            return Err(MokshaWalletError::UnexpectedResponse(response_text));
+        }
+        serde_json::from_str(&response_text)
+            .map_err(|_| MokshaWalletError::UnexpectedResponse(response_text))
+    }
+
+    /// Posts a json document to a service that is not a mint, e.g. the transport of a payment request
+    pub async fn post_json<B: serde::Serialize>(
+        &self,
+        url: &Url,
+        body: &B,
+    ) -> Result<(), MokshaWalletError> {
+        let resp = Request::post(url.as_str())
+            .header("content-type", "application/json")
+            .json(body)?
+            .send()
+            .await?;
+        if !resp.ok() {
+            return Err(MokshaWalletError::UnexpectedResponse(resp.text().await?));
+        }
+        Ok(())
+    }
+
     pub async fn get_status(&self, url: &Url) -> Result<u16, MokshaWalletError> {
         let resp = Request::get(url.as_str()).send().await?;
 
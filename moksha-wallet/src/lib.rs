@@ -1,7 +1,11 @@
+pub mod backup;
 pub mod client;
 pub mod config_path;
+pub mod encryption;
 pub mod error;
 pub mod http;
+pub mod lnurl;
 pub mod localstore;
+pub mod mpp;
 pub mod secret;
 pub mod wallet;
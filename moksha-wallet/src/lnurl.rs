@@ -0,0 +1,278 @@
+//! Resolves Lightning addresses ([LUD-16](https://github.com/lnurl/luds/blob/luds/16.md)) and LNURL-pay links
+//! ([LUD-06](https://github.com/lnurl/luds/blob/luds/06.md)) to bolt11 invoices.
+//!
+//! The invoice returned by the callback is only accepted if it is for the requested amount and commits to the
+//! metadata of the pay request with its description hash.
+
+use std::str::FromStr;
+
+use bech32::FromBase32;
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use secp256k1::hashes::{sha256, Hash};
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::{error::MokshaWalletError, http::CrossPlatformHttpClient};
+
+const LNURL_HRP: &str = "lnurl";
+
+/// Response of the first request of a LNURL-pay flow
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LnurlPayRequest {
+    pub callback: Url,
+    /// minimum amount in millisatoshis
+    pub min_sendable: u64,
+    /// maximum amount in millisatoshis
+    pub max_sendable: u64,
+    /// json encoded array of `[mime type, content]` entries that the invoice commits to
+    pub metadata: String,
+    pub tag: String,
+}
+
+impl LnurlPayRequest {
+    /// Minimum amount in sats
+    pub const fn min_amount(&self) -> u64 {
+        self.min_sendable.div_ceil(1_000)
+    }
+
+    /// Maximum amount in sats
+    pub const fn max_amount(&self) -> u64 {
+        self.max_sendable / 1_000
+    }
+
+    /// Returns the `text/plain` entry of the metadata
+    pub fn description(&self) -> Option<String> {
+        serde_json::from_str::<Vec<(String, Value)>>(&self.metadata)
+            .ok()?
+            .into_iter()
+            .find(|(mime_type, _)| mime_type == "text/plain")
+            .and_then(|(_, content)| content.as_str().map(ToOwned::to_owned))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LnurlCallbackResponse {
+    pr: String,
+}
+
+/// Returns true if the input is a Lightning address or a bech32 encoded LNURL
+pub fn is_lnurl(input: &str) -> bool {
+    let input = strip_lightning_prefix(input);
+    input.to_lowercase().starts_with(LNURL_HRP) || input.contains('@')
+}
+
+/// Returns the url of the pay request for a Lightning address or a bech32 encoded LNURL
+pub fn decode_lnurl(input: &str) -> Result<Url, MokshaWalletError> {
+    let input = strip_lightning_prefix(input);
+    let invalid = || MokshaWalletError::InvalidLnurl(input.to_owned());
+
+    if let Some((user, domain)) = input.split_once('@') {
+        if user.is_empty() || domain.is_empty() {
+            return Err(invalid());
+        }
+        // LUD-16: onion services are reached over http
+        let scheme = if domain.ends_with(".onion") {
+            "http"
+        } else {
+            "https"
+        };
+        return Url::parse(&format!(
+            "{scheme}://{domain}/.well-known/lnurlp/{}",
+            user.to_lowercase()
+        ))
+        .map_err(|_| invalid());
+    }
+
+    let (hrp, data, _) = bech32::decode(input).map_err(|_| invalid())?;
+    if hrp != LNURL_HRP {
+        return Err(invalid());
+    }
+    let url = String::from_utf8(Vec::<u8>::from_base32(&data).map_err(|_| invalid())?)?;
+    Url::parse(&url).map_err(|_| invalid())
+}
+
+/// Checks that the invoice is for `amount_msat` and that its description hash commits to the metadata
+pub fn validate_invoice(
+    invoice: &Bolt11Invoice,
+    amount_msat: u64,
+    metadata: &str,
+) -> Result<(), MokshaWalletError> {
+    if invoice.amount_milli_satoshis() != Some(amount_msat) {
+        return Err(MokshaWalletError::InvalidLnurlInvoice(format!(
+            "expected amount of {amount_msat} msat, got {:?}",
+            invoice.amount_milli_satoshis()
+        )));
+    }
+    let metadata_hash = sha256::Hash::hash(metadata.as_bytes());
+    match invoice.description() {
+        Bolt11InvoiceDescription::Hash(hash) if hash.0[..] == metadata_hash.as_byte_array()[..] => {
+            Ok(())
+        }
+        _ => Err(MokshaWalletError::InvalidLnurlInvoice(
+            "description hash doesn't match the metadata".to_owned(),
+        )),
+    }
+}
+
+impl CrossPlatformHttpClient {
+    /// Fetches the pay request of a Lightning address or LNURL
+    pub async fn get_lnurl_pay_request(
+        &self,
+        lnurl: &str,
+    ) -> Result<LnurlPayRequest, MokshaWalletError> {
+        let response = self.get_lnurl(&decode_lnurl(lnurl)?).await?;
+        let pay_request: LnurlPayRequest = serde_json::from_value(response)?;
+        if pay_request.tag != "payRequest" {
+            return Err(MokshaWalletError::InvalidLnurl(format!(
+                "unsupported tag {}",
+                pay_request.tag
+            )));
+        }
+        Ok(pay_request)
+    }
+
+    /// Requests an invoice for `amount` sats from the callback of the pay request and validates it
+    pub async fn get_lnurl_invoice(
+        &self,
+        pay_request: &LnurlPayRequest,
+        amount: u64,
+    ) -> Result<String, MokshaWalletError> {
+        let amount_msat = amount
+            .checked_mul(1_000)
+            .ok_or_else(|| MokshaWalletError::Lnurl(format!("amount {amount} sat is too large")))?;
+        if amount_msat < pay_request.min_sendable || amount_msat > pay_request.max_sendable {
+            return Err(MokshaWalletError::Lnurl(format!(
+                "amount must be between {} and {} sat",
+                pay_request.min_amount(),
+                pay_request.max_amount()
+            )));
+        }
+
+        let mut callback = pay_request.callback.clone();
+        callback
+            .query_pairs_mut()
+            .append_pair("amount", &amount_msat.to_string());
+        let response: LnurlCallbackResponse =
+            serde_json::from_value(self.get_lnurl(&callback).await?)?;
+
+        let invoice = Bolt11Invoice::from_str(&response.pr)
+            .map_err(|err| MokshaWalletError::DecodeInvoice(response.pr.clone(), err))?;
+        validate_invoice(&invoice, amount_msat, &pay_request.metadata)?;
+        Ok(response.pr)
+    }
+
+    /// Fetches a LNURL endpoint and returns the reason of error responses as error
+    async fn get_lnurl(&self, url: &Url) -> Result<Value, MokshaWalletError> {
+        let response = self.get_json(url).await?;
+        if response["status"].as_str() == Some("ERROR") {
+            return Err(MokshaWalletError::Lnurl(
+                response["reason"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_owned(),
+            ));
+        }
+        Ok(response)
+    }
+}
+
+fn strip_lightning_prefix(input: &str) -> &str {
+    let input = input.trim();
+    match input.get(..10) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("lightning:") => &input[10..],
+        _ => input,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lightning_invoice::Bolt11Invoice;
+    use url::Url;
+
+    use super::{decode_lnurl, is_lnurl, validate_invoice, LnurlPayRequest};
+    use crate::{error::MokshaWalletError, http::CrossPlatformHttpClient};
+
+    #[test]
+    fn test_decode_lnurl() -> anyhow::Result<()> {
+        // example from LUD-01
+        let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+        assert!(is_lnurl(lnurl));
+        assert_eq!(
+            Url::parse("https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df")?,
+            decode_lnurl(&format!("lightning:{lnurl}"))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_lightning_address() -> anyhow::Result<()> {
+        assert!(is_lnurl("Alice@example.com"));
+        assert_eq!(
+            Url::parse("https://example.com/.well-known/lnurlp/alice")?,
+            decode_lnurl("Alice@example.com")?
+        );
+        assert_eq!(
+            Url::parse("http://example.onion/.well-known/lnurlp/bob")?,
+            decode_lnurl("bob@example.onion")?
+        );
+        assert!(matches!(
+            decode_lnurl("@example.com"),
+            Err(MokshaWalletError::InvalidLnurl(_))
+        ));
+        assert!(!is_lnurl("lnbcrt10u1pj"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pay_request_description() -> anyhow::Result<()> {
+        let pay_request: LnurlPayRequest = serde_json::from_str(
+            r#"{
+                "callback": "https://example.com/lnurlp/alice/callback",
+                "minSendable": 1500,
+                "maxSendable": 100000000,
+                "metadata": "[[\"text/plain\",\"Pay to alice\"],[\"text/identifier\",\"alice@example.com\"]]",
+                "tag": "payRequest"
+            }"#,
+        )?;
+        assert_eq!(Some("Pay to alice".to_owned()), pay_request.description());
+        assert_eq!(2, pay_request.min_amount());
+        assert_eq!(100_000, pay_request.max_amount());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_invoice_without_description_hash() -> anyhow::Result<()> {
+        let invoice = Bolt11Invoice::from_str("lnbcrt10u1pjfzuugpp5hmgp79w40upjw5l5n2x2ne4rrj7w2t6r0ksmyszadxk2wg0g7xhqdqqcqzzsxqyz5vqsp5x7zaf09wc8nvz50udasvmhx872xgh0a6g34sj0q6lea0c4emsufq9qyyssq4tzagx79z4yez48t6sg8df98g2rtqmtq2frdf7cedfltn4qpfwu3rrr7774skcxajzshg2vhfkl26cax4r0rcsqg8l33rd4yg7pz0ecqdjpp9r")?;
+        let result = validate_invoice(&invoice, 100_000, "[]");
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::InvalidLnurlInvoice(_))
+        ));
+        let result = validate_invoice(&invoice, 1_000_000, "[]");
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::InvalidLnurlInvoice(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_lnurl_invoice_amount_overflow() -> anyhow::Result<()> {
+        let pay_request = LnurlPayRequest {
+            callback: Url::parse("https://example.com/lnurlp/alice/callback")?,
+            min_sendable: 1_000,
+            max_sendable: u64::MAX,
+            metadata: "[]".to_owned(),
+            tag: "payRequest".to_owned(),
+        };
+        let result = CrossPlatformHttpClient::new()
+            .get_lnurl_invoice(&pay_request, u64::MAX)
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::Lnurl(_))));
+        Ok(())
+    }
+}
@@ -1,8 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use async_trait::async_trait;
-use moksha_core::{keyset::KeysetId, primitives::CurrencyUnit, proof::Proofs};
+use moksha_core::{
+    blind::{BlindedMessage, BlindingFactor},
+    keyset::KeysetId,
+    primitives::{CurrencyUnit, PaymentMethod},
+    proof::{Proof, Proofs},
+};
 use secp256k1::PublicKey;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
 use crate::error::MokshaWalletError;
@@ -13,7 +19,7 @@ pub mod sqlite;
 #[cfg(target_arch = "wasm32")]
 pub mod rexie;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalletKeyset {
     /// primary key
     pub id: Option<u64>,
@@ -24,17 +30,36 @@ pub struct WalletKeyset {
     pub last_index: u64,
     pub public_keys: HashMap<u64, PublicKey>,
     pub active: bool,
+    /// fee in parts per thousand sats per input proof
+    pub input_fee_ppk: u64,
 }
 
-impl WalletKeysetFilter for Vec<WalletKeyset> {
+impl WalletKeysetFilter for [WalletKeyset] {
     fn get_active(&self, mint_url: &Url, currency_unit: &CurrencyUnit) -> Option<&WalletKeyset> {
         self.iter()
             .find(|k| k.mint_url == *mint_url && k.currency_unit == *currency_unit && k.active)
     }
+
+    fn get_by_mint_and_unit(
+        &self,
+        mint_url: &Url,
+        currency_unit: &CurrencyUnit,
+    ) -> Vec<&WalletKeyset> {
+        self.iter()
+            .filter(|k| k.mint_url == *mint_url && k.currency_unit == *currency_unit)
+            .collect()
+    }
 }
 
 pub trait WalletKeysetFilter {
     fn get_active(&self, mint_url: &Url, currency_unit: &CurrencyUnit) -> Option<&WalletKeyset>;
+
+    /// Returns the active and inactive keysets of the mint
+    fn get_by_mint_and_unit(
+        &self,
+        mint_url: &Url,
+        currency_unit: &CurrencyUnit,
+    ) -> Vec<&WalletKeyset>;
 }
 
 impl WalletKeyset {
@@ -54,10 +79,327 @@ impl WalletKeyset {
             last_index,
             public_keys,
             active,
+            input_fee_ppk: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProofState {
+    /// can be used for payments and counts towards the balance
+    Unspent,
+    /// part of a token that was sent, but not claimed yet
+    Reserved,
+    /// used as input in a request to the mint, that has not been completed
+    PendingSpent,
+    /// received offline and not swapped at the mint yet. Doesn't count towards the balance.
+    Unverified,
+}
+
+impl fmt::Display for ProofState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unspent => write!(f, "UNSPENT"),
+            Self::Reserved => write!(f, "RESERVED"),
+            Self::PendingSpent => write!(f, "PENDING_SPENT"),
+            Self::Unverified => write!(f, "UNVERIFIED"),
+        }
+    }
+}
+
+impl FromStr for ProofState {
+    type Err = MokshaWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UNSPENT" => Ok(Self::Unspent),
+            "RESERVED" => Ok(Self::Reserved),
+            "PENDING_SPENT" => Ok(Self::PendingSpent),
+            "UNVERIFIED" => Ok(Self::Unverified),
+            _ => Err(MokshaWalletError::InvalidProofState(s.to_owned())),
+        }
+    }
+}
+
+/// A proof together with its state. Proofs that were stored before states were tracked are unspent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredProof {
+    #[serde(flatten)]
+    pub proof: Proof,
+    #[serde(default = "unspent")]
+    pub state: ProofState,
+    /// the token a reserved proof was sent with
+    pub token: Option<String>,
+}
+
+const fn unspent() -> ProofState {
+    ProofState::Unspent
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Swap,
+    MintBolt11,
+    MintOnchain,
+    MeltBolt11,
+    MeltOnchain,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Swap => write!(f, "swap"),
+            Self::MintBolt11 => write!(f, "mint_bolt11"),
+            Self::MintOnchain => write!(f, "mint_onchain"),
+            Self::MeltBolt11 => write!(f, "melt_bolt11"),
+            Self::MeltOnchain => write!(f, "melt_onchain"),
+        }
+    }
+}
+
+impl FromStr for OperationKind {
+    type Err = MokshaWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(Self::Swap),
+            "mint_bolt11" => Ok(Self::MintBolt11),
+            "mint_onchain" => Ok(Self::MintOnchain),
+            "melt_bolt11" => Ok(Self::MeltBolt11),
+            "melt_onchain" => Ok(Self::MeltOnchain),
+            _ => Err(MokshaWalletError::InvalidOperationKind(s.to_owned())),
+        }
+    }
+}
+
+/// A blinded message together with everything needed to unblind the mint's signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOutput {
+    /// counter used for deriving the secret and blinding factor from the master key
+    pub counter: u32,
+    pub secret: String,
+    pub blinding_factor: BlindingFactor,
+    pub blinded_message: BlindedMessage,
+}
+
+/// A request to the mint that is journaled before it is sent, so it can be recovered after a crash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletOperation {
+    /// primary key
+    pub id: Option<u64>,
+    pub kind: OperationKind,
+    pub mint_url: Url,
+    pub keyset_id: KeysetId,
+    pub quote_id: Option<String>,
+    /// proofs sent to the mint
+    pub inputs: Proofs,
+    pub outputs: Vec<PendingOutput>,
+}
+
+impl WalletOperation {
+    pub fn new(
+        kind: OperationKind,
+        wallet_keyset: &WalletKeyset,
+        quote_id: Option<String>,
+        inputs: Proofs,
+        outputs: Vec<PendingOutput>,
+    ) -> Self {
+        Self {
+            id: None,
+            kind,
+            mint_url: wallet_keyset.mint_url.to_owned(),
+            keyset_id: wallet_keyset.keyset_id.to_owned(),
+            quote_id,
+            inputs,
+            outputs,
+        }
+    }
+
+    pub fn blinded_messages(&self) -> Vec<BlindedMessage> {
+        self.outputs
+            .iter()
+            .map(|output| output.blinded_message.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MintQuoteState {
+    /// the invoice or address has not been paid yet
+    Unpaid,
+    /// the tokens have been minted
+    Issued,
+    /// the quote expired before it was paid
+    Expired,
+    /// the mint rejected the quote, e.g. because it doesn't know it
+    Failed,
+}
+
+impl fmt::Display for MintQuoteState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unpaid => write!(f, "UNPAID"),
+            Self::Issued => write!(f, "ISSUED"),
+            Self::Expired => write!(f, "EXPIRED"),
+            Self::Failed => write!(f, "FAILED"),
+        }
+    }
+}
+
+impl FromStr for MintQuoteState {
+    type Err = MokshaWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UNPAID" => Ok(Self::Unpaid),
+            "ISSUED" => Ok(Self::Issued),
+            "EXPIRED" => Ok(Self::Expired),
+            "FAILED" => Ok(Self::Failed),
+            _ => Err(MokshaWalletError::InvalidMintQuoteState(s.to_owned())),
         }
     }
 }
 
+/// A mint quote created by the wallet. It is stored until the tokens are minted or the quote expires.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletMintQuote {
+    pub quote_id: String,
+    pub mint_url: Url,
+    pub payment_method: PaymentMethod,
+    pub currency_unit: CurrencyUnit,
+    pub amount: u64,
+    /// bolt11 invoice or bitcoin address
+    pub payment_request: String,
+    /// unix timestamp in seconds
+    pub expiry: Option<u64>,
+    pub state: MintQuoteState,
+}
+
+impl WalletMintQuote {
+    pub fn is_expired(&self) -> bool {
+        self.expiry.map_or(false, |expiry| {
+            expiry < chrono::Utc::now().timestamp() as u64
+        })
+    }
+}
+
+/// A mint the wallet is allowed to hold a balance at. Tokens of other mints are moved to the preferred mint on receive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedMint {
+    pub mint_url: Url,
+    pub preferred: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Mint,
+    Melt,
+    Send,
+    Receive,
+    Swap,
+}
+
+impl fmt::Display for TransactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mint => write!(f, "mint"),
+            Self::Melt => write!(f, "melt"),
+            Self::Send => write!(f, "send"),
+            Self::Receive => write!(f, "receive"),
+            Self::Swap => write!(f, "swap"),
+        }
+    }
+}
+
+impl FromStr for TransactionKind {
+    type Err = MokshaWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mint" => Ok(Self::Mint),
+            "melt" => Ok(Self::Melt),
+            "send" => Ok(Self::Send),
+            "receive" => Ok(Self::Receive),
+            "swap" => Ok(Self::Swap),
+            _ => Err(MokshaWalletError::InvalidTransactionKind(s.to_owned())),
+        }
+    }
+}
+
+/// An entry in the transaction history of the wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletTransaction {
+    /// primary key
+    pub id: Option<u64>,
+    pub kind: TransactionKind,
+    pub amount: u64,
+    pub fee: u64,
+    pub mint_url: Url,
+    pub currency_unit: CurrencyUnit,
+    pub memo: Option<String>,
+    /// bolt11 invoice or bitcoin address
+    pub payment_request: Option<String>,
+    /// payment preimage or bitcoin txid
+    pub payment_proof: Option<String>,
+    /// unix timestamp in seconds
+    pub timestamp: u64,
+}
+
+impl WalletTransaction {
+    pub fn new(kind: TransactionKind, wallet_keyset: &WalletKeyset, amount: u64) -> Self {
+        Self {
+            id: None,
+            kind,
+            amount,
+            fee: 0,
+            mint_url: wallet_keyset.mint_url.to_owned(),
+            currency_unit: wallet_keyset.currency_unit.clone(),
+            memo: None,
+            payment_request: None,
+            payment_proof: None,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+        }
+    }
+}
+
+/// Filter and paging for the transaction history. Transactions are returned newest first.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub kind: Option<TransactionKind>,
+    pub mint_url: Option<Url>,
+    pub currency_unit: Option<CurrencyUnit>,
+    /// unix timestamp in seconds (inclusive)
+    pub since: Option<u64>,
+    /// unix timestamp in seconds (exclusive)
+    pub until: Option<u64>,
+    pub limit: Option<u64>,
+    pub offset: u64,
+}
+
+impl TransactionFilter {
+    pub fn matches(&self, transaction: &WalletTransaction) -> bool {
+        self.kind.map_or(true, |kind| kind == transaction.kind)
+            && self
+                .mint_url
+                .as_ref()
+                .map_or(true, |mint_url| *mint_url == transaction.mint_url)
+            && self
+                .currency_unit
+                .as_ref()
+                .map_or(true, |unit| *unit == transaction.currency_unit)
+            && self
+                .since
+                .map_or(true, |since| transaction.timestamp >= since)
+            && self
+                .until
+                .map_or(true, |until| transaction.timestamp < until)
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[async_trait(?Send)]
 pub trait LocalStore {
@@ -73,11 +415,53 @@ pub trait LocalStore {
         tx: &mut sqlx::Transaction<Self::DB>,
         proofs: &Proofs,
     ) -> Result<(), MokshaWalletError>;
+    /// Returns all unspent proofs
     async fn get_proofs(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Proofs, MokshaWalletError>;
 
+    /// Returns the secrets of all stored proofs, regardless of their state
+    async fn get_proof_secrets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<String>, MokshaWalletError>;
+
+    /// Returns all proofs together with their state
+    async fn get_stored_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<StoredProof>, MokshaWalletError>;
+
+    /// Returns all proofs that were received offline and not swapped yet
+    async fn get_unverified_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Proofs, MokshaWalletError>;
+
+    /// Updates the state of the given proofs that are currently in state `from`
+    async fn update_proof_state(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        from: ProofState,
+        to: ProofState,
+    ) -> Result<(), MokshaWalletError>;
+
+    /// Marks the proofs as reserved and stores the serialized token they were sent in
+    async fn reserve_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        token: &str,
+    ) -> Result<(), MokshaWalletError>;
+
+    /// Returns the serialized tokens of all reserved proofs
+    async fn get_reserved_tokens(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<String>, MokshaWalletError>;
+
     async fn get_keysets(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
@@ -104,6 +488,87 @@ pub trait LocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Option<String>, MokshaWalletError>;
+
+    /// Replaces the stored seed, e.g. after it was encrypted with a new password
+    async fn update_seed(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        seed_words: &str,
+    ) -> Result<(), MokshaWalletError>;
+
+    /// Returns the primary key of the new operation
+    async fn add_operation(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        operation: &WalletOperation,
+    ) -> Result<u64, MokshaWalletError>;
+
+    async fn delete_operation(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        operation: &WalletOperation,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn get_operations(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletOperation>, MokshaWalletError>;
+
+    /// Returns the primary key of the new transaction
+    async fn add_transaction(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        transaction: &WalletTransaction,
+    ) -> Result<u64, MokshaWalletError>;
+
+    async fn get_transactions(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<WalletTransaction>, MokshaWalletError>;
+
+    async fn get_trusted_mints(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TrustedMint>, MokshaWalletError>;
+
+    /// Adds or updates the mint. If the mint is preferred, all other mints are no longer preferred
+    async fn upsert_trusted_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        trusted_mint: &TrustedMint,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn delete_trusted_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        mint_url: &Url,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn add_mint_quote(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote: &WalletMintQuote,
+    ) -> Result<(), MokshaWalletError>;
+
+    /// Returns the quotes in state [`MintQuoteState::Unpaid`]
+    async fn get_pending_mint_quotes(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError>;
+
+    /// Returns the quotes in all states
+    async fn get_mint_quotes(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError>;
+
+    async fn update_mint_quote_state(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &str,
+        state: MintQuoteState,
+    ) -> Result<(), MokshaWalletError>;
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -135,6 +600,41 @@ pub trait LocalStore {
     ) -> Result<(), MokshaWalletError>;
     async fn get_proofs(&self, tx: &mut RexieTransaction) -> Result<Proofs, MokshaWalletError>;
 
+    async fn get_proof_secrets(
+        &self,
+        tx: &mut RexieTransaction,
+    ) -> Result<Vec<String>, MokshaWalletError>;
+
+    async fn get_stored_proofs(
+        &self,
+        tx: &mut RexieTransaction,
+    ) -> Result<Vec<StoredProof>, MokshaWalletError>;
+
+    async fn get_unverified_proofs(
+        &self,
+        tx: &mut RexieTransaction,
+    ) -> Result<Proofs, MokshaWalletError>;
+
+    async fn update_proof_state(
+        &self,
+        _tx: &mut RexieTransaction,
+        proofs: &Proofs,
+        from: ProofState,
+        to: ProofState,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn reserve_proofs(
+        &self,
+        _tx: &mut RexieTransaction,
+        proofs: &Proofs,
+        token: &str,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn get_reserved_tokens(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Vec<String>, MokshaWalletError>;
+
     async fn get_keysets(
         &self,
         _tx: &mut RexieTransaction,
@@ -162,6 +662,82 @@ pub trait LocalStore {
         &self,
         _tx: &mut RexieTransaction,
     ) -> Result<Option<String>, MokshaWalletError>;
+
+    async fn update_seed(
+        &self,
+        _tx: &mut RexieTransaction,
+        seed_words: &str,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn add_operation(
+        &self,
+        _tx: &mut RexieTransaction,
+        operation: &WalletOperation,
+    ) -> Result<u64, MokshaWalletError>;
+
+    async fn delete_operation(
+        &self,
+        _tx: &mut RexieTransaction,
+        operation: &WalletOperation,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn get_operations(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Vec<WalletOperation>, MokshaWalletError>;
+
+    async fn add_transaction(
+        &self,
+        _tx: &mut RexieTransaction,
+        transaction: &WalletTransaction,
+    ) -> Result<u64, MokshaWalletError>;
+
+    async fn get_transactions(
+        &self,
+        _tx: &mut RexieTransaction,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<WalletTransaction>, MokshaWalletError>;
+
+    async fn get_trusted_mints(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Vec<TrustedMint>, MokshaWalletError>;
+
+    async fn upsert_trusted_mint(
+        &self,
+        _tx: &mut RexieTransaction,
+        trusted_mint: &TrustedMint,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn delete_trusted_mint(
+        &self,
+        _tx: &mut RexieTransaction,
+        mint_url: &Url,
+    ) -> Result<(), MokshaWalletError>;
+
+    async fn add_mint_quote(
+        &self,
+        _tx: &mut RexieTransaction,
+        quote: &WalletMintQuote,
+    ) -> Result<(), MokshaWalletError>;
+
+    /// Returns the quotes in state [`MintQuoteState::Unpaid`]
+    async fn get_pending_mint_quotes(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError>;
+
+    async fn get_mint_quotes(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError>;
+
+    async fn update_mint_quote_state(
+        &self,
+        _tx: &mut RexieTransaction,
+        quote_id: &str,
+        state: MintQuoteState,
+    ) -> Result<(), MokshaWalletError>;
 }
 
 #[cfg(test)]
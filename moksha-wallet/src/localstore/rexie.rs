@@ -1,14 +1,26 @@
-use super::{LocalStore, RexieTransaction, WalletKeyset};
+use super::{
+    LocalStore, MintQuoteState, ProofState, RexieTransaction, StoredProof, TransactionFilter,
+    TrustedMint, WalletKeyset, WalletMintQuote, WalletOperation, WalletTransaction,
+};
 use crate::error::MokshaWalletError;
 use async_trait::async_trait;
 use moksha_core::proof::{Proof, Proofs};
 use rexie::*;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
 use wasm_bindgen::JsValue;
 
 #[derive(Clone, Default)]
 pub struct RexieLocalStore;
 
 const STORE_NAME: &str = "proofs";
+const OPERATIONS_STORE_NAME: &str = "operations";
+const TRANSACTIONS_STORE_NAME: &str = "transactions";
+const TRUSTED_MINTS_STORE_NAME: &str = "trusted_mints";
+const SEED_STORE_NAME: &str = "seed";
+const SEED_KEY: &str = "seed";
+const KEYSETS_STORE_NAME: &str = "keysets";
+const MINT_QUOTES_STORE_NAME: &str = "mint_quotes";
 
 impl RexieLocalStore {
     pub async fn new() -> Self {
@@ -19,8 +31,14 @@ impl RexieLocalStore {
 impl RexieLocalStore {
     async fn get_rexie() -> Rexie {
         Rexie::builder("moksha")
-            .version(1)
+            .version(7)
             .add_object_store(ObjectStore::new(STORE_NAME))
+            .add_object_store(ObjectStore::new(OPERATIONS_STORE_NAME).auto_increment(true))
+            .add_object_store(ObjectStore::new(TRANSACTIONS_STORE_NAME).auto_increment(true))
+            .add_object_store(ObjectStore::new(TRUSTED_MINTS_STORE_NAME))
+            .add_object_store(ObjectStore::new(SEED_STORE_NAME))
+            .add_object_store(ObjectStore::new(KEYSETS_STORE_NAME).auto_increment(true))
+            .add_object_store(ObjectStore::new(MINT_QUOTES_STORE_NAME).auto_increment(true))
             .build()
             .await
             .unwrap()
@@ -33,6 +51,105 @@ impl RexieLocalStore {
         let key = serde_json::to_string(&key).unwrap();
         serde_wasm_bindgen::to_value(&key).unwrap()
     }
+
+    /// Returns the keys and values of a store. Values are stored as json strings.
+    async fn get_all<T: DeserializeOwned>(
+        store_name: &str,
+    ) -> std::result::Result<Vec<(JsValue, T)>, MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[store_name], rexie::TransactionMode::ReadOnly)
+            .expect("db error");
+        let store = transaction.store(store_name).expect("db error");
+        let all = store
+            .get_all(None, None, None, None)
+            .await
+            .unwrap_or_default();
+
+        all.into_iter()
+            .map(|(key, value)| {
+                let json: String = serde_wasm_bindgen::from_value(value).unwrap();
+                Ok((key, serde_json::from_str(&json)?))
+            })
+            .collect()
+    }
+
+    /// Inserts or replaces a value. Stores with auto increment generate the key if it is `None`.
+    async fn put<T: Serialize>(
+        store_name: &str,
+        value: &T,
+        key: Option<&JsValue>,
+    ) -> std::result::Result<JsValue, MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[store_name], rexie::TransactionMode::ReadWrite)
+            .expect("db error");
+        let store = transaction.store(store_name).expect("db error");
+        let json = serde_json::to_string(value)?;
+        let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
+
+        let key = store.put(&js_value, key).await.expect("db store error");
+        transaction.done().await.expect("db error");
+        Ok(key)
+    }
+
+    async fn delete(store_name: &str, key: &JsValue) -> std::result::Result<(), MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction = db
+            .transaction(&[store_name], rexie::TransactionMode::ReadWrite)
+            .expect("db error");
+        let store = transaction.store(store_name).expect("db error");
+
+        store.delete(key).await.expect("db error");
+        transaction.done().await.expect("db error");
+        Ok(())
+    }
+
+    async fn get_proofs_by_state(
+        state: ProofState,
+    ) -> std::result::Result<Proofs, MokshaWalletError> {
+        Ok(Self::get_all::<StoredProof>(STORE_NAME)
+            .await?
+            .into_iter()
+            .filter(|(_, stored)| stored.state == state)
+            .map(|(_, stored)| stored.proof)
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    /// Changes the state of the stored proofs with the secrets of `proofs` that match `filter`. The token is
+    /// only replaced if one is given.
+    async fn update_proofs(
+        proofs: &Proofs,
+        filter: impl Fn(&StoredProof) -> bool,
+        state: ProofState,
+        token: Option<&str>,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        let secrets = proofs
+            .proofs()
+            .into_iter()
+            .map(|proof| proof.secret)
+            .collect::<Vec<_>>();
+        for (key, stored) in Self::get_all::<StoredProof>(STORE_NAME).await? {
+            if secrets.contains(&stored.proof.secret) && filter(&stored) {
+                Self::put(
+                    STORE_NAME,
+                    &StoredProof {
+                        state,
+                        token: token.map(ToOwned::to_owned).or(stored.token),
+                        proof: stored.proof,
+                    },
+                    Some(&key),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn id_of(key: &JsValue) -> Option<u64> {
+        key.as_f64().map(|id| id as u64)
+    }
 }
 
 #[async_trait(?Send)]
@@ -50,7 +167,12 @@ impl LocalStore for RexieLocalStore {
                 .transaction(&[STORE_NAME], rexie::TransactionMode::ReadWrite)
                 .expect("db error");
             let store = transaction.store(STORE_NAME).expect("db error");
-            let json = serde_json::to_string(&proof).unwrap();
+            let json = serde_json::to_string(&StoredProof {
+                proof: proof.clone(),
+                state: ProofState::Unspent,
+                token: None,
+            })
+            .unwrap();
             let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
 
             store
@@ -67,24 +189,7 @@ impl LocalStore for RexieLocalStore {
         &self,
         _tx: &mut RexieTransaction,
     ) -> std::result::Result<Proofs, MokshaWalletError> {
-        let db = Self::get_rexie().await;
-        let transaction = db
-            .transaction(&[STORE_NAME], rexie::TransactionMode::ReadOnly)
-            .expect("db error");
-        let store = transaction.store(STORE_NAME).expect("db error");
-        let all = store.get_all(None, None, None, None).await;
-        match all {
-            Ok(all) => {
-                let mut proofs = vec![];
-                for (_, proof) in all {
-                    let proof: String = serde_wasm_bindgen::from_value(proof).unwrap();
-                    let proof = serde_json::from_str::<Proof>(&proof).unwrap();
-                    proofs.push(proof);
-                }
-                Ok(Proofs::new(proofs))
-            }
-            Err(_) => Ok(Proofs::new(vec![])),
-        }
+        Self::get_proofs_by_state(ProofState::Unspent).await
     }
 
     async fn delete_proofs(
@@ -110,41 +215,498 @@ impl LocalStore for RexieLocalStore {
         Ok(())
     }
 
+    async fn get_proof_secrets(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<String>, MokshaWalletError> {
+        Ok(Self::get_all::<StoredProof>(STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, stored)| stored.proof.secret)
+            .collect())
+    }
+
+    async fn get_stored_proofs(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<StoredProof>, MokshaWalletError> {
+        Ok(Self::get_all::<StoredProof>(STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, stored)| stored)
+            .collect())
+    }
+
+    async fn get_unverified_proofs(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Proofs, MokshaWalletError> {
+        Self::get_proofs_by_state(ProofState::Unverified).await
+    }
+
+    async fn update_proof_state(
+        &self,
+        _tx: &mut RexieTransaction,
+        proofs: &Proofs,
+        from: ProofState,
+        to: ProofState,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        Self::update_proofs(proofs, |stored| stored.state == from, to, None).await
+    }
+
+    async fn reserve_proofs(
+        &self,
+        _tx: &mut RexieTransaction,
+        proofs: &Proofs,
+        token: &str,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        Self::update_proofs(proofs, |_| true, ProofState::Reserved, Some(token)).await
+    }
+
+    async fn get_reserved_tokens(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<String>, MokshaWalletError> {
+        let mut tokens = vec![];
+        for (_, stored) in Self::get_all::<StoredProof>(STORE_NAME).await? {
+            match stored.token {
+                Some(token) if stored.state == ProofState::Reserved && !tokens.contains(&token) => {
+                    tokens.push(token)
+                }
+                _ => {}
+            }
+        }
+        Ok(tokens)
+    }
+
     async fn get_keysets(
         &self,
         _tx: &mut RexieTransaction,
     ) -> std::result::Result<Vec<WalletKeyset>, MokshaWalletError> {
-        todo!()
+        Ok(Self::get_all::<WalletKeyset>(KEYSETS_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(key, keyset)| WalletKeyset {
+                id: Self::id_of(&key),
+                ..keyset
+            })
+            .collect())
     }
 
     async fn upsert_keyset(
         &self,
         _tx: &mut RexieTransaction,
-        _keyset: &WalletKeyset,
+        keyset: &WalletKeyset,
     ) -> std::result::Result<(), MokshaWalletError> {
-        todo!()
+        let existing = Self::get_all::<WalletKeyset>(KEYSETS_STORE_NAME)
+            .await?
+            .into_iter()
+            .find(|(_, k)| k.keyset_id == keyset.keyset_id && k.mint_url == keyset.mint_url);
+
+        match existing {
+            // the last index is only changed by update_keyset_last_index
+            Some((key, existing)) => {
+                Self::put(
+                    KEYSETS_STORE_NAME,
+                    &WalletKeyset {
+                        id: None,
+                        last_index: existing.last_index,
+                        ..keyset.clone()
+                    },
+                    Some(&key),
+                )
+                .await?;
+            }
+            None => {
+                Self::put(
+                    KEYSETS_STORE_NAME,
+                    &WalletKeyset {
+                        id: None,
+                        ..keyset.clone()
+                    },
+                    None,
+                )
+                .await?;
+            }
+        }
+        Ok(())
     }
 
     async fn update_keyset_last_index(
         &self,
         _tx: &mut RexieTransaction,
-        _keyset: &WalletKeyset,
+        keyset: &WalletKeyset,
     ) -> std::result::Result<(), MokshaWalletError> {
-        todo!()
+        let id = keyset.id.ok_or(MokshaWalletError::IdNotSet)?;
+        let existing = Self::get_all::<WalletKeyset>(KEYSETS_STORE_NAME)
+            .await?
+            .into_iter()
+            .find(|(key, _)| Self::id_of(key) == Some(id));
+
+        if let Some((key, existing)) = existing {
+            Self::put(
+                KEYSETS_STORE_NAME,
+                &WalletKeyset {
+                    last_index: keyset.last_index,
+                    ..existing
+                },
+                Some(&key),
+            )
+            .await?;
+        }
+        Ok(())
     }
 
     async fn add_seed(
         &self,
         _tx: &mut RexieTransaction,
-        _seed_words: &str,
+        seed_words: &str,
     ) -> std::result::Result<(), MokshaWalletError> {
-        todo!()
+        Self::put(
+            SEED_STORE_NAME,
+            &seed_words,
+            Some(&JsValue::from_str(SEED_KEY)),
+        )
+        .await?;
+        Ok(())
     }
 
     async fn get_seed(
         &self,
         _tx: &mut RexieTransaction,
     ) -> std::result::Result<Option<String>, MokshaWalletError> {
-        todo!()
+        Ok(Self::get_all::<String>(SEED_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, seed_words)| seed_words)
+            .next())
+    }
+
+    async fn update_seed(
+        &self,
+        _tx: &mut RexieTransaction,
+        seed_words: &str,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        // there is only one seed, which is stored under a fixed key
+        Self::put(
+            SEED_STORE_NAME,
+            &seed_words,
+            Some(&JsValue::from_str(SEED_KEY)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn add_operation(
+        &self,
+        _tx: &mut RexieTransaction,
+        operation: &WalletOperation,
+    ) -> std::result::Result<u64, MokshaWalletError> {
+        let key = Self::put(
+            OPERATIONS_STORE_NAME,
+            &WalletOperation {
+                id: None,
+                ..operation.clone()
+            },
+            None,
+        )
+        .await?;
+        Ok(Self::id_of(&key).unwrap_or_default())
+    }
+
+    async fn delete_operation(
+        &self,
+        _tx: &mut RexieTransaction,
+        operation: &WalletOperation,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        let id = operation.id.ok_or(MokshaWalletError::IdNotSet)?;
+        Self::delete(OPERATIONS_STORE_NAME, &JsValue::from_f64(id as f64)).await
+    }
+
+    async fn get_operations(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<WalletOperation>, MokshaWalletError> {
+        let mut operations = Self::get_all::<WalletOperation>(OPERATIONS_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(key, operation)| WalletOperation {
+                id: Self::id_of(&key),
+                ..operation
+            })
+            .collect::<Vec<_>>();
+        operations.sort_by_key(|operation| operation.id);
+        Ok(operations)
+    }
+
+    async fn add_transaction(
+        &self,
+        _tx: &mut RexieTransaction,
+        transaction: &WalletTransaction,
+    ) -> std::result::Result<u64, MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction_db = db
+            .transaction(
+                &[TRANSACTIONS_STORE_NAME],
+                rexie::TransactionMode::ReadWrite,
+            )
+            .expect("db error");
+        let store = transaction_db
+            .store(TRANSACTIONS_STORE_NAME)
+            .expect("db error");
+        let json = serde_json::to_string(&transaction)?;
+        let js_value = serde_wasm_bindgen::to_value(&json).unwrap();
+
+        let key = store.add(&js_value, None).await.expect("db store error");
+        transaction_db.done().await.expect("db error");
+        Ok(key.as_f64().unwrap_or_default() as u64)
+    }
+
+    async fn get_transactions(
+        &self,
+        _tx: &mut RexieTransaction,
+        filter: &TransactionFilter,
+    ) -> std::result::Result<Vec<WalletTransaction>, MokshaWalletError> {
+        let db = Self::get_rexie().await;
+        let transaction_db = db
+            .transaction(&[TRANSACTIONS_STORE_NAME], rexie::TransactionMode::ReadOnly)
+            .expect("db error");
+        let store = transaction_db
+            .store(TRANSACTIONS_STORE_NAME)
+            .expect("db error");
+        let all = store
+            .get_all(None, None, None, None)
+            .await
+            .unwrap_or_default();
+
+        let mut transactions = vec![];
+        for (key, value) in all {
+            let json: String = serde_wasm_bindgen::from_value(value).unwrap();
+            let transaction = WalletTransaction {
+                id: key.as_f64().map(|id| id as u64),
+                ..serde_json::from_str::<WalletTransaction>(&json)?
+            };
+            if filter.matches(&transaction) {
+                transactions.push(transaction);
+            }
+        }
+        transactions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.id.cmp(&a.id)));
+
+        Ok(transactions
+            .into_iter()
+            .skip(filter.offset as usize)
+            .take(filter.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect())
+    }
+
+    async fn get_trusted_mints(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<TrustedMint>, MokshaWalletError> {
+        let mut trusted_mints = Self::get_all::<TrustedMint>(TRUSTED_MINTS_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, trusted_mint)| trusted_mint)
+            .collect::<Vec<_>>();
+        trusted_mints.sort_by(|a, b| a.mint_url.as_str().cmp(b.mint_url.as_str()));
+        Ok(trusted_mints)
+    }
+
+    async fn upsert_trusted_mint(
+        &self,
+        _tx: &mut RexieTransaction,
+        trusted_mint: &TrustedMint,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        if trusted_mint.preferred {
+            for (key, other) in Self::get_all::<TrustedMint>(TRUSTED_MINTS_STORE_NAME).await? {
+                if other.preferred {
+                    Self::put(
+                        TRUSTED_MINTS_STORE_NAME,
+                        &TrustedMint {
+                            preferred: false,
+                            ..other
+                        },
+                        Some(&key),
+                    )
+                    .await?;
+                }
+            }
+        }
+        Self::put(
+            TRUSTED_MINTS_STORE_NAME,
+            trusted_mint,
+            Some(&JsValue::from_str(trusted_mint.mint_url.as_str())),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_trusted_mint(
+        &self,
+        _tx: &mut RexieTransaction,
+        mint_url: &Url,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        Self::delete(
+            TRUSTED_MINTS_STORE_NAME,
+            &JsValue::from_str(mint_url.as_str()),
+        )
+        .await
+    }
+
+    async fn add_mint_quote(
+        &self,
+        _tx: &mut RexieTransaction,
+        quote: &WalletMintQuote,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        // the generated keys keep the quotes in the order they were created
+        Self::put(MINT_QUOTES_STORE_NAME, quote, None).await?;
+        Ok(())
+    }
+
+    async fn get_pending_mint_quotes(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<WalletMintQuote>, MokshaWalletError> {
+        Ok(Self::get_all::<WalletMintQuote>(MINT_QUOTES_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, quote)| quote)
+            .filter(|quote| quote.state == MintQuoteState::Unpaid)
+            .collect())
+    }
+
+    async fn get_mint_quotes(
+        &self,
+        _tx: &mut RexieTransaction,
+    ) -> std::result::Result<Vec<WalletMintQuote>, MokshaWalletError> {
+        Ok(Self::get_all::<WalletMintQuote>(MINT_QUOTES_STORE_NAME)
+            .await?
+            .into_iter()
+            .map(|(_, quote)| quote)
+            .collect())
+    }
+
+    async fn update_mint_quote_state(
+        &self,
+        _tx: &mut RexieTransaction,
+        quote_id: &str,
+        state: MintQuoteState,
+    ) -> std::result::Result<(), MokshaWalletError> {
+        for (key, quote) in Self::get_all::<WalletMintQuote>(MINT_QUOTES_STORE_NAME).await? {
+            if quote.quote_id == quote_id {
+                Self::put(
+                    MINT_QUOTES_STORE_NAME,
+                    &WalletMintQuote { state, ..quote },
+                    Some(&key),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RexieLocalStore;
+    use crate::{
+        backup::BackupImportResult,
+        http::CrossPlatformHttpClient,
+        localstore::{LocalStore, TrustedMint, WalletKeyset},
+        wallet::WalletBuilder,
+    };
+    use moksha_core::{
+        keyset::KeysetId,
+        primitives::CurrencyUnit,
+        proof::{Proof, Proofs},
+    };
+    use std::collections::HashMap;
+    use url::Url;
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_export_import_backup() -> anyhow::Result<()> {
+        rexie::Rexie::delete("moksha").await.expect("db error");
+        let mint_url = Url::parse("http://127.0.0.1:3338")?;
+        let keyset_id = KeysetId::new("00d31cecf59d18c0")?;
+        let c = "02599b9ea0a1ad4143706c2a5a4a568ce442dd4313e1cf1f7f0b58a317c1a355ee".parse()?;
+        let proofs: Proofs = vec![
+            Proof::new(4, "secret_1".to_owned(), c, keyset_id.to_string()),
+            Proof::new(8, "secret_2".to_owned(), c, keyset_id.to_string()),
+        ]
+        .into();
+
+        let localstore = RexieLocalStore::new().await;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .upsert_keyset(
+                &mut tx,
+                &WalletKeyset::new(
+                    &keyset_id,
+                    &mint_url,
+                    &CurrencyUnit::Sat,
+                    10,
+                    HashMap::new(),
+                    true,
+                ),
+            )
+            .await?;
+        localstore.add_proofs(&mut tx, &proofs).await?;
+        // the 8 sat proof was sent, but not claimed yet
+        localstore
+            .reserve_proofs(&mut tx, &proofs.proofs()[1..].to_vec().into(), "cashuAsent")
+            .await?;
+        localstore
+            .upsert_trusted_mint(
+                &mut tx,
+                &TrustedMint {
+                    mint_url: mint_url.clone(),
+                    preferred: true,
+                },
+            )
+            .await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::default()
+            .with_client(CrossPlatformHttpClient::new())
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+        let backup = wallet.export_backup("secret").await?;
+
+        // lose the proofs and the trusted mint
+        let mut tx = localstore.begin_tx().await?;
+        localstore.delete_proofs(&mut tx, &proofs).await?;
+        localstore.delete_trusted_mint(&mut tx, &mint_url).await?;
+        assert!(localstore.get_proof_secrets(&mut tx).await?.is_empty());
+        tx.commit().await?;
+
+        let result = wallet.import_backup(&backup, "secret").await?;
+        assert_eq!(
+            BackupImportResult {
+                keysets: 0,
+                proofs: 2,
+                amount: 12,
+                transactions: 0,
+                trusted_mints: 1,
+                mint_quotes: 0,
+            },
+            result
+        );
+        assert_eq!(4, wallet.get_balance().await?);
+        let mut tx = localstore.begin_tx().await?;
+        assert_eq!(
+            vec!["cashuAsent".to_owned()],
+            localstore.get_reserved_tokens(&mut tx).await?
+        );
+        tx.commit().await?;
+        assert_eq!(10, wallet.get_wallet_keysets().await?[0].last_index);
+        assert_eq!(Some(mint_url), wallet.get_preferred_mint().await?);
+
+        let result = wallet.import_backup(&backup, "secret").await?;
+        assert_eq!(BackupImportResult::default(), result);
+        Ok(())
     }
 }
@@ -7,7 +7,10 @@ use secp256k1::PublicKey;
 use url::Url;
 
 use crate::error::MokshaWalletError;
-use crate::localstore::{LocalStore, WalletKeyset};
+use crate::localstore::{
+    LocalStore, MintQuoteState, ProofState, StoredProof, TransactionFilter, TrustedMint,
+    WalletKeyset, WalletMintQuote, WalletOperation, WalletTransaction,
+};
 
 use sqlx::sqlite::SqliteError;
 
@@ -60,9 +63,10 @@ impl LocalStore for SqliteLocalStore {
         for proof in proofs.proofs() {
             let c = proof.c.to_string();
             let amount = proof.amount as i64;
+            let dleq = proof.dleq.as_ref().map(serde_json::to_string).transpose()?;
             sqlx::query!(
-                "INSERT INTO proofs (keyset_id, amount, C, secret, time_created) VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP);",
-            proof.keyset_id,amount, c, proof.secret )
+                "INSERT INTO proofs (keyset_id, amount, C, secret, dleq, witness, time_created) VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP);",
+            proof.keyset_id,amount, c, proof.secret, dleq, proof.witness )
             .execute(&mut **tx)
             .await?;
         }
@@ -73,24 +77,151 @@ impl LocalStore for SqliteLocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Proofs, MokshaWalletError> {
-        let rows = sqlx::query!("SELECT keyset_id, amount, C, secret FROM proofs;")
-            .fetch_all(&mut **tx)
-            .await?;
+        let rows = sqlx::query!(
+            "SELECT keyset_id, amount, C, secret, dleq, witness FROM proofs WHERE state = 'UNSPENT';"
+        )
+        .fetch_all(&mut **tx)
+        .await?;
 
         // FIXME read time_created
         Ok(rows
             .into_iter()
-            .map(|row| Proof {
-                keyset_id: row.keyset_id,
-                amount: row.amount as u64,
-                c: row.C.parse().expect("Invalid Pubkey"),
-                secret: row.secret,
-                script: None,
+            .map(|row| {
+                Ok(Proof {
+                    keyset_id: row.keyset_id,
+                    amount: row.amount as u64,
+                    c: row.C.parse().expect("Invalid Pubkey"),
+                    secret: row.secret,
+                    script: None,
+                    dleq: row.dleq.as_deref().map(serde_json::from_str).transpose()?,
+                    witness: row.witness,
+                })
+            })
+            .collect::<Result<Vec<Proof>, MokshaWalletError>>()?
+            .into())
+    }
+
+    async fn get_stored_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<StoredProof>, MokshaWalletError> {
+        let rows = sqlx::query!(
+            "SELECT keyset_id, amount, C, secret, dleq, witness, state, token FROM proofs;"
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(StoredProof {
+                    proof: Proof {
+                        keyset_id: row.keyset_id,
+                        amount: row.amount as u64,
+                        c: row.C.parse().expect("Invalid Pubkey"),
+                        secret: row.secret,
+                        script: None,
+                        dleq: row.dleq.as_deref().map(serde_json::from_str).transpose()?,
+                        witness: row.witness,
+                    },
+                    state: row.state.parse()?,
+                    token: row.token,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_unverified_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Proofs, MokshaWalletError> {
+        let rows = sqlx::query!(
+            "SELECT keyset_id, amount, C, secret, dleq, witness FROM proofs WHERE state = 'UNVERIFIED';"
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                Ok(Proof {
+                    keyset_id: row.keyset_id,
+                    amount: row.amount as u64,
+                    c: row.C.parse().expect("Invalid Pubkey"),
+                    secret: row.secret,
+                    script: None,
+                    dleq: row.dleq.as_deref().map(serde_json::from_str).transpose()?,
+                    witness: row.witness,
+                })
             })
-            .collect::<Vec<Proof>>()
+            .collect::<Result<Vec<Proof>, MokshaWalletError>>()?
             .into())
     }
 
+    async fn get_proof_secrets(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<String>, MokshaWalletError> {
+        let rows = sqlx::query!("SELECT secret FROM proofs;")
+            .fetch_all(&mut **tx)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.secret).collect())
+    }
+
+    async fn update_proof_state(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        from: ProofState,
+        to: ProofState,
+    ) -> Result<(), MokshaWalletError> {
+        let placeholders: Vec<String> = (3..=proofs.len() + 2).map(|i| format!("?{}", i)).collect();
+
+        let sql = format!(
+            "UPDATE proofs SET state = ?2 WHERE state = ?1 AND secret IN ({})",
+            placeholders.join(",")
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(from.to_string())
+            .bind(to.to_string());
+        for proof in proofs.proofs() {
+            query = query.bind(proof.secret);
+        }
+        query.execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    async fn reserve_proofs(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        proofs: &Proofs,
+        token: &str,
+    ) -> Result<(), MokshaWalletError> {
+        let state = ProofState::Reserved.to_string();
+        for proof in proofs.proofs() {
+            sqlx::query!(
+                "UPDATE proofs SET state = $1, token = $2 WHERE secret = $3;",
+                state,
+                token,
+                proof.secret
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_reserved_tokens(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<String>, MokshaWalletError> {
+        let rows = sqlx::query!(
+            "SELECT DISTINCT token FROM proofs WHERE state = 'RESERVED' AND token IS NOT NULL;"
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+        Ok(rows.into_iter().filter_map(|row| row.token).collect())
+    }
+
     async fn upsert_keyset(
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
@@ -101,10 +232,11 @@ impl LocalStore for SqliteLocalStore {
         let currency_unit = keyset.currency_unit.to_string();
         let last_index = keyset.last_index as i64;
         let public_keys = serde_json::to_string(&keyset.public_keys)?;
+        let input_fee_ppk = keyset.input_fee_ppk as i64;
         sqlx::query!(
-            r#"INSERT INTO keysets (keyset_id, mint_url, currency_unit, last_index, public_keys, active) VALUES ($1, $2, $3, $4, $5, $6)
-            ON CONFLICT(keyset_id, mint_url) DO UPDATE SET currency_unit = $3, public_keys = $5, active = $6;
-            "#,keyset_id, mint_url, currency_unit, last_index, public_keys, keyset.active)
+            r#"INSERT INTO keysets (keyset_id, mint_url, currency_unit, last_index, public_keys, active, input_fee_ppk) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT(keyset_id, mint_url) DO UPDATE SET currency_unit = $3, public_keys = $5, active = $6, input_fee_ppk = $7;
+            "#,keyset_id, mint_url, currency_unit, last_index, public_keys, keyset.active, input_fee_ppk)
         .execute(&mut **tx)
         .await?;
         Ok(())
@@ -114,7 +246,7 @@ impl LocalStore for SqliteLocalStore {
         &self,
         tx: &mut sqlx::Transaction<Self::DB>,
     ) -> Result<Vec<WalletKeyset>, MokshaWalletError> {
-        let rows = sqlx::query!("SELECT id, mint_url, keyset_id, currency_unit, active, last_index, public_keys FROM keysets;")
+        let rows = sqlx::query!("SELECT id, mint_url, keyset_id, currency_unit, active, last_index, public_keys, input_fee_ppk FROM keysets;")
             .fetch_all(&mut **tx)
             .await?;
 
@@ -139,6 +271,7 @@ impl LocalStore for SqliteLocalStore {
                     active,
                     last_index: last_index as u64,
                     public_keys,
+                    input_fee_ppk: row.input_fee_ppk as u64,
                 })
             })
             .collect::<Result<Vec<WalletKeyset>, SqliteError>>()?)
@@ -189,6 +322,289 @@ impl LocalStore for SqliteLocalStore {
             _ => Err(MokshaWalletError::MultipleSeeds),
         }
     }
+
+    async fn update_seed(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        seed_words: &str,
+    ) -> Result<(), MokshaWalletError> {
+        sqlx::query!("UPDATE seed SET seed_words = $1;", seed_words)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_operation(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        operation: &WalletOperation,
+    ) -> Result<u64, MokshaWalletError> {
+        let kind = operation.kind.to_string();
+        let mint_url = operation.mint_url.as_str();
+        let keyset_id = operation.keyset_id.to_string();
+        let inputs = serde_json::to_string(&operation.inputs)?;
+        let outputs = serde_json::to_string(&operation.outputs)?;
+        let id = sqlx::query!(
+            r#"INSERT INTO operations (kind, mint_url, keyset_id, quote_id, inputs, outputs, time_created)
+            VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP);"#,
+            kind, mint_url, keyset_id, operation.quote_id, inputs, outputs)
+        .execute(&mut **tx)
+        .await?
+        .last_insert_rowid();
+        Ok(id as u64)
+    }
+
+    async fn delete_operation(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        operation: &WalletOperation,
+    ) -> Result<(), MokshaWalletError> {
+        let id = match operation.id {
+            None => return Err(MokshaWalletError::IdNotSet),
+            Some(id) => id as i64,
+        };
+        sqlx::query!("DELETE FROM operations WHERE id = $1;", id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_operations(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletOperation>, MokshaWalletError> {
+        let rows = sqlx::query!(
+            "SELECT id, kind, mint_url, keyset_id, quote_id, inputs, outputs FROM operations ORDER BY id;"
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WalletOperation {
+                    id: Some(row.id as u64),
+                    kind: row.kind.parse()?,
+                    mint_url: Url::parse(&row.mint_url)?,
+                    keyset_id: KeysetId::new(&row.keyset_id)?,
+                    quote_id: row.quote_id,
+                    inputs: serde_json::from_str(&row.inputs)?,
+                    outputs: serde_json::from_str(&row.outputs)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn add_transaction(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        transaction: &WalletTransaction,
+    ) -> Result<u64, MokshaWalletError> {
+        let kind = transaction.kind.to_string();
+        let amount = transaction.amount as i64;
+        let fee = transaction.fee as i64;
+        let mint_url = transaction.mint_url.as_str();
+        let currency_unit = transaction.currency_unit.to_string();
+        let timestamp = transaction.timestamp as i64;
+        let id = sqlx::query!(
+            r#"INSERT INTO transactions (kind, amount, fee, mint_url, currency_unit, memo, payment_request, payment_proof, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);"#,
+            kind, amount, fee, mint_url, currency_unit, transaction.memo, transaction.payment_request, transaction.payment_proof, timestamp)
+        .execute(&mut **tx)
+        .await?
+        .last_insert_rowid();
+        Ok(id as u64)
+    }
+
+    async fn get_transactions(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<WalletTransaction>, MokshaWalletError> {
+        let kind = filter.kind.map(|kind| kind.to_string());
+        let mint_url = filter.mint_url.as_ref().map(|url| url.to_string());
+        let currency_unit = filter.currency_unit.as_ref().map(|unit| unit.to_string());
+        let since = filter.since.map(|since| since as i64);
+        let until = filter.until.map(|until| until as i64);
+        // a negative limit means no limit in sqlite
+        let limit = filter.limit.map_or(-1, |limit| limit as i64);
+        let offset = filter.offset as i64;
+
+        let rows = sqlx::query!(
+            r#"SELECT id as "id!", kind, amount, fee, mint_url, currency_unit, memo, payment_request, payment_proof, timestamp FROM transactions
+            WHERE ($1 IS NULL OR kind = $1) AND ($2 IS NULL OR mint_url = $2) AND ($3 IS NULL OR currency_unit = $3)
+            AND ($4 IS NULL OR timestamp >= $4) AND ($5 IS NULL OR timestamp < $5)
+            ORDER BY timestamp DESC, id DESC LIMIT $6 OFFSET $7;"#,
+            kind, mint_url, currency_unit, since, until, limit, offset)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WalletTransaction {
+                    id: Some(row.id as u64),
+                    kind: row.kind.parse()?,
+                    amount: row.amount as u64,
+                    fee: row.fee as u64,
+                    mint_url: Url::parse(&row.mint_url)?,
+                    currency_unit: row.currency_unit.into(),
+                    memo: row.memo,
+                    payment_request: row.payment_request,
+                    payment_proof: row.payment_proof,
+                    timestamp: row.timestamp as u64,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_trusted_mints(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<TrustedMint>, MokshaWalletError> {
+        let rows = sqlx::query!("SELECT mint_url, preferred FROM trusted_mints ORDER BY mint_url;")
+            .fetch_all(&mut **tx)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TrustedMint {
+                    mint_url: Url::parse(&row.mint_url)?,
+                    preferred: row.preferred,
+                })
+            })
+            .collect()
+    }
+
+    async fn upsert_trusted_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        trusted_mint: &TrustedMint,
+    ) -> Result<(), MokshaWalletError> {
+        if trusted_mint.preferred {
+            sqlx::query!("UPDATE trusted_mints SET preferred = FALSE;")
+                .execute(&mut **tx)
+                .await?;
+        }
+        let mint_url = trusted_mint.mint_url.as_str();
+        sqlx::query!(
+            r#"INSERT INTO trusted_mints (mint_url, preferred) VALUES ($1, $2)
+            ON CONFLICT(mint_url) DO UPDATE SET preferred = excluded.preferred;"#,
+            mint_url,
+            trusted_mint.preferred
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_trusted_mint(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        mint_url: &Url,
+    ) -> Result<(), MokshaWalletError> {
+        let mint_url = mint_url.as_str();
+        sqlx::query!("DELETE FROM trusted_mints WHERE mint_url = $1;", mint_url)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_mint_quote(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote: &WalletMintQuote,
+    ) -> Result<(), MokshaWalletError> {
+        let mint_url = quote.mint_url.as_str();
+        let payment_method = serde_json::to_value(&quote.payment_method)?
+            .as_str()
+            .unwrap_or_default()
+            .to_owned();
+        let currency_unit = quote.currency_unit.to_string();
+        let amount = quote.amount as i64;
+        let expiry = quote.expiry.map(|expiry| expiry as i64);
+        let state = quote.state.to_string();
+        sqlx::query!(
+            r#"INSERT INTO mint_quotes (quote_id, mint_url, payment_method, currency_unit, amount, payment_request, expiry, state, time_created)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP);"#,
+            quote.quote_id, mint_url, payment_method, currency_unit, amount, quote.payment_request, expiry, state)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_pending_mint_quotes(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError> {
+        let rows = sqlx::query!(
+            r#"SELECT quote_id, mint_url, payment_method, currency_unit, amount, payment_request, expiry, state
+            FROM mint_quotes WHERE state = 'UNPAID' ORDER BY time_created;"#
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WalletMintQuote {
+                    quote_id: row.quote_id,
+                    mint_url: Url::parse(&row.mint_url)?,
+                    payment_method: serde_json::from_value(serde_json::Value::String(
+                        row.payment_method,
+                    ))?,
+                    currency_unit: row.currency_unit.into(),
+                    amount: row.amount as u64,
+                    payment_request: row.payment_request,
+                    expiry: row.expiry.map(|expiry| expiry as u64),
+                    state: row.state.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_mint_quotes(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+    ) -> Result<Vec<WalletMintQuote>, MokshaWalletError> {
+        let rows = sqlx::query!(
+            r#"SELECT quote_id, mint_url, payment_method, currency_unit, amount, payment_request, expiry, state
+            FROM mint_quotes ORDER BY time_created;"#
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WalletMintQuote {
+                    quote_id: row.quote_id,
+                    mint_url: Url::parse(&row.mint_url)?,
+                    payment_method: serde_json::from_value(serde_json::Value::String(
+                        row.payment_method,
+                    ))?,
+                    currency_unit: row.currency_unit.into(),
+                    amount: row.amount as u64,
+                    payment_request: row.payment_request,
+                    expiry: row.expiry.map(|expiry| expiry as u64),
+                    state: row.state.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    async fn update_mint_quote_state(
+        &self,
+        tx: &mut sqlx::Transaction<Self::DB>,
+        quote_id: &str,
+        state: MintQuoteState,
+    ) -> Result<(), MokshaWalletError> {
+        let state = state.to_string();
+        sqlx::query!(
+            "UPDATE mint_quotes SET state = $1 WHERE quote_id = $2;",
+            state,
+            quote_id
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
 }
 
 impl SqliteLocalStore {
@@ -218,9 +634,22 @@ impl SqliteLocalStore {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::SqliteLocalStore;
-    use crate::localstore::LocalStore;
-    use moksha_core::{fixture::read_fixture, token::TokenV3};
+    use crate::localstore::{
+        LocalStore, MintQuoteState, OperationKind, ProofState, TransactionFilter, TransactionKind,
+        TrustedMint, WalletKeyset, WalletMintQuote, WalletOperation, WalletTransaction,
+    };
+    use moksha_core::{
+        fixture::read_fixture,
+        keyset::KeysetId,
+        primitives::{CurrencyUnit, PaymentMethod},
+        proof::{Proof, ProofDleq, Proofs},
+        token::TokenV3,
+    };
+    use secp256k1::SecretKey;
+    use url::Url;
 
     #[tokio::test]
     async fn test_add_proofs() -> anyhow::Result<()> {
@@ -239,6 +668,38 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_add_proofs_with_dleq_and_witness() -> anyhow::Result<()> {
+        let db = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = db.begin_tx().await?;
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?
+            .trim()
+            .to_string()
+            .try_into()?;
+        let key = SecretKey::from_slice(&[1; 32])?;
+        let proofs: Proofs = tokens
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                dleq: Some(ProofDleq {
+                    e: key,
+                    s: key,
+                    r: key,
+                }),
+                witness: Some(r#"{"signatures":[]}"#.to_owned()),
+                ..proof
+            })
+            .collect::<Vec<_>>()
+            .into();
+
+        db.add_proofs(&mut tx, &proofs).await?;
+
+        assert_eq!(proofs, db.get_proofs(&mut tx).await?);
+        tx.commit().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_delete_proofs() -> anyhow::Result<()> {
         let localstore = SqliteLocalStore::with_in_memory().await?;
@@ -301,4 +762,257 @@ mod tests {
         tx.commit().await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_proof_states() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+
+        let token = read_fixture("token_60.cashu")?.trim().to_string();
+        let tokens: TokenV3 = token.clone().try_into()?;
+        let proofs = tokens.proofs();
+        localstore.add_proofs(&mut tx, &proofs).await?;
+
+        let proof_4: Proofs = proofs.proofs()[0].clone().into();
+        localstore
+            .update_proof_state(
+                &mut tx,
+                &proof_4,
+                ProofState::Unspent,
+                ProofState::PendingSpent,
+            )
+            .await?;
+        assert_eq!(56, localstore.get_proofs(&mut tx).await?.total_amount());
+
+        // only proofs in the given state are updated
+        localstore
+            .update_proof_state(
+                &mut tx,
+                &proofs,
+                ProofState::PendingSpent,
+                ProofState::Unspent,
+            )
+            .await?;
+        assert_eq!(60, localstore.get_proofs(&mut tx).await?.total_amount());
+
+        // unverified proofs don't count towards the balance
+        localstore
+            .update_proof_state(
+                &mut tx,
+                &proof_4,
+                ProofState::Unspent,
+                ProofState::Unverified,
+            )
+            .await?;
+        assert_eq!(56, localstore.get_proofs(&mut tx).await?.total_amount());
+        assert_eq!(proof_4, localstore.get_unverified_proofs(&mut tx).await?);
+        localstore
+            .update_proof_state(
+                &mut tx,
+                &proof_4,
+                ProofState::Unverified,
+                ProofState::Unspent,
+            )
+            .await?;
+
+        localstore.reserve_proofs(&mut tx, &proofs, &token).await?;
+        assert!(localstore.get_proofs(&mut tx).await?.is_empty());
+        assert_eq!(vec![token], localstore.get_reserved_tokens(&mut tx).await?);
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_delete_operation() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?
+            .trim()
+            .to_string()
+            .try_into()?;
+        let keyset = WalletKeyset::new(
+            &KeysetId::new("00d31cecf59d18c0")?,
+            &Url::parse("http://127.0.0.1:3338")?,
+            &CurrencyUnit::Sat,
+            0,
+            HashMap::new(),
+            true,
+        );
+        let operation = WalletOperation::new(
+            OperationKind::MeltBolt11,
+            &keyset,
+            Some("quote".to_owned()),
+            tokens.proofs(),
+            vec![],
+        );
+        let id = localstore.add_operation(&mut tx, &operation).await?;
+
+        let loaded = localstore.get_operations(&mut tx).await?;
+        assert_eq!(1, loaded.len());
+        let loaded = loaded.first().expect("operation not found");
+        assert_eq!(Some(id), loaded.id);
+        assert_eq!(OperationKind::MeltBolt11, loaded.kind);
+        assert_eq!(keyset.keyset_id, loaded.keyset_id);
+        assert_eq!(tokens.proofs(), loaded.inputs);
+
+        localstore.delete_operation(&mut tx, loaded).await?;
+        assert!(localstore.get_operations(&mut tx).await?.is_empty());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trusted_mints() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        let mint_a = Url::parse("http://127.0.0.1:3338")?;
+        let mint_b = Url::parse("http://127.0.0.1:3339")?;
+
+        localstore
+            .upsert_trusted_mint(
+                &mut tx,
+                &TrustedMint {
+                    mint_url: mint_a.clone(),
+                    preferred: true,
+                },
+            )
+            .await?;
+        localstore
+            .upsert_trusted_mint(
+                &mut tx,
+                &TrustedMint {
+                    mint_url: mint_b.clone(),
+                    preferred: true,
+                },
+            )
+            .await?;
+
+        let trusted_mints = localstore.get_trusted_mints(&mut tx).await?;
+        assert_eq!(
+            vec![
+                TrustedMint {
+                    mint_url: mint_a.clone(),
+                    preferred: false,
+                },
+                TrustedMint {
+                    mint_url: mint_b,
+                    preferred: true,
+                },
+            ],
+            trusted_mints
+        );
+
+        localstore.delete_trusted_mint(&mut tx, &mint_a).await?;
+        assert_eq!(1, localstore.get_trusted_mints(&mut tx).await?.len());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_quotes() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        let quote = WalletMintQuote {
+            quote_id: "quote-1".to_owned(),
+            mint_url: Url::parse("http://127.0.0.1:3338")?,
+            payment_method: PaymentMethod::BtcOnchain,
+            currency_unit: CurrencyUnit::Sat,
+            amount: 1_000,
+            payment_request: "bcrt1qxyz".to_owned(),
+            expiry: Some(1_700_000_000),
+            state: MintQuoteState::Unpaid,
+        };
+        localstore.add_mint_quote(&mut tx, &quote).await?;
+        assert_eq!(
+            vec![quote],
+            localstore.get_pending_mint_quotes(&mut tx).await?
+        );
+
+        localstore
+            .update_mint_quote_state(&mut tx, "quote-1", MintQuoteState::Issued)
+            .await?;
+        assert!(localstore
+            .get_pending_mint_quotes(&mut tx)
+            .await?
+            .is_empty());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+
+        let keyset = WalletKeyset::new(
+            &KeysetId::new("00d31cecf59d18c0")?,
+            &Url::parse("http://127.0.0.1:3338")?,
+            &CurrencyUnit::Sat,
+            0,
+            HashMap::new(),
+            true,
+        );
+        for (i, kind) in [
+            TransactionKind::Mint,
+            TransactionKind::Send,
+            TransactionKind::Melt,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let transaction = WalletTransaction {
+                timestamp: 1_700_000_000 + i as u64,
+                memo: Some(format!("memo {i}")),
+                ..WalletTransaction::new(kind, &keyset, 21)
+            };
+            localstore.add_transaction(&mut tx, &transaction).await?;
+        }
+
+        let all = localstore
+            .get_transactions(&mut tx, &TransactionFilter::default())
+            .await?;
+        assert_eq!(3, all.len());
+        assert_eq!(TransactionKind::Melt, all[0].kind);
+        assert_eq!(Some("memo 2".to_owned()), all[0].memo);
+        assert_eq!(keyset.mint_url, all[0].mint_url);
+
+        let page = localstore
+            .get_transactions(
+                &mut tx,
+                &TransactionFilter {
+                    limit: Some(1),
+                    offset: 1,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(1, page.len());
+        assert_eq!(TransactionKind::Send, page[0].kind);
+
+        let filtered = localstore
+            .get_transactions(
+                &mut tx,
+                &TransactionFilter {
+                    kind: Some(TransactionKind::Mint),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(1, filtered.len());
+        assert!(filtered.iter().all(|t| t.kind == TransactionKind::Mint));
+
+        let since = localstore
+            .get_transactions(
+                &mut tx,
+                &TransactionFilter {
+                    since: Some(1_700_000_001),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(2, since.len());
+        tx.commit().await?;
+        Ok(())
+    }
 }
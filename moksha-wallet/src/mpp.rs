@@ -0,0 +1,105 @@
+//! Planning of multi-path payments (NUT-15), which pay a single lightning invoice from the balances of multiple mints.
+
+use url::Url;
+
+use crate::error::MokshaWalletError;
+
+/// The part of an invoice that is paid by a single mint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MppPart {
+    pub mint_url: Url,
+    pub amount: u64,
+}
+
+/// Splits `amount` across the mints. `capacities` contains the amount each mint can pay including its fees.
+///
+/// Mints with larger capacities are used first, so the payment is split into as few parts as possible.
+pub fn plan_mpp_payment(
+    capacities: &[(Url, u64)],
+    amount: u64,
+) -> Result<Vec<MppPart>, MokshaWalletError> {
+    if amount == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut capacities = capacities
+        .iter()
+        .filter(|(_, capacity)| *capacity > 0)
+        .collect::<Vec<_>>();
+    capacities.sort_by(|(url_a, a), (url_b, b)| b.cmp(a).then_with(|| url_a.cmp(url_b)));
+
+    let mut parts = vec![];
+    let mut remaining = amount;
+    for (mint_url, capacity) in capacities {
+        if remaining == 0 {
+            break;
+        }
+        let part = remaining.min(*capacity);
+        parts.push(MppPart {
+            mint_url: mint_url.clone(),
+            amount: part,
+        });
+        remaining -= part;
+    }
+
+    if remaining > 0 {
+        return Err(MokshaWalletError::NotEnoughTokens);
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{plan_mpp_payment, MppPart};
+    use crate::error::MokshaWalletError;
+
+    fn mint(name: &str) -> Url {
+        Url::parse(&format!("https://{name}.example.com")).expect("invalid url")
+    }
+
+    #[test]
+    fn test_plan_single_mint() -> anyhow::Result<()> {
+        let parts = plan_mpp_payment(&[(mint("a"), 50), (mint("b"), 200)], 100)?;
+        assert_eq!(
+            vec![MppPart {
+                mint_url: mint("b"),
+                amount: 100
+            }],
+            parts
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_largest_mints_first() -> anyhow::Result<()> {
+        let capacities = [
+            (mint("a"), 30),
+            (mint("b"), 60),
+            (mint("c"), 0),
+            (mint("d"), 50),
+        ];
+        let parts = plan_mpp_payment(&capacities, 100)?;
+        assert_eq!(
+            vec![
+                MppPart {
+                    mint_url: mint("b"),
+                    amount: 60
+                },
+                MppPart {
+                    mint_url: mint("d"),
+                    amount: 40
+                }
+            ],
+            parts
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_not_enough_tokens() {
+        let result = plan_mpp_payment(&[(mint("a"), 30), (mint("b"), 60)], 100);
+        assert!(matches!(result, Err(MokshaWalletError::NotEnoughTokens)));
+    }
+}
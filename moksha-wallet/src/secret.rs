@@ -7,6 +7,9 @@ use moksha_core::{blind::BlindingFactor, keyset::KeysetId};
 use rand::Rng;
 use secp256k1::SecretKey;
 
+/// Derivation path of the key that proofs are locked to for receiving them offline (P2PK)
+const P2PK_DERIVATION_PATH: &str = "m/129372'/10'/0'/0'/0'";
+
 enum DerivationType {
     Secret = 0,
     Blinding = 1,
@@ -14,12 +17,14 @@ enum DerivationType {
 
 pub struct DeterministicSecret {
     pub seed: Seed,
+    seed_words: String,
 }
 
 impl Clone for DeterministicSecret {
     fn clone(&self) -> Self {
         Self {
             seed: Seed::new(*self.seed.as_bytes()),
+            seed_words: self.seed_words.clone(),
         }
     }
 }
@@ -28,7 +33,10 @@ impl DeterministicSecret {
     pub fn from_seed_words(seed_words: &str) -> Result<Self, MokshaWalletError> {
         let mnemonic = Mnemonic::from_str(seed_words)?;
         let seed = Seed::new(mnemonic.to_seed(""));
-        Ok(Self { seed })
+        Ok(Self {
+            seed,
+            seed_words: mnemonic.to_string(),
+        })
     }
 
     pub fn from_random_seed() -> Result<Self, MokshaWalletError> {
@@ -36,7 +44,15 @@ impl DeterministicSecret {
         let entropy: [u8; 16] = rng.gen(); // 16 bytes for 12 words mnemonic
         let mnemonic = Mnemonic::from_entropy(&entropy)?;
         let seed = Seed::new(mnemonic.to_seed(""));
-        Ok(Self { seed })
+        Ok(Self {
+            seed,
+            seed_words: mnemonic.to_string(),
+        })
+    }
+
+    /// Returns the BIP39 mnemonic of the seed
+    pub fn seed_words(&self) -> &str {
+        &self.seed_words
     }
 
     pub fn generate_random_seed_words() -> Result<String, MokshaWalletError> {
@@ -80,6 +96,13 @@ impl DeterministicSecret {
             .collect::<Vec<(String, BlindingFactor)>>())
     }
 
+    /// Returns the key that proofs are locked to for receiving them offline
+    pub fn derive_p2pk_key(&self) -> Result<SecretKey, MokshaWalletError> {
+        let derivation_path = bip32::DerivationPath::from_str(P2PK_DERIVATION_PATH)?;
+        let key = XPrv::derive_from_path(&self.seed, &derivation_path)?;
+        Ok(SecretKey::from_slice(&key.private_key().to_bytes())?)
+    }
+
     fn derive_blinding_factor(
         &self,
         keyset_id: u32,
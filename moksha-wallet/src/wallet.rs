@@ -1,34 +1,88 @@
 use moksha_core::{
-    amount::Amount,
-    blind::{BlindedMessage, BlindedSignature, BlindingFactor, TotalAmount},
+    amount::{Amount, SplitAmount, SplitStrategy},
+    blind::{BlindedMessage, BlindedSignature, TotalAmount},
+    coin_selection::CoinSelector,
     dhke::Dhke,
     keyset::KeysetId,
+    p2pk::{P2PKSecret, P2PKWitness},
+    payment_request::{PaymentRequest, PaymentRequestPayload},
     primitives::{
-        CurrencyUnit, MintInfoResponse, PaymentMethod, PostMeltBolt11Response,
-        PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response, PostMeltQuoteBtcOnchainResponse,
-        PostMintQuoteBolt11Response, PostMintQuoteBtcOnchainResponse,
+        CurrencyUnit, MeltQuoteBolt11Options, MintInfoResponse, PaymentMethod,
+        PostMeltBolt11Response, PostMeltBtcOnchainResponse, PostMeltQuoteBolt11Response,
+        PostMeltQuoteBtcOnchainResponse, PostMintQuoteBolt11Response,
+        PostMintQuoteBtcOnchainResponse, SpentState,
     },
     proof::{Proof, Proofs},
     token::TokenV3,
 };
 
-use secp256k1::PublicKey;
 use url::Url;
 
 use crate::{
+    backup::{BackupImportResult, WalletBackup, BACKUP_VERSION},
     client::CashuClient,
+    encryption,
     error::MokshaWalletError,
     http::CrossPlatformHttpClient,
-    localstore::{LocalStore, WalletKeyset},
+    localstore::{
+        LocalStore, MintQuoteState, OperationKind, PendingOutput, ProofState, TransactionFilter,
+        TransactionKind, TrustedMint, WalletKeyset, WalletKeysetFilter, WalletMintQuote,
+        WalletOperation, WalletTransaction,
+    },
+    mpp::plan_mpp_payment,
     secret::DeterministicSecret,
 };
+use futures_util::{future::join_all, FutureExt};
 use lightning_invoice::Bolt11Invoice as LNInvoice;
+use secp256k1::{PublicKey, Secp256k1};
 use std::{
     collections::{HashMap, HashSet},
     str::FromStr,
     vec,
 };
 
+/// Number of times a multi-path payment is re-planned if a mint can't cover its fee reserve
+const MAX_MPP_PLAN_ATTEMPTS: usize = 3;
+
+/// Number of outputs that are sent to the mint per restore request
+const RESTORE_BATCH_SIZE: u32 = 100;
+
+/// Restoring a keyset stops after this many batches in a row without signed outputs
+const RESTORE_EMPTY_BATCHES: u32 = 3;
+
+/// Number of quotes requested while searching the amount that can be moved away from an untrusted mint
+const MAX_SWEEP_QUOTES: usize = 3;
+
+/// Balance of the wallet per mint and currency unit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalletBalance {
+    pub mints: Vec<MintBalance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintBalance {
+    pub mint_url: Url,
+    pub currency_unit: CurrencyUnit,
+    pub amount: u64,
+}
+
+impl WalletBalance {
+    pub fn total(&self, currency_unit: &CurrencyUnit) -> u64 {
+        self.mints
+            .iter()
+            .filter(|b| &b.currency_unit == currency_unit)
+            .map(|b| b.amount)
+            .sum()
+    }
+
+    pub fn by_mint_and_unit(&self, mint_url: &Url, currency_unit: &CurrencyUnit) -> u64 {
+        self.mints
+            .iter()
+            .find(|b| &b.mint_url == mint_url && &b.currency_unit == currency_unit)
+            .map_or(0, |b| b.amount)
+    }
+}
+
 #[derive(Clone)]
 pub struct Wallet<L, C>
 where
@@ -39,6 +93,100 @@ where
     dhke: Dhke,
     localstore: L,
     secret: DeterministicSecret,
+    split_strategy: SplitStrategy,
+}
+
+/// Outcome of recovering an operation from the journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryResult {
+    /// The request was sent again and the resulting proofs with the given amount were stored
+    Replayed(u64),
+    /// The mint had already processed the request. The proofs with the given amount were restored from its signatures
+    Restored(u64),
+    /// The mint did not process the request. Proofs with the given amount are spendable again
+    RolledBack(u64),
+    /// The inputs were spent. The given amount is the amount of the inputs minus the restored change
+    Spent(u64),
+    /// The outcome could not be determined, e.g. because the mint could not be reached. The operation stays in the journal
+    Pending,
+}
+
+/// Outcome of checking a stored mint quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MintQuoteResult {
+    /// The quote was paid and tokens with the given amount were minted
+    Minted(u64),
+    /// The quote has not been paid yet
+    Unpaid,
+    /// The quote expired without being paid
+    Expired,
+    /// The mint rejected the quote, e.g. because it doesn't know it anymore
+    Failed(String),
+    /// The mint could not be reached, the quote is checked again next time
+    Pending,
+}
+
+/// Changes made by refreshing the keysets of a mint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintRefreshResult {
+    /// keysets that were unknown to the wallet
+    pub new_keysets: usize,
+    /// keysets that were active before and are inactive now
+    pub deactivated_keysets: usize,
+    /// amount that was swapped from inactive keysets into the active keysets
+    pub swapped_amount: u64,
+}
+
+/// Number of proofs before and after consolidating the proofs of a mint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsolidationResult {
+    pub proofs_before: usize,
+    pub proofs_after: usize,
+    /// input fee paid for the swap
+    pub fee: u64,
+}
+
+/// Result of swapping the proofs of a mint that were received offline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineSyncResult {
+    pub mint_url: Url,
+    /// amount of the new proofs after deducting the input fees
+    pub amount: u64,
+    /// proofs the mint reports as spent, because the sender spent them already. They are removed from the wallet.
+    pub double_spent: Proofs,
+    /// proofs that were not redeemed, because the mint rejected them without reporting them as spent, their
+    /// state could not be checked or the wallet has no active keyset for their unit. They stay unverified and
+    /// are retried by the next sync.
+    pub unverified: Proofs,
+}
+
+/// Result of reclaiming sent tokens that the receivers did not claim
+#[derive(Debug, Default)]
+pub struct ReclaimResult {
+    /// amount swapped back into the wallet after deducting the input fees
+    pub reclaimed: u64,
+    /// amount the receivers claimed already. These proofs are removed from the wallet
+    pub claimed: u64,
+    /// tokens that could not be reclaimed. They stay reserved and are retried next time
+    pub errors: Vec<(TokenV3, MokshaWalletError)>,
+}
+
+/// Outcome of the part of a multi-path payment that was paid by a single mint
+#[derive(Debug)]
+pub struct MppPaymentResult {
+    pub mint_url: Url,
+    pub amount: u64,
+    /// melt response and the returned fees
+    pub result: Result<(PostMeltBolt11Response, u64), MokshaWalletError>,
+}
+
+/// Result of moving funds from one mint to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferResult {
+    /// amount minted at the destination mint
+    pub amount: u64,
+    /// lightning fees paid at the source mint
+    pub fee: u64,
 }
 
 pub struct WalletBuilder<L, C: CashuClient = CrossPlatformHttpClient>
@@ -48,6 +196,9 @@ where
 {
     client: Option<C>,
     localstore: Option<L>,
+    password: Option<String>,
+    seed_words: Option<String>,
+    split_strategy: SplitStrategy,
 }
 
 impl<L, C> WalletBuilder<L, C>
@@ -59,6 +210,9 @@ where
         Self {
             client: Some(C::default()),
             localstore: None,
+            password: None,
+            seed_words: None,
+            split_strategy: SplitStrategy::default(),
         }
     }
 
@@ -72,6 +226,25 @@ where
         self
     }
 
+    /// Password to unlock an encrypted seed. A new seed is stored encrypted with this password.
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Creates the wallet from an existing BIP39 mnemonic instead of a random seed. Fails if the localstore
+    /// already contains a different seed.
+    pub fn with_seed_words(mut self, seed_words: impl Into<String>) -> Self {
+        self.seed_words = Some(seed_words.into());
+        self
+    }
+
+    /// Denominations of new outputs for swaps and mints. Defaults to [`SplitStrategy::Minimal`].
+    pub fn with_split_strategy(mut self, split_strategy: SplitStrategy) -> Self {
+        self.split_strategy = split_strategy;
+        self
+    }
+
     pub async fn build(self) -> Result<Wallet<L, C>, MokshaWalletError> {
         let client = self.client.unwrap_or_default();
         let localstore = self.localstore.expect("localstore is required");
@@ -79,20 +252,46 @@ where
         let mut tx = localstore.begin_tx().await?;
         let seed_words = localstore.get_seed(&mut tx).await?;
         let seed = match seed_words {
+            Some(seed) if encryption::is_encrypted(&seed) => {
+                let password = self
+                    .password
+                    .as_deref()
+                    .ok_or(MokshaWalletError::WalletLocked)?;
+                String::from_utf8(encryption::decrypt(password, &seed)?)?
+            }
             Some(seed) => seed,
             None => {
-                let seed = DeterministicSecret::generate_random_seed_words()?;
-                localstore.add_seed(&mut tx, &seed).await?;
+                let seed = match self.seed_words {
+                    Some(ref seed_words) => DeterministicSecret::from_seed_words(seed_words)?
+                        .seed_words()
+                        .to_owned(),
+                    None => DeterministicSecret::generate_random_seed_words()?,
+                };
+                let stored_seed = match &self.password {
+                    Some(password) => encryption::encrypt(password, seed.as_bytes())?,
+                    None => seed.clone(),
+                };
+                localstore.add_seed(&mut tx, &stored_seed).await?;
                 seed
             }
         };
 
         tx.commit().await?;
 
+        let secret = DeterministicSecret::from_seed_words(&seed)?;
+        if let Some(seed_words) = self.seed_words {
+            if DeterministicSecret::from_seed_words(&seed_words)?.seed_words()
+                != secret.seed_words()
+            {
+                return Err(MokshaWalletError::SeedAlreadyExists);
+            }
+        }
+
         Ok(Wallet::new(
             client as C,
             localstore,
-            DeterministicSecret::from_seed_words(&seed)?,
+            secret,
+            self.split_strategy,
         ))
     }
 }
@@ -112,15 +311,25 @@ where
     C: CashuClient + Default,
     L: LocalStore,
 {
-    fn new(client: C, localstore: L, secret: DeterministicSecret) -> Self {
+    fn new(
+        client: C,
+        localstore: L,
+        secret: DeterministicSecret,
+        split_strategy: SplitStrategy,
+    ) -> Self {
         Self {
             client,
             dhke: Dhke::new(),
             localstore,
             secret,
+            split_strategy,
         }
     }
 
+    pub const fn split_strategy(&self) -> &SplitStrategy {
+        &self.split_strategy
+    }
+
     pub fn builder() -> WalletBuilder<L, C> {
         WalletBuilder::default()
     }
@@ -130,9 +339,22 @@ where
         mint_url: &Url,
         amount: u64,
     ) -> Result<PostMintQuoteBolt11Response, MokshaWalletError> {
-        self.client
+        let quote = self
+            .client
             .post_mint_quote_bolt11(mint_url, amount, CurrencyUnit::Sat)
-            .await
+            .await?;
+        self.add_mint_quote(WalletMintQuote {
+            quote_id: quote.quote.clone(),
+            mint_url: mint_url.to_owned(),
+            payment_method: PaymentMethod::Bolt11,
+            currency_unit: CurrencyUnit::Sat,
+            amount,
+            payment_request: quote.payment_request.clone(),
+            expiry: quote.expiry,
+            state: MintQuoteState::Unpaid,
+        })
+        .await?;
+        Ok(quote)
     }
 
     pub async fn create_quote_onchain(
@@ -140,9 +362,137 @@ where
         mint_url: &Url,
         amount: u64,
     ) -> Result<PostMintQuoteBtcOnchainResponse, MokshaWalletError> {
-        self.client
+        let quote = self
+            .client
             .post_mint_quote_onchain(mint_url, amount, CurrencyUnit::Sat)
+            .await?;
+        self.add_mint_quote(WalletMintQuote {
+            quote_id: quote.quote.clone(),
+            mint_url: mint_url.to_owned(),
+            payment_method: PaymentMethod::BtcOnchain,
+            currency_unit: CurrencyUnit::Sat,
+            amount,
+            payment_request: quote.address.clone(),
+            expiry: Some(quote.expiry),
+            state: MintQuoteState::Unpaid,
+        })
+        .await?;
+        Ok(quote)
+    }
+
+    /// Stores the quote, so it can be minted by [`Wallet::check_pending_mint_quotes`] after a restart
+    async fn add_mint_quote(&self, quote: WalletMintQuote) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore.add_mint_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mints the tokens of all stored quotes that have been paid in the meantime. Unpaid quotes that expired
+    /// and quotes the mint rejected are no longer checked. Quotes of journaled mint operations are skipped, they are handled by
+    /// [`Wallet::recover_pending_operations`].
+    pub async fn check_pending_mint_quotes(
+        &self,
+    ) -> Result<Vec<(WalletMintQuote, MintQuoteResult)>, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let quotes = self.localstore.get_pending_mint_quotes(&mut tx).await?;
+        let journaled_quotes = self
+            .localstore
+            .get_operations(&mut tx)
+            .await?
+            .into_iter()
+            .filter_map(|operation| operation.quote_id)
+            .collect::<HashSet<_>>();
+        tx.commit().await?;
+
+        let mut results = vec![];
+        for quote in quotes
+            .into_iter()
+            .filter(|quote| !journaled_quotes.contains(&quote.quote_id))
+        {
+            let result = self.check_mint_quote(&quote).await?;
+            results.push((quote, result));
+        }
+        Ok(results)
+    }
+
+    async fn check_mint_quote(
+        &self,
+        quote: &WalletMintQuote,
+    ) -> Result<MintQuoteResult, MokshaWalletError> {
+        let paid = match self
+            .is_quote_paid(
+                &quote.mint_url,
+                &quote.payment_method,
+                quote.quote_id.clone(),
+            )
+            .await
+        {
+            Ok(paid) => paid,
+            // the mint doesn't know the quote anymore
+            Err(e) if is_rejected_by_mint(&e) => return self.fail_mint_quote(quote, e).await,
+            Err(_) if !quote.is_expired() => return Ok(MintQuoteResult::Pending),
+            // an expired quote can't be paid anymore, even if the mint is not reachable
+            Err(_) => false,
+        };
+        if !paid {
+            if !quote.is_expired() {
+                return Ok(MintQuoteResult::Unpaid);
+            }
+            self.close_mint_quote(quote, MintQuoteState::Expired)
+                .await?;
+            return Ok(MintQuoteResult::Expired);
+        }
+
+        let mut wallet_keysets = self.get_wallet_keysets().await?;
+        if wallet_keysets
+            .get_active(&quote.mint_url, &quote.currency_unit)
+            .is_none()
+        {
+            wallet_keysets = self.add_mint_keysets(&quote.mint_url).await?;
+        }
+        let Some(wallet_keyset) = wallet_keysets.get_active(&quote.mint_url, &quote.currency_unit)
+        else {
+            return Ok(MintQuoteResult::Pending);
+        };
+
+        match self
+            .mint_tokens(
+                wallet_keyset,
+                &quote.payment_method,
+                quote.amount.into(),
+                quote.quote_id.clone(),
+            )
             .await
+        {
+            Ok(tokens) => Ok(MintQuoteResult::Minted(tokens.total_amount())),
+            Err(MokshaWalletError::InvoiceNotPaidYet(_, _)) => Ok(MintQuoteResult::Unpaid),
+            Err(e) if is_rejected_by_mint(&e) => self.fail_mint_quote(quote, e).await,
+            Err(_) => Ok(MintQuoteResult::Pending),
+        }
+    }
+
+    /// Marks a quote the mint rejected as failed, so it is no longer checked
+    async fn fail_mint_quote(
+        &self,
+        quote: &WalletMintQuote,
+        err: MokshaWalletError,
+    ) -> Result<MintQuoteResult, MokshaWalletError> {
+        self.close_mint_quote(quote, MintQuoteState::Failed).await?;
+        Ok(MintQuoteResult::Failed(err.to_string()))
+    }
+
+    async fn close_mint_quote(
+        &self,
+        quote: &WalletMintQuote,
+        state: MintQuoteState,
+    ) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .update_mint_quote_state(&mut tx, &quote.quote_id, state)
+            .await?;
+        tx.commit().await?;
+        Ok(())
     }
 
     pub async fn is_quote_paid(
@@ -243,14 +593,17 @@ where
                 }
             };
 
-            let wallet_keyset = WalletKeyset::new(
-                &keyset_id,
-                mint_url,
-                &keyset.unit,
-                0,
-                public_keys,
-                keyset.active,
-            );
+            let wallet_keyset = WalletKeyset {
+                input_fee_ppk: keyset.input_fee_ppk,
+                ..WalletKeyset::new(
+                    &keyset_id,
+                    mint_url,
+                    &keyset.unit,
+                    0,
+                    public_keys,
+                    keyset.active,
+                )
+            };
 
             result.push(wallet_keyset.clone());
             self.localstore
@@ -261,6 +614,177 @@ where
         Ok(result)
     }
 
+    /// Refreshes the keysets of all known mints. Keysets the mint no longer lists or marks as inactive are
+    /// deactivated and their proofs are swapped into the active keyset of the same unit. A mint that can't be
+    /// refreshed, e.g. because it is not reachable, doesn't stop the refresh of the other mints.
+    pub async fn refresh_mints(
+        &self,
+    ) -> Result<Vec<(Url, Result<MintRefreshResult, MokshaWalletError>)>, MokshaWalletError> {
+        let mut results = vec![];
+        for mint_url in self.get_mint_urls().await? {
+            let result = self.refresh_mint(&mint_url).await;
+            results.push((mint_url, result));
+        }
+        Ok(results)
+    }
+
+    async fn refresh_mint(&self, mint_url: &Url) -> Result<MintRefreshResult, MokshaWalletError> {
+        let known_keysets = self
+            .get_wallet_keysets()
+            .await?
+            .into_iter()
+            .filter(|keyset| &keyset.mint_url == mint_url)
+            .collect::<Vec<_>>();
+        let mint_keysets = self.client.get_keysets(mint_url).await?.keysets;
+        let added_keysets = self.add_mint_keysets(mint_url).await?;
+        let new_keysets = added_keysets
+            .iter()
+            .filter(|keyset| {
+                known_keysets
+                    .iter()
+                    .all(|known| known.keyset_id != keyset.keyset_id)
+            })
+            .count();
+
+        // keysets the mint doesn't list anymore can't be used for new outputs
+        let mut tx = self.localstore.begin_tx().await?;
+        for keyset in known_keysets.iter().filter(|keyset| {
+            keyset.active
+                && mint_keysets
+                    .iter()
+                    .all(|mint_keyset| mint_keyset.id != keyset.keyset_id.to_string())
+        }) {
+            self.localstore
+                .upsert_keyset(
+                    &mut tx,
+                    &WalletKeyset {
+                        active: false,
+                        ..keyset.clone()
+                    },
+                )
+                .await?;
+        }
+        tx.commit().await?;
+
+        let keysets = self
+            .get_wallet_keysets()
+            .await?
+            .into_iter()
+            .filter(|keyset| &keyset.mint_url == mint_url)
+            .collect::<Vec<_>>();
+        let deactivated_keysets = known_keysets
+            .iter()
+            .filter(|known| {
+                known.active
+                    && keysets
+                        .iter()
+                        .any(|keyset| keyset.keyset_id == known.keyset_id && !keyset.active)
+            })
+            .count();
+
+        let mut currency_units: Vec<CurrencyUnit> = vec![];
+        for keyset in &keysets {
+            if !currency_units.contains(&keyset.currency_unit) {
+                currency_units.push(keyset.currency_unit.clone());
+            }
+        }
+        let mut swapped_amount = 0;
+        for currency_unit in currency_units {
+            if let Some(active_keyset) = keysets.get_active(mint_url, &currency_unit) {
+                swapped_amount += self.swap_inactive_proofs(&keysets, active_keyset).await?;
+            }
+        }
+
+        Ok(MintRefreshResult {
+            new_keysets,
+            deactivated_keysets,
+            swapped_amount,
+        })
+    }
+
+    /// Swaps the proofs of the mint and unit into a set of denominations with up to `target_count` proofs per
+    /// denomination. Nothing is swapped if that doesn't lower the number of proofs.
+    pub async fn consolidate(
+        &self,
+        mint_url: &Url,
+        currency_unit: &CurrencyUnit,
+        target_count: usize,
+    ) -> Result<ConsolidationResult, MokshaWalletError> {
+        let keysets = self.get_wallet_keysets().await?;
+        let wallet_keyset = keysets
+            .get_active(mint_url, currency_unit)
+            .ok_or_else(|| MokshaWalletError::NoActiveKeyset(mint_url.to_owned()))?;
+        let proofs = self
+            .get_proofs_by_mint_and_unit(mint_url, currency_unit)
+            .await?;
+        let proofs_before = proofs.len();
+
+        let fee = Self::coin_selector(&keysets).fee(&proofs);
+        let amounts =
+            Amount(proofs.total_amount().saturating_sub(fee)).split_with_target(target_count);
+        if fee >= proofs.total_amount() || amounts.len() >= proofs_before {
+            return Ok(ConsolidationResult {
+                proofs_before,
+                proofs_after: proofs_before,
+                fee: 0,
+            });
+        }
+
+        let tokens: TokenV3 = (mint_url.to_owned(), currency_unit.clone(), proofs).into();
+        let (_, consolidated_tokens) = self
+            .swap_split_amounts(wallet_keyset, &tokens, vec![].into(), amounts)
+            .await?;
+        Ok(ConsolidationResult {
+            proofs_before,
+            proofs_after: consolidated_tokens.proofs().len(),
+            fee,
+        })
+    }
+
+    /// Swaps the proofs of inactive keysets with the same mint and unit into the active keyset. Returns the
+    /// amount of the new proofs.
+    async fn swap_inactive_proofs(
+        &self,
+        keysets: &[WalletKeyset],
+        active_keyset: &WalletKeyset,
+    ) -> Result<u64, MokshaWalletError> {
+        let inactive_keyset_ids = keysets
+            .iter()
+            .filter(|keyset| {
+                !keyset.active
+                    && keyset.mint_url == active_keyset.mint_url
+                    && keyset.currency_unit == active_keyset.currency_unit
+            })
+            .map(|keyset| keyset.keyset_id.to_string())
+            .collect::<HashSet<_>>();
+        let proofs: Proofs = self
+            .get_proofs()
+            .await?
+            .proofs()
+            .into_iter()
+            .filter(|proof| inactive_keyset_ids.contains(&proof.keyset_id))
+            .collect::<Vec<_>>()
+            .into();
+        if proofs.is_empty() {
+            return Ok(0);
+        }
+
+        let amount = proofs
+            .total_amount()
+            .saturating_sub(self.input_fee(&proofs).await?);
+        let tokens: TokenV3 = (
+            active_keyset.mint_url.to_owned(),
+            active_keyset.currency_unit.clone(),
+            proofs,
+        )
+            .into();
+        let (_, swapped_tokens) = self
+            .swap_tokens(active_keyset, &tokens, amount.into())
+            .await?;
+        Ok(swapped_tokens.total_amount())
+    }
+
+    /// Returns the total amount of all unspent proofs, regardless of mint and unit
     pub async fn get_balance(&self) -> Result<u64, MokshaWalletError> {
         let mut tx = self.localstore.begin_tx().await?;
         let total_amount = self.localstore.get_proofs(&mut tx).await?.total_amount();
@@ -268,41 +792,218 @@ where
         Ok(total_amount)
     }
 
+    pub async fn get_balance_by_mint_and_unit(
+        &self,
+        mint_url: &Url,
+        currency_unit: &CurrencyUnit,
+    ) -> Result<u64, MokshaWalletError> {
+        Ok(self
+            .get_proofs_by_mint_and_unit(mint_url, currency_unit)
+            .await?
+            .total_amount())
+    }
+
+    /// Returns the balance of every mint and unit the wallet has keysets for
+    pub async fn get_balances(&self) -> Result<WalletBalance, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let all_proofs = self.localstore.get_proofs(&mut tx).await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        tx.commit().await?;
+
+        let mut balances: Vec<MintBalance> = vec![];
+        for keyset in keysets.iter() {
+            let amount = all_proofs
+                .proofs_by_keyset(&keyset.keyset_id)
+                .total_amount();
+            match balances
+                .iter_mut()
+                .find(|b| b.mint_url == keyset.mint_url && b.currency_unit == keyset.currency_unit)
+            {
+                Some(balance) => balance.amount += amount,
+                None => balances.push(MintBalance {
+                    mint_url: keyset.mint_url.clone(),
+                    currency_unit: keyset.currency_unit.clone(),
+                    amount,
+                }),
+            }
+        }
+        balances.sort_by(|a, b| {
+            (a.mint_url.as_str(), a.currency_unit.to_string())
+                .cmp(&(b.mint_url.as_str(), b.currency_unit.to_string()))
+        });
+        Ok(WalletBalance { mints: balances })
+    }
+
+    /// Sends tokens of the mint of the keyset for a payment request and returns the payload for the transport of the
+    /// request. `amount` is only used if the request doesn't specify an amount.
+    pub async fn pay_payment_request(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        request: &PaymentRequest,
+        amount: Option<u64>,
+    ) -> Result<PaymentRequestPayload, MokshaWalletError> {
+        if !request.accepts_mint(&wallet_keyset.mint_url) {
+            return Err(MokshaWalletError::MintNotAccepted(
+                wallet_keyset.mint_url.clone(),
+            ));
+        }
+        if let Some(currency_unit) = &request.currency_unit {
+            if currency_unit != &wallet_keyset.currency_unit {
+                return Err(MokshaWalletError::PaymentRequest(format!(
+                    "unit {currency_unit} is not supported"
+                )));
+            }
+        }
+        let amount = request
+            .amount
+            .or(amount)
+            .ok_or_else(|| MokshaWalletError::PaymentRequest("no amount specified".to_owned()))?;
+
+        let token = self.send_tokens(wallet_keyset, amount).await?;
+        Ok(PaymentRequestPayload {
+            id: request.payment_id.clone(),
+            memo: None,
+            mint: wallet_keyset.mint_url.clone(),
+            unit: wallet_keyset.currency_unit.clone(),
+            proofs: token.proofs(),
+        })
+    }
+
     pub async fn send_tokens(
         &self,
         wallet_keyset: &WalletKeyset,
         amount: u64,
     ) -> Result<TokenV3, MokshaWalletError> {
-        let balance = self.get_balance().await?;
-        if amount > balance {
+        let all_proofs = self
+            .get_proofs_by_mint_and_unit(&wallet_keyset.mint_url, &wallet_keyset.currency_unit)
+            .await?;
+        if amount > all_proofs.total_amount() {
             return Err(MokshaWalletError::NotEnoughTokens);
         }
 
+        let keysets = self.get_wallet_keysets().await?;
+        let selection = Self::coin_selector(&keysets).select_for_send(&all_proofs, amount)?;
+
+        // proofs that match the amount exactly can be sent without swapping them first
+        let result: TokenV3 = if selection.proofs.total_amount() == amount {
+            (
+                wallet_keyset.mint_url.to_owned(),
+                wallet_keyset.currency_unit.clone(),
+                selection.proofs,
+            )
+                .into()
+        } else {
+            let selected_tokens = (wallet_keyset.mint_url.to_owned(), selection.proofs).into();
+            self.swap_tokens(wallet_keyset, &selected_tokens, amount.into())
+                .await?
+                .1
+        };
+
         let mut tx = self.localstore.begin_tx().await?;
-        let all_proofs = self
-            .localstore
-            .get_proofs(&mut tx)
-            .await?
-            .proofs_by_keyset(&wallet_keyset.keyset_id);
+        self.localstore
+            .reserve_proofs(&mut tx, &result.proofs(), &result.serialize()?)
+            .await?;
+        self.localstore
+            .add_transaction(
+                &mut tx,
+                &WalletTransaction {
+                    memo: result.memo.clone(),
+                    ..WalletTransaction::new(TransactionKind::Send, wallet_keyset, amount)
+                },
+            )
+            .await?;
         tx.commit().await?;
+        Ok(result)
+    }
 
-        let selected_proofs = all_proofs.proofs_for_amount(amount)?;
-        let selected_tokens = (wallet_keyset.mint_url.to_owned(), selected_proofs.clone()).into();
+    /// Swaps the proofs of sent tokens back into the wallet, if the receiver didn't claim them yet.
+    /// The mint is asked for the state of the proofs first (Nut-07), proofs that were already claimed are removed.
+    /// A token that fails is kept reserved and reported in the result, the other tokens are reclaimed anyway.
+    pub async fn reclaim_unclaimed_tokens(&self) -> Result<ReclaimResult, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let tokens = self.localstore.get_reserved_tokens(&mut tx).await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        tx.commit().await?;
 
-        let (remaining_tokens, result) = self
-            .swap_tokens(wallet_keyset, &selected_tokens, amount.into())
-            .await?;
+        let mut result = ReclaimResult::default();
+        for token in tokens {
+            let token = TokenV3::deserialize(token)?;
+            match self.reclaim_token(&keysets, &token).await {
+                Ok((reclaimed, claimed)) => {
+                    result.reclaimed += reclaimed;
+                    result.claimed += claimed;
+                }
+                Err(e) => result.errors.push((token, e)),
+            }
+        }
+        Ok(result)
+    }
 
-        let mut tx = self.localstore.begin_tx().await?;
-        self.localstore
-            .delete_proofs(&mut tx, &selected_proofs)
-            .await?;
+    /// Reclaims the unspent proofs of a single token and removes the spent ones.
+    /// Returns the reclaimed amount and the amount claimed by the receiver.
+    async fn reclaim_token(
+        &self,
+        keysets: &[WalletKeyset],
+        token: &TokenV3,
+    ) -> Result<(u64, u64), MokshaWalletError> {
+        let mint_url = token.mint().ok_or(MokshaWalletError::InvalidProofs)?;
+        let currency_unit = token.currency_unit.clone().unwrap_or(CurrencyUnit::Sat);
+        let wallet_keyset = keysets
+            .get_active(&mint_url, &currency_unit)
+            .ok_or_else(|| MokshaWalletError::NoActiveKeyset(mint_url.clone()))?;
+
+        let proofs = token.proofs().proofs();
+        let states = self.proof_states(&mint_url, &proofs).await?;
+        let (spent, unspent) = proofs.into_iter().zip(states).fold(
+            (vec![], vec![]),
+            |(mut spent, mut unspent), (proof, state)| {
+                match state {
+                    SpentState::Spent => spent.push(proof),
+                    SpentState::Unspent => unspent.push(proof),
+                    // the receiver is redeeming the proofs right now, they are checked again next time
+                    SpentState::Pending => {}
+                }
+                (spent, unspent)
+            },
+        );
+
+        let spent: Proofs = spent.into();
+        if !spent.is_empty() {
+            let mut tx = self.localstore.begin_tx().await?;
+            self.localstore.delete_proofs(&mut tx, &spent).await?;
+            tx.commit().await?;
+        }
+
+        let unspent: Proofs = unspent.into();
+        if unspent.is_empty() {
+            return Ok((0, spent.total_amount()));
+        }
+        let fee = Self::coin_selector(keysets).fee(&unspent);
+        let unclaimed: TokenV3 = (mint_url, currency_unit, unspent).into();
+        self.swap_tokens(
+            wallet_keyset,
+            &unclaimed,
+            unclaimed.total_amount().saturating_sub(fee).into(),
+        )
+        .await?;
 
+        let mut tx = self.localstore.begin_tx().await?;
         self.localstore
-            .add_proofs(&mut tx, &remaining_tokens.proofs())
+            .add_transaction(
+                &mut tx,
+                &WalletTransaction {
+                    memo: Some("reclaimed unclaimed token".to_owned()),
+                    fee,
+                    ..WalletTransaction::new(
+                        TransactionKind::Swap,
+                        wallet_keyset,
+                        unclaimed.total_amount(),
+                    )
+                },
+            )
             .await?;
         tx.commit().await?;
-        Ok(result)
+        Ok((unclaimed.total_amount() - fee, spent.total_amount()))
     }
 
     pub async fn receive_tokens(
@@ -310,652 +1011,4262 @@ where
         wallet_keyset: &WalletKeyset,
         tokens: &TokenV3,
     ) -> Result<(), MokshaWalletError> {
-        let total_amount = tokens.total_amount();
+        let fee = self.input_fee(&tokens.proofs()).await?;
         let (_, redeemed_tokens) = self
-            .swap_tokens(wallet_keyset, tokens, total_amount.into())
+            .swap_tokens(
+                wallet_keyset,
+                tokens,
+                tokens.total_amount().saturating_sub(fee).into(),
+            )
             .await?;
+
         let mut tx = self.localstore.begin_tx().await?;
         self.localstore
-            .add_proofs(&mut tx, &redeemed_tokens.proofs())
+            .add_transaction(
+                &mut tx,
+                &WalletTransaction {
+                    memo: tokens.memo.clone(),
+                    fee,
+                    ..WalletTransaction::new(
+                        TransactionKind::Receive,
+                        wallet_keyset,
+                        redeemed_tokens.total_amount(),
+                    )
+                },
+            )
             .await?;
         tx.commit().await?;
         Ok(())
     }
 
-    pub async fn get_mint_quote(
-        &self,
-        mint_url: &Url,
-        amount: Amount,
-        currency: CurrencyUnit,
-    ) -> Result<PostMintQuoteBolt11Response, MokshaWalletError> {
-        self.client
-            .post_mint_quote_bolt11(mint_url, amount.0, currency)
-            .await
+    /// Returns the public key that tokens must be locked to (P2PK), so they can be received offline
+    pub fn p2pk_pubkey(&self) -> Result<PublicKey, MokshaWalletError> {
+        Ok(self
+            .secret
+            .derive_p2pk_key()?
+            .public_key(&Secp256k1::signing_only()))
     }
 
-    pub async fn get_melt_quote_bolt11(
-        &self,
-        mint_url: &Url,
-        invoice: String,
-        currency: CurrencyUnit,
-    ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
-        self.client
-            .post_melt_quote_bolt11(mint_url, invoice.clone(), currency)
-            .await
-    }
+    /// Verifies the tokens without contacting the mint and stores the proofs as unverified. The keysets of
+    /// the proofs must be known, every proof needs a valid DLEQ proof and must be locked to
+    /// [`Self::p2pk_pubkey`] only, so nobody but the sender can spend them in the meantime. The proofs don't count
+    /// towards the balance until they are swapped with [`Self::sync_offline_proofs`]. Returns the received
+    /// amount.
+    pub async fn receive_offline(&self, tokens: &TokenV3) -> Result<u64, MokshaWalletError> {
+        let keysets = self.get_wallet_keysets().await?;
+        let pubkey = self.p2pk_pubkey()?;
+        let mut tx = self.localstore.begin_tx().await?;
+        let mut known_secrets = self
+            .localstore
+            .get_proof_secrets(&mut tx)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        tx.commit().await?;
 
-    pub async fn pay_invoice(
-        &self,
-        wallet_keyset: &WalletKeyset,
-        melt_quote: &PostMeltQuoteBolt11Response,
-        invoice: String,
-    ) -> Result<(PostMeltBolt11Response, u64), MokshaWalletError> {
+        for token in &tokens.tokens {
+            for proof in token.proofs.proofs() {
+                let keyset = keysets
+                    .iter()
+                    .find(|keyset| {
+                        keyset.keyset_id.to_string() == proof.keyset_id
+                            && token.mint.as_ref() == Some(&keyset.mint_url)
+                    })
+                    .ok_or_else(|| MokshaWalletError::UnknownKeyset(proof.keyset_id.clone()))?;
+                let mint_pubkey = keyset
+                    .public_keys
+                    .get(&proof.amount)
+                    .ok_or(MokshaWalletError::PubkeyNotFound)?;
+                if !self.dhke.verify_dleq(&proof, mint_pubkey)? {
+                    return Err(MokshaWalletError::InvalidDleq);
+                }
+
+                // nobody else may be able to spend the proofs: no additional keys, no refund keys and no
+                // locktime, after which the sender could spend them again
+                let locked_to_wallet = P2PKSecret::from_str(&proof.secret).is_ok_and(|secret| {
+                    secret.pubkey == pubkey
+                        && secret.tag("pubkeys").is_none()
+                        && secret.tag("refund").is_none()
+                        && secret.n_sigs() == 1
+                        && secret.locktime().is_none()
+                });
+                if !locked_to_wallet {
+                    return Err(MokshaWalletError::NotLockedToWallet);
+                }
+                if !known_secrets.insert(proof.secret.clone()) {
+                    return Err(MokshaWalletError::ProofAlreadyReceived);
+                }
+            }
+        }
+
+        let proofs = tokens.proofs();
         let mut tx = self.localstore.begin_tx().await?;
-        let all_proofs = self.localstore.get_proofs(&mut tx).await?;
+        self.localstore.add_proofs(&mut tx, &proofs).await?;
+        self.localstore
+            .update_proof_state(
+                &mut tx,
+                &proofs,
+                ProofState::Unspent,
+                ProofState::Unverified,
+            )
+            .await?;
         tx.commit().await?;
+        Ok(proofs.total_amount())
+    }
 
-        let ln_amount = Self::get_invoice_amount(&invoice)? + melt_quote.fee_reserve;
+    /// Returns the proofs that were received offline and not swapped yet
+    pub async fn get_unverified_proofs(&self) -> Result<Proofs, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let proofs = self.localstore.get_unverified_proofs(&mut tx).await?;
+        tx.commit().await?;
+        Ok(proofs)
+    }
 
-        if ln_amount > all_proofs.total_amount() {
-            return Err(MokshaWalletError::NotEnoughTokens);
+    /// Swaps the proofs that were received offline into the active keysets. If the mint rejects the proofs of
+    /// a mint, their state is checked ([Nut-07](https://github.com/cashubtc/nuts/blob/main/07.md)) and the
+    /// unspent proofs are swapped one by one. Only proofs the mint reports as spent are removed.
+    pub async fn sync_offline_proofs(&self) -> Result<Vec<OfflineSyncResult>, MokshaWalletError> {
+        let keysets = self.get_wallet_keysets().await?;
+        let secret_key = self.secret.derive_p2pk_key()?;
+
+        let mut proofs_by_keyset: Vec<(&WalletKeyset, Vec<Proof>)> = vec![];
+        let mut proofs_without_keyset: Vec<(&Url, Vec<Proof>)> = vec![];
+        for proof in self.get_unverified_proofs().await?.proofs() {
+            // the keyset is known, because it was checked by receive_offline
+            let keyset = keysets
+                .iter()
+                .find(|keyset| keyset.keyset_id.to_string() == proof.keyset_id)
+                .ok_or_else(|| MokshaWalletError::UnknownKeyset(proof.keyset_id.clone()))?;
+            let Some(active_keyset) = keysets.get_active(&keyset.mint_url, &keyset.currency_unit)
+            else {
+                match proofs_without_keyset
+                    .iter_mut()
+                    .find(|(mint_url, _)| *mint_url == &keyset.mint_url)
+                {
+                    Some((_, proofs)) => proofs.push(proof),
+                    None => proofs_without_keyset.push((&keyset.mint_url, vec![proof])),
+                }
+                continue;
+            };
+            let proof = Proof {
+                witness: Some(P2PKWitness::sign(&proof.secret, &secret_key).to_string()),
+                ..proof
+            };
+            match proofs_by_keyset
+                .iter_mut()
+                .find(|(keyset, _)| keyset.keyset_id == active_keyset.keyset_id)
+            {
+                Some((_, proofs)) => proofs.push(proof),
+                None => proofs_by_keyset.push((active_keyset, vec![proof])),
+            }
         }
-        let selected_proofs = all_proofs.proofs_for_amount(ln_amount)?;
 
-        let total_proofs = {
-            let selected_tokens =
-                (wallet_keyset.mint_url.to_owned(), selected_proofs.clone()).into();
-            let swap_result = self
-                .swap_tokens(wallet_keyset, &selected_tokens, ln_amount.into())
-                .await?;
+        let mut results = vec![];
+        for (wallet_keyset, proofs) in proofs_by_keyset {
+            let (amount, double_spent, unverified) = match self
+                .redeem_unverified_proofs(wallet_keyset, &proofs.clone().into())
+                .await?
+            {
+                Some(amount) => (amount, vec![], vec![]),
+                None => self.redeem_rejected_proofs(wallet_keyset, proofs).await?,
+            };
 
-            let mut tx = self.localstore.begin_tx().await?;
-            self.localstore
-                .delete_proofs(&mut tx, &selected_proofs)
-                .await?;
-            self.localstore
-                .add_proofs(&mut tx, &swap_result.0.proofs())
-                .await?;
-            tx.commit().await?;
+            let double_spent: Proofs = double_spent.into();
+            if !double_spent.is_empty() {
+                let mut tx = self.localstore.begin_tx().await?;
+                self.localstore
+                    .delete_proofs(&mut tx, &double_spent)
+                    .await?;
+                tx.commit().await?;
+            }
+            results.push(OfflineSyncResult {
+                mint_url: wallet_keyset.mint_url.to_owned(),
+                amount,
+                double_spent,
+                unverified: unverified.into(),
+            });
+        }
+        for (mint_url, proofs) in proofs_without_keyset {
+            results.push(OfflineSyncResult {
+                mint_url: mint_url.to_owned(),
+                amount: 0,
+                double_spent: Proofs::empty(),
+                unverified: proofs.into(),
+            });
+        }
+        Ok(results)
+    }
 
-            swap_result.1.proofs()
+    /// Finds out why the mint rejected unverified proofs. Proofs the mint reports as spent are double spent,
+    /// the unspent proofs are swapped one by one. Returns the redeemed amount, the double spent proofs and the
+    /// proofs that stay unverified.
+    async fn redeem_rejected_proofs(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        proofs: Vec<Proof>,
+    ) -> Result<(u64, Vec<Proof>, Vec<Proof>), MokshaWalletError> {
+        // without the state of the proofs it's unknown if they were spent
+        let Ok(states) = self.proof_states(&wallet_keyset.mint_url, &proofs).await else {
+            return Ok((0, vec![], proofs));
         };
 
-        let fee_blind = self
-            .create_blank(melt_quote.fee_reserve.into(), &wallet_keyset.keyset_id)
-            .await?;
+        let mut amount = 0;
+        let mut double_spent = vec![];
+        let mut unverified = vec![];
+        for (proof, state) in proofs.into_iter().zip(states) {
+            if state == SpentState::Spent {
+                double_spent.push(proof);
+                continue;
+            }
+            match self
+                .redeem_unverified_proofs(wallet_keyset, &proof.clone().into())
+                .await?
+            {
+                Some(redeemed) => amount += redeemed,
+                None => unverified.push(proof),
+            }
+        }
+        Ok((amount, double_spent, unverified))
+    }
 
-        let msgs = fee_blind
-            .iter()
-            .map(|(msg, _, _)| msg.clone())
-            .collect::<Vec<BlindedMessage>>();
+    /// Swaps unverified proofs into the keyset. Returns `None` if the mint rejected the proofs.
+    async fn redeem_unverified_proofs(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        proofs: &Proofs,
+    ) -> Result<Option<u64>, MokshaWalletError> {
+        let amount = proofs
+            .total_amount()
+            .saturating_sub(self.input_fee(proofs).await?);
+        let tokens: TokenV3 = (
+            wallet_keyset.mint_url.to_owned(),
+            wallet_keyset.currency_unit.clone(),
+            proofs.to_owned(),
+        )
+            .into();
+        match self.receive_tokens(wallet_keyset, &tokens).await {
+            Ok(()) => Ok(Some(amount)),
+            Err(e) if is_rejected_by_mint(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
 
-        let secrets = fee_blind
-            .iter()
-            .map(|(_, _, secret)| secret.clone())
-            .collect::<Vec<String>>();
+    pub async fn get_mint_quote(
+        &self,
+        mint_url: &Url,
+        amount: Amount,
+        currency: CurrencyUnit,
+    ) -> Result<PostMintQuoteBolt11Response, MokshaWalletError> {
+        self.client
+            .post_mint_quote_bolt11(mint_url, amount.0, currency)
+            .await
+    }
 
-        let outputs = fee_blind
-            .iter()
-            .map(|(msg, blinding_factor, _)| (msg.clone(), blinding_factor.clone()))
-            .collect::<Vec<(BlindedMessage, BlindingFactor)>>();
+    pub async fn get_melt_quote_bolt11(
+        &self,
+        mint_url: &Url,
+        invoice: String,
+        currency: CurrencyUnit,
+    ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
+        self.client
+            .post_melt_quote_bolt11(mint_url, invoice.clone(), currency, None)
+            .await
+    }
 
-        let mut tx = self.localstore.begin_tx().await?;
-        match self
-            .melt_token(
-                &wallet_keyset.mint_url,
-                melt_quote.to_owned().quote,
-                &total_proofs,
-                msgs,
+    /// Requests a quote for paying `amount_msat` of the invoice as part of a multi-path payment (NUT-15)
+    pub async fn get_partial_melt_quote_bolt11(
+        &self,
+        mint_url: &Url,
+        invoice: String,
+        currency: CurrencyUnit,
+        amount_msat: u64,
+    ) -> Result<PostMeltQuoteBolt11Response, MokshaWalletError> {
+        self.client
+            .post_melt_quote_bolt11(
+                mint_url,
+                invoice,
+                currency,
+                Some(MeltQuoteBolt11Options::mpp(amount_msat)),
             )
             .await
-        {
-            Ok(response) => {
-                if !response.paid {
-                    self.localstore.add_proofs(&mut tx, &total_proofs).await?;
+    }
+
+    /// Pays the invoice from the balances of multiple mints. If no single mint has enough funds
+    /// the invoice is split into partial payments (NUT-15), which are melted concurrently.
+    /// Returns the outcome of every part, because some parts might be paid even if others fail.
+    pub async fn pay_invoice_mpp(
+        &self,
+        invoice: String,
+        currency: CurrencyUnit,
+    ) -> Result<Vec<MppPaymentResult>, MokshaWalletError> {
+        let amount_msat = Self::decode_invoice(&invoice)?
+            .amount_milli_satoshis()
+            .ok_or_else(|| MokshaWalletError::InvalidInvoice(invoice.clone()))?;
+        let amount = amount_msat / 1_000;
+
+        let balances = self.get_balances().await?;
+        let wallet_keysets = self.get_wallet_keysets().await?;
+        let mut capacities = balances
+            .mints
+            .iter()
+            .filter(|b| b.currency_unit == currency && b.amount > 0)
+            .filter(|b| wallet_keysets.get_active(&b.mint_url, &currency).is_some())
+            .map(|b| (b.mint_url.clone(), b.amount))
+            .collect::<Vec<_>>();
+        let mut mpp_checked = false;
+
+        for _ in 0..MAX_MPP_PLAN_ATTEMPTS {
+            let mut parts = plan_mpp_payment(&capacities, amount)?;
+
+            if parts.len() > 1 {
+                // only mints that support NUT-15 can pay a part of the invoice
+                if !mpp_checked {
+                    capacities = self.retain_mpp_mints(capacities, &currency).await;
+                    mpp_checked = true;
+                    parts = plan_mpp_payment(&capacities, amount)?;
                 }
-                let change_proofs = self.create_proofs_from_blinded_signatures(
-                    &wallet_keyset.keyset_id,
-                    &wallet_keyset.public_keys,
-                    response.clone().change,
-                    secrets,
-                    outputs,
-                )?;
-                self.localstore.add_proofs(&mut tx, &change_proofs).await?;
-                tx.commit().await?;
+                if parts.len() > 1 && amount_msat % 1_000 != 0 {
+                    return Err(MokshaWalletError::InvalidInvoice(invoice));
+                }
+            }
 
-                Ok((response, change_proofs.total_amount()))
+            let quotes = join_all(parts.iter().map(|part| {
+                if parts.len() == 1 {
+                    self.get_melt_quote_bolt11(&part.mint_url, invoice.clone(), currency.clone())
+                        .boxed_local()
+                } else {
+                    self.get_partial_melt_quote_bolt11(
+                        &part.mint_url,
+                        invoice.clone(),
+                        currency.clone(),
+                        part.amount * 1_000,
+                    )
+                    .boxed_local()
+                }
+            }))
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+            // the fee reserve is only known after quoting, so mints that can't cover it get less in the next plan
+            let mut fees_covered = true;
+            for (part, quote) in parts.iter().zip(quotes.iter()) {
+                let balance = balances.by_mint_and_unit(&part.mint_url, &currency);
+                if quote.amount + quote.fee_reserve > balance {
+                    fees_covered = false;
+                    if let Some(capacity) =
+                        capacities.iter_mut().find(|(url, _)| url == &part.mint_url)
+                    {
+                        capacity.1 = balance.saturating_sub(quote.fee_reserve);
+                    }
+                }
             }
-            Err(e) => {
-                self.localstore.add_proofs(&mut tx, &total_proofs).await?;
-                tx.commit().await?;
-                Err(e)
+            if !fees_covered {
+                continue;
             }
+
+            let payments = parts.iter().zip(quotes.iter()).map(|(part, quote)| async {
+                let wallet_keyset = wallet_keysets
+                    .get_active(&part.mint_url, &currency)
+                    .expect("keyset of planned mint not found");
+                MppPaymentResult {
+                    mint_url: part.mint_url.clone(),
+                    amount: part.amount,
+                    result: self
+                        .pay_invoice(wallet_keyset, quote, invoice.clone())
+                        .await,
+                }
+            });
+            return Ok(join_all(payments).await);
         }
+        Err(MokshaWalletError::NotEnoughTokens)
     }
 
-    pub async fn get_melt_quote_btconchain(
+    async fn retain_mpp_mints(
         &self,
-        mint_url: &Url,
-        address: String,
-        amount: u64,
-    ) -> Result<Vec<PostMeltQuoteBtcOnchainResponse>, MokshaWalletError> {
-        self.client
-            .post_melt_quote_onchain(mint_url, address, amount, CurrencyUnit::Sat)
-            .await
+        capacities: Vec<(Url, u64)>,
+        currency: &CurrencyUnit,
+    ) -> Vec<(Url, u64)> {
+        let infos = join_all(capacities.iter().map(|(url, _)| self.get_mint_info(url))).await;
+        capacities
+            .into_iter()
+            .zip(infos)
+            .filter(|(_, info)| {
+                info.as_ref().is_ok_and(|info| {
+                    info.nuts
+                        .nut15
+                        .as_ref()
+                        .is_some_and(|nut15| nut15.supports(&PaymentMethod::Bolt11, currency))
+                })
+            })
+            .map(|(capacity, _)| capacity)
+            .collect()
     }
 
-    pub async fn pay_onchain(
+    pub async fn pay_invoice(
         &self,
         wallet_keyset: &WalletKeyset,
-        melt_quote: &PostMeltQuoteBtcOnchainResponse,
-    ) -> Result<PostMeltBtcOnchainResponse, MokshaWalletError> {
-        let mut tx = self.localstore.begin_tx().await?;
-        let all_proofs = self.localstore.get_proofs(&mut tx).await?;
-        tx.commit().await?;
-
-        let ln_amount = melt_quote.amount + melt_quote.fee;
-
-        if ln_amount > all_proofs.total_amount() {
-            return Err(MokshaWalletError::NotEnoughTokens);
-        }
-        let selected_proofs = all_proofs.proofs_for_amount(ln_amount)?;
+        melt_quote: &PostMeltQuoteBolt11Response,
+        invoice: String,
+    ) -> Result<(PostMeltBolt11Response, u64), MokshaWalletError> {
+        let ln_amount = melt_quote.amount + melt_quote.fee_reserve;
+        let total_proofs = self.select_melt_proofs(wallet_keyset, ln_amount).await?;
 
-        let mut tx = self.localstore.begin_tx().await?;
-        let total_proofs = {
-            let selected_tokens =
-                (wallet_keyset.mint_url.to_owned(), selected_proofs.clone()).into();
-            let swap_result = self
-                .swap_tokens(wallet_keyset, &selected_tokens, ln_amount.into())
-                .await?;
-            self.localstore
-                .delete_proofs(&mut tx, &selected_proofs)
-                .await?;
-            self.localstore
-                .add_proofs(&mut tx, &swap_result.0.proofs())
-                .await?;
+        let fee_outputs = self
+            .create_blank(melt_quote.fee_reserve.into(), &wallet_keyset.keyset_id)
+            .await?;
 
-            swap_result.1.proofs()
-        };
+        let operation = self
+            .begin_operation(WalletOperation::new(
+                OperationKind::MeltBolt11,
+                wallet_keyset,
+                Some(melt_quote.quote.clone()),
+                total_proofs.clone(),
+                fee_outputs.clone(),
+            ))
+            .await?;
 
-        let melt_response = self
+        let response = match self
             .client
-            .post_melt_onchain(
+            .post_melt_bolt11(
                 &wallet_keyset.mint_url,
                 total_proofs.clone(),
                 melt_quote.quote.clone(),
+                operation.blinded_messages(),
             )
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(self.fail_operation(&operation, e).await),
+        };
 
-        if melt_response.paid {
+        let change_proofs = self.create_proofs_from_blinded_signatures(
+            wallet_keyset,
+            &response.change,
+            &fee_outputs,
+        )?;
+
+        let mut tx = self.localstore.begin_tx().await?;
+        if response.paid {
             self.localstore
                 .delete_proofs(&mut tx, &total_proofs)
                 .await?;
+            let invoice_amount = melt_quote.amount;
+            self.localstore
+                .add_transaction(
+                    &mut tx,
+                    &WalletTransaction {
+                        fee: total_proofs
+                            .total_amount()
+                            .saturating_sub(invoice_amount + change_proofs.total_amount()),
+                        payment_request: Some(invoice),
+                        payment_proof: response.payment_preimage.clone(),
+                        ..WalletTransaction::new(
+                            TransactionKind::Melt,
+                            wallet_keyset,
+                            invoice_amount,
+                        )
+                    },
+                )
+                .await?;
+        } else {
+            self.localstore
+                .update_proof_state(
+                    &mut tx,
+                    &total_proofs,
+                    ProofState::PendingSpent,
+                    ProofState::Unspent,
+                )
+                .await?;
         }
-        tx.commit().await?;
-        Ok(melt_response)
-    }
-
-    async fn create_secrets(
-        &self,
-        keyset_id: &KeysetId,
-        amount: u32,
-    ) -> Result<Vec<(String, BlindingFactor)>, MokshaWalletError> {
-        let mut tx = self.localstore.begin_tx().await?;
-        let all_keysets = self.localstore.get_keysets(&mut tx).await?;
-        let keyset = all_keysets
-            .iter()
-            .find(|k| k.keyset_id == *keyset_id)
-            .expect("keyset not found create-secrets");
-
-        let start_index = (keyset.last_index + 1) as u32;
-        let secret_range = self.secret.derive_range(keyset_id, start_index, amount)?;
-
+        self.localstore.add_proofs(&mut tx, &change_proofs).await?;
         self.localstore
-            .update_keyset_last_index(
-                &mut tx,
-                &WalletKeyset {
-                    last_index: (start_index + amount - 1) as u64,
-                    ..keyset.clone()
-                },
-            )
+            .delete_operation(&mut tx, &operation)
             .await?;
         tx.commit().await?;
-        Ok(secret_range)
+
+        Ok((response, change_proofs.total_amount()))
     }
 
-    pub async fn swap_tokens(
+    /// Moves `amount` sats from one mint to another by paying a mint quote of the destination with a melt at the source.
+    ///
+    /// The mint request is journaled before the melt. If the transfer is interrupted after the melt, the tokens are
+    /// minted by [`Wallet::recover_pending_operations`].
+    pub async fn transfer(
         &self,
-        wallet_keyset: &WalletKeyset,
-        tokens: &TokenV3,
-        splt_amount: Amount,
-    ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
-        let total_token_amount = tokens.total_amount();
-        let first_amount: Amount = (total_token_amount - splt_amount.0).into();
-        let first_secrets = self
-            .create_secrets(&wallet_keyset.keyset_id, first_amount.split().len() as u32)
-            .await?;
-        let first_outputs = self.create_blinded_messages(
-            &wallet_keyset.keyset_id,
-            first_amount,
-            first_secrets.clone(),
-        )?;
+        from_mint: &Url,
+        to_mint: &Url,
+        amount: u64,
+    ) -> Result<TransferResult, MokshaWalletError> {
+        if from_mint == to_mint {
+            return Err(MokshaWalletError::TransferFailed(
+                "source and destination mint are the same".to_owned(),
+            ));
+        }
 
-        // ############################################################################
+        let currency = CurrencyUnit::Sat;
+        let (source_keyset, destination_keyset) =
+            self.transfer_keysets(from_mint, to_mint, &currency).await?;
 
-        let second_amount = splt_amount.clone();
-        let second_secrets = self
-            .create_secrets(&wallet_keyset.keyset_id, second_amount.split().len() as u32)
+        let mint_quote = self.create_quote_bolt11(to_mint, amount).await?;
+        let melt_quote = self
+            .get_melt_quote_bolt11(
+                from_mint,
+                mint_quote.payment_request.clone(),
+                currency.clone(),
+            )
             .await?;
-        let second_outputs = self.create_blinded_messages(
-            &wallet_keyset.keyset_id,
-            second_amount,
-            second_secrets.clone(),
-        )?;
-
-        let mut total_outputs = vec![];
-        total_outputs.extend(get_blinded_msg(first_outputs.clone()));
-        total_outputs.extend(get_blinded_msg(second_outputs.clone()));
-
-        if tokens.total_amount() != total_outputs.total_amount() {
-            return Err(MokshaWalletError::InvalidProofs);
+        if melt_quote.amount != amount {
+            return Err(MokshaWalletError::TransferFailed(format!(
+                "melt quote amount {} does not match transfer amount {amount}",
+                melt_quote.amount
+            )));
         }
-
-        let split_result = self
-            .client
-            .post_swap(&wallet_keyset.mint_url, tokens.proofs(), total_outputs)
+        let balance = self
+            .get_balance_by_mint_and_unit(from_mint, &currency)
             .await?;
-
-        if split_result.signatures.is_empty() {
-            return Ok((TokenV3::empty(), TokenV3::empty()));
+        if amount + melt_quote.fee_reserve > balance {
+            return Err(MokshaWalletError::NotEnoughTokens);
         }
 
-        let len_first = first_secrets.len();
-        let secrets = [first_secrets, second_secrets].concat();
-        let outputs = [first_outputs, second_outputs].concat();
+        self.transfer_with_quotes(&source_keyset, &destination_keyset, mint_quote, melt_quote)
+            .await
+    }
 
-        let secrets = secrets.into_iter().map(|(s, _)| s).collect::<Vec<String>>();
+    /// Returns the active keysets of the source and destination mint of a transfer. The keysets of the
+    /// destination mint are added to the wallet if they are unknown.
+    async fn transfer_keysets(
+        &self,
+        from_mint: &Url,
+        to_mint: &Url,
+        currency: &CurrencyUnit,
+    ) -> Result<(WalletKeyset, WalletKeyset), MokshaWalletError> {
+        let mut wallet_keysets = self.get_wallet_keysets().await?;
+        let source_keyset = wallet_keysets
+            .get_active(from_mint, currency)
+            .cloned()
+            .ok_or(MokshaWalletError::NotEnoughTokens)?;
+        if wallet_keysets.get_active(to_mint, currency).is_none() {
+            wallet_keysets = self.add_mint_keysets(to_mint).await?;
+        }
+        let destination_keyset = wallet_keysets
+            .get_active(to_mint, currency)
+            .cloned()
+            .ok_or_else(|| {
+                MokshaWalletError::TransferFailed(format!("no active keyset found for {to_mint}"))
+            })?;
+        Ok((source_keyset, destination_keyset))
+    }
 
-        let proofs = self
-            .create_proofs_from_blinded_signatures(
-                &wallet_keyset.keyset_id,
-                &wallet_keyset.public_keys,
-                split_result.signatures,
-                secrets,
+    /// Pays the mint quote of the destination with the melt quote of the source and mints the tokens
+    async fn transfer_with_quotes(
+        &self,
+        source_keyset: &WalletKeyset,
+        destination_keyset: &WalletKeyset,
+        mint_quote: PostMintQuoteBolt11Response,
+        melt_quote: PostMeltQuoteBolt11Response,
+    ) -> Result<TransferResult, MokshaWalletError> {
+        let amount = melt_quote.amount;
+        let outputs = self
+            .create_outputs(
+                &destination_keyset.keyset_id,
+                Amount(amount).split_with_strategy(&self.split_strategy),
+            )
+            .await?;
+        let mint_operation = self
+            .begin_operation(WalletOperation::new(
+                OperationKind::MintBolt11,
+                destination_keyset,
+                Some(mint_quote.quote),
+                Proofs::empty(),
                 outputs,
-            )?
-            .proofs();
-
-        let first_tokens: TokenV3 = (
-            wallet_keyset.mint_url.to_owned(),
-            wallet_keyset.currency_unit.clone(),
-            proofs[0..len_first].to_vec().into(),
-        )
-            .into();
-        let second_tokens: TokenV3 = (
-            wallet_keyset.mint_url.to_owned(),
-            wallet_keyset.currency_unit.clone(),
-            proofs[len_first..proofs.len()].to_vec().into(),
-        )
-            .into();
+            ))
+            .await?;
 
-        if tokens.total_amount() != first_tokens.total_amount() + second_tokens.total_amount() {
-            println!(
-                "Error in swap: input {:?} != output {:?} + {:?}",
-                tokens.total_amount(),
-                first_tokens.total_amount(),
-                second_tokens.total_amount()
-            );
+        let (melt_response, change) = match self
+            .pay_invoice(source_keyset, &melt_quote, mint_quote.payment_request)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(self.fail_operation(&mint_operation, e).await),
+        };
+        if !melt_response.paid {
+            self.rollback_operation(&mint_operation).await?;
+            return Err(MokshaWalletError::TransferFailed(
+                "invoice of the destination mint was not paid".to_owned(),
+            ));
         }
 
-        Ok((first_tokens, second_tokens))
+        // the melt is paid, so the operation stays in the journal until the tokens are minted
+        let signatures = self.post_mint(&mint_operation).await?;
+        let tokens = self
+            .store_minted_proofs(destination_keyset, &mint_operation, &signatures)
+            .await?;
+
+        Ok(TransferResult {
+            amount: tokens.total_amount(),
+            fee: melt_quote.fee_reserve.saturating_sub(change),
+        })
     }
 
-    pub async fn get_mint_info(
+    /// Returns the mints the wallet is allowed to hold a balance at
+    pub async fn get_trusted_mints(&self) -> Result<Vec<TrustedMint>, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let trusted_mints = self.localstore.get_trusted_mints(&mut tx).await?;
+        tx.commit().await?;
+        Ok(trusted_mints)
+    }
+
+    /// Adds the mint to the trusted mints. A preferred mint receives the tokens of untrusted mints.
+    pub async fn trust_mint(
         &self,
         mint_url: &Url,
-    ) -> Result<MintInfoResponse, MokshaWalletError> {
-        self.client.get_info(mint_url).await
+        preferred: bool,
+    ) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .upsert_trusted_mint(
+                &mut tx,
+                &TrustedMint {
+                    mint_url: mint_url.to_owned(),
+                    preferred,
+                },
+            )
+            .await?;
+        tx.commit().await?;
+        Ok(())
     }
 
-    async fn melt_token(
-        &self,
-        mint_url: &Url,
-        quote_id: String,
-        proofs: &Proofs,
-        fee_blinded_messages: Vec<BlindedMessage>,
-    ) -> Result<PostMeltBolt11Response, MokshaWalletError> {
-        let melt_response = self
-            .client
-            .post_melt_bolt11(mint_url, proofs.clone(), quote_id, fee_blinded_messages)
+    pub async fn untrust_mint(&self, mint_url: &Url) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .delete_trusted_mint(&mut tx, mint_url)
             .await?;
-
-        if melt_response.paid {
-            let mut tx = self.localstore.begin_tx().await?;
-            self.localstore.delete_proofs(&mut tx, proofs).await?;
-            tx.commit().await?;
-        }
-        Ok(melt_response)
+        tx.commit().await?;
+        Ok(())
     }
 
-    fn decode_invoice(payment_request: &str) -> Result<LNInvoice, MokshaWalletError> {
-        LNInvoice::from_str(payment_request)
-            .map_err(|err| MokshaWalletError::DecodeInvoice(payment_request.to_owned(), err))
+    pub async fn get_preferred_mint(&self) -> Result<Option<Url>, MokshaWalletError> {
+        Ok(self
+            .get_trusted_mints()
+            .await?
+            .into_iter()
+            .find(|mint| mint.preferred)
+            .map(|mint| mint.mint_url))
     }
 
-    fn get_invoice_amount(payment_request: &str) -> Result<u64, MokshaWalletError> {
-        let invoice = Self::decode_invoice(payment_request)?;
-        Ok(invoice
-            .amount_milli_satoshis()
-            .ok_or_else(|| MokshaWalletError::InvalidInvoice(payment_request.to_owned()))?
-            / 1000)
+    /// Returns true if the mint is trusted. Every mint is trusted as long as no trusted mints are configured.
+    pub async fn is_trusted_mint(&self, mint_url: &Url) -> Result<bool, MokshaWalletError> {
+        let trusted_mints = self.get_trusted_mints().await?;
+        Ok(trusted_mints.is_empty() || trusted_mints.iter().any(|mint| &mint.mint_url == mint_url))
     }
 
-    pub async fn mint_tokens(
+    /// Redeems tokens of an untrusted mint and moves the received amount to the preferred mint via Lightning.
+    /// The fee contains the input fee of the swap and the lightning fees. If moving the tokens fails, they stay
+    /// at the untrusted mint and [`MokshaWalletError::SweepFailed`] is returned, so the transfer can be retried.
+    pub async fn receive_into_preferred_mint(
         &self,
-        wallet_keyset: &WalletKeyset,
-        payment_method: &PaymentMethod,
-        amount: Amount,
-        quote_id: String,
-    ) -> Result<TokenV3, MokshaWalletError> {
-        let split_amount = amount.split();
-
-        let secret_range = self
-            .create_secrets(&wallet_keyset.keyset_id, split_amount.len() as u32)
-            .await?;
+        tokens: &TokenV3,
+    ) -> Result<TransferResult, MokshaWalletError> {
+        let preferred_mint = self
+            .get_preferred_mint()
+            .await?
+            .ok_or(MokshaWalletError::NoPreferredMint)?;
+        let token_mint = tokens
+            .mint()
+            .ok_or_else(|| MokshaWalletError::TransferFailed("token has no mint url".to_owned()))?;
+        let currency = tokens.currency_unit.clone().unwrap_or(CurrencyUnit::Sat);
+        if currency != CurrencyUnit::Sat {
+            return Err(MokshaWalletError::TransferFailed(format!(
+                "can not move {currency:?} tokens via lightning"
+            )));
+        }
 
-        let blinded_messages = split_amount
-            .into_iter()
-            .zip(secret_range)
-            .map(|(amount, (secret, blinding_factor))| {
-                let b_ = self.dhke.step1_alice(&secret, &blinding_factor)?;
-                Ok((
-                    BlindedMessage {
-                        amount,
-                        b_,
-                        id: wallet_keyset.keyset_id.to_string(), // FIXME use keyset_id
-                    },
-                    blinding_factor,
-                    secret,
+        let mut wallet_keysets = self.get_wallet_keysets().await?;
+        if wallet_keysets.get_active(&token_mint, &currency).is_none() {
+            wallet_keysets = self.add_mint_keysets(&token_mint).await?;
+        }
+        let token_keyset = wallet_keysets
+            .get_active(&token_mint, &currency)
+            .cloned()
+            .ok_or_else(|| {
+                MokshaWalletError::TransferFailed(format!(
+                    "no active keyset found for {token_mint}"
                 ))
-            })
-            .collect::<Result<Vec<(_, _, _)>, MokshaWalletError>>()?;
+            })?;
+
+        let swap_fee = self.input_fee(&tokens.proofs()).await?;
+        let received_amount = tokens.total_amount().saturating_sub(swap_fee);
+        self.receive_tokens(&token_keyset, tokens).await?;
+        if token_mint == preferred_mint {
+            return Ok(TransferResult {
+                amount: received_amount,
+                fee: swap_fee,
+            });
+        }
 
-        let signatures = match payment_method {
-            PaymentMethod::Bolt11 => {
-                let post_mint_resp = self
-                    .client
-                    .post_mint_bolt11(
-                        &wallet_keyset.mint_url,
-                        quote_id,
-                        blinded_messages
-                            .clone()
-                            .into_iter()
-                            .map(|(msg, _, _)| msg)
-                            .collect::<Vec<BlindedMessage>>(),
-                    )
-                    .await?;
-                post_mint_resp.signatures
+        // only the received amount is moved, a balance the wallet had at the mint before stays there
+        match self
+            .sweep_mint(&token_mint, &preferred_mint, received_amount)
+            .await
+        {
+            Ok(result) => Ok(TransferResult {
+                amount: result.amount,
+                fee: swap_fee + result.fee,
+            }),
+            Err(e) => Err(MokshaWalletError::SweepFailed(
+                token_mint,
+                received_amount,
+                e.to_string(),
+            )),
+        }
+    }
+
+    /// Moves up to `max_amount` sats of the balance of `from_mint` to `to_mint`. The transferred amount is lowered
+    /// until `max_amount` covers the fee reserve of the melt quote.
+    async fn sweep_mint(
+        &self,
+        from_mint: &Url,
+        to_mint: &Url,
+        max_amount: u64,
+    ) -> Result<TransferResult, MokshaWalletError> {
+        let currency = CurrencyUnit::Sat;
+        let (source_keyset, destination_keyset) =
+            self.transfer_keysets(from_mint, to_mint, &currency).await?;
+        let proofs = self
+            .get_proofs_by_mint_and_unit(from_mint, &currency)
+            .await?;
+        let available = proofs
+            .total_amount()
+            .saturating_sub(self.input_fee(&proofs).await?)
+            .min(max_amount);
+
+        let mut amount = available;
+        for _ in 0..MAX_SWEEP_QUOTES {
+            if amount == 0 {
+                break;
             }
-            PaymentMethod::BtcOnchain => {
-                let post_mint_resp = self
-                    .client
-                    .post_mint_onchain(
-                        &wallet_keyset.mint_url,
-                        quote_id,
-                        blinded_messages
-                            .clone()
-                            .into_iter()
-                            .map(|(msg, _, _)| msg)
-                            .collect::<Vec<BlindedMessage>>(),
+            let mint_quote = self.create_quote_bolt11(to_mint, amount).await?;
+            let melt_quote = self
+                .get_melt_quote_bolt11(
+                    from_mint,
+                    mint_quote.payment_request.clone(),
+                    currency.clone(),
+                )
+                .await?;
+            if melt_quote.amount + melt_quote.fee_reserve <= available {
+                return self
+                    .transfer_with_quotes(
+                        &source_keyset,
+                        &destination_keyset,
+                        mint_quote,
+                        melt_quote,
                     )
-                    .await?;
-                post_mint_resp.signatures
+                    .await;
             }
+            amount = available
+                .saturating_sub(melt_quote.fee_reserve)
+                .min(amount - 1);
+        }
+        Err(MokshaWalletError::TransferFailed(format!(
+            "{available} (sat) at {from_mint} do not cover the lightning fees"
+        )))
+    }
+
+    /// Returns true if the seed is stored encrypted with a password
+    pub async fn is_seed_encrypted(&self) -> Result<bool, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let seed = self.localstore.get_seed(&mut tx).await?;
+        tx.commit().await?;
+        Ok(seed.is_some_and(|seed| encryption::is_encrypted(&seed)))
+    }
+
+    /// Sets, changes or removes the password of the seed. `old_password` is required if the seed is encrypted,
+    /// without a `new_password` the seed is stored in plaintext.
+    pub async fn change_password(
+        &self,
+        old_password: Option<&str>,
+        new_password: Option<&str>,
+    ) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let stored_seed = self
+            .localstore
+            .get_seed(&mut tx)
+            .await?
+            .ok_or(MokshaWalletError::WalletLocked)?;
+        let seed = if encryption::is_encrypted(&stored_seed) {
+            let old_password = old_password.ok_or(MokshaWalletError::WalletLocked)?;
+            String::from_utf8(encryption::decrypt(old_password, &stored_seed)?)?
+        } else {
+            stored_seed
         };
 
-        // step 3: unblind signatures
-        let current_keyset_id = wallet_keyset.keyset_id.to_string(); // FIXME
+        let stored_seed = match new_password {
+            Some(new_password) => encryption::encrypt(new_password, seed.as_bytes())?,
+            None => seed,
+        };
+        self.localstore.update_seed(&mut tx, &stored_seed).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        let proofs = signatures
-            .iter()
-            .zip(blinded_messages)
-            .map(|(p, (_, priv_key, secret))| {
-                let key = wallet_keyset
-                    .public_keys
-                    .get(&p.amount)
-                    .expect("msg amount not found in mint keys");
-                let pub_alice = self.dhke.step3_alice(p.c_, priv_key, *key).unwrap();
-                Proof::new(p.amount, secret, pub_alice, current_keyset_id.clone())
-            })
-            .collect::<Vec<Proof>>()
-            .into();
+    pub async fn get_melt_quote_btconchain(
+        &self,
+        mint_url: &Url,
+        address: String,
+        amount: u64,
+    ) -> Result<Vec<PostMeltQuoteBtcOnchainResponse>, MokshaWalletError> {
+        self.client
+            .post_melt_quote_onchain(mint_url, address, amount, CurrencyUnit::Sat)
+            .await
+    }
+
+    pub async fn pay_onchain(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        melt_quote: &PostMeltQuoteBtcOnchainResponse,
+        address: String,
+    ) -> Result<PostMeltBtcOnchainResponse, MokshaWalletError> {
+        let ln_amount = melt_quote.amount + melt_quote.fee;
+        let total_proofs = self.select_melt_proofs(wallet_keyset, ln_amount).await?;
+
+        let operation = self
+            .begin_operation(WalletOperation::new(
+                OperationKind::MeltOnchain,
+                wallet_keyset,
+                Some(melt_quote.quote.clone()),
+                total_proofs.clone(),
+                vec![],
+            ))
+            .await?;
+
+        let melt_response = match self
+            .client
+            .post_melt_onchain(
+                &wallet_keyset.mint_url,
+                total_proofs.clone(),
+                melt_quote.quote.clone(),
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(self.fail_operation(&operation, e).await),
+        };
 
-        let tokens: TokenV3 = (wallet_keyset.mint_url.to_owned(), proofs).into();
         let mut tx = self.localstore.begin_tx().await?;
+        if melt_response.paid {
+            self.localstore
+                .delete_proofs(&mut tx, &total_proofs)
+                .await?;
+            self.localstore
+                .add_transaction(
+                    &mut tx,
+                    &WalletTransaction {
+                        fee: melt_quote.fee,
+                        payment_request: Some(address),
+                        payment_proof: Some(melt_response.txid.clone()),
+                        ..WalletTransaction::new(
+                            TransactionKind::Melt,
+                            wallet_keyset,
+                            melt_quote.amount,
+                        )
+                    },
+                )
+                .await?;
+        } else {
+            self.localstore
+                .update_proof_state(
+                    &mut tx,
+                    &total_proofs,
+                    ProofState::PendingSpent,
+                    ProofState::Unspent,
+                )
+                .await?;
+        }
         self.localstore
-            .add_proofs(&mut tx, &tokens.proofs())
+            .delete_operation(&mut tx, &operation)
             .await?;
         tx.commit().await?;
-
-        Ok(tokens)
+        Ok(melt_response)
     }
 
-    pub async fn create_blank(
+    /// Derives the next secrets for the keyset and blinds them. The counter of the keyset is updated before returning.
+    async fn create_outputs(
         &self,
-        fee_reserve: Amount,
         keyset_id: &KeysetId,
-    ) -> Result<Vec<(BlindedMessage, BlindingFactor, String)>, MokshaWalletError> {
-        if fee_reserve.0 == 0 {
+        amounts: SplitAmount,
+    ) -> Result<Vec<PendingOutput>, MokshaWalletError> {
+        if amounts.is_empty() {
             return Ok(vec![]);
         }
 
-        let fee_reserve_float = fee_reserve.0 as f64;
-        let count = (fee_reserve_float.log2().ceil() as u64).max(1);
+        let mut tx = self.localstore.begin_tx().await?;
+        let all_keysets = self.localstore.get_keysets(&mut tx).await?;
+        let keyset = all_keysets
+            .iter()
+            .find(|k| k.keyset_id == *keyset_id)
+            .expect("keyset not found create-secrets");
+
+        let length = amounts.len() as u32;
+        let start_index = (keyset.last_index + 1) as u32;
+        let secret_range = self.secret.derive_range(keyset_id, start_index, length)?;
+
+        self.localstore
+            .update_keyset_last_index(
+                &mut tx,
+                &WalletKeyset {
+                    last_index: (start_index + length - 1) as u64,
+                    ..keyset.clone()
+                },
+            )
+            .await?;
+        tx.commit().await?;
 
-        let secret_range = self.create_secrets(keyset_id, count as u32).await?;
-        let blinded_messages = secret_range
+        amounts
             .into_iter()
-            .map(|(secret, blinding_factor)| {
+            .zip(secret_range)
+            .zip(start_index..)
+            .map(|((amount, (secret, blinding_factor)), counter)| {
                 let b_ = self.dhke.step1_alice(secret.clone(), &blinding_factor)?;
-                Ok((
-                    BlindedMessage {
-                        amount: 1,
+                Ok(PendingOutput {
+                    counter,
+                    secret,
+                    blinding_factor,
+                    blinded_message: BlindedMessage {
+                        amount,
                         b_,
                         id: keyset_id.to_string(),
                     },
-                    blinding_factor,
-                    secret,
-                ))
+                })
             })
-            .collect::<Result<Vec<(_, _, _)>, MokshaWalletError>>()?;
-
-        Ok(blinded_messages)
+            .collect()
     }
 
-    fn create_blinded_messages(
+    /// Stores the operation in the journal before it is sent to the mint. Unspent inputs are marked as
+    /// pending, so they can't be selected by another payment.
+    async fn begin_operation(
         &self,
-        keyset_id: &KeysetId,
-        amount: Amount,
-        secrets_factors: Vec<(String, BlindingFactor)>,
-    ) -> Result<Vec<(BlindedMessage, BlindingFactor)>, MokshaWalletError> {
-        let split_amount = amount.split();
-
-        split_amount
-            .into_iter()
-            .zip(secrets_factors)
-            .map(|(amount, (secret, blinding_factor))| {
-                let b_ = self.dhke.step1_alice(secret, &blinding_factor)?;
-                Ok((
-                    BlindedMessage {
-                        amount,
-                        b_,
-                        id: keyset_id.to_string(),
-                    },
-                    blinding_factor,
-                ))
-            })
-            .collect::<Result<Vec<(_, _)>, MokshaWalletError>>()
+        operation: WalletOperation,
+    ) -> Result<WalletOperation, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .update_proof_state(
+                &mut tx,
+                &operation.inputs,
+                ProofState::Unspent,
+                ProofState::PendingSpent,
+            )
+            .await?;
+        let id = self.localstore.add_operation(&mut tx, &operation).await?;
+        tx.commit().await?;
+        Ok(WalletOperation {
+            id: Some(id),
+            ..operation
+        })
     }
 
-    fn create_proofs_from_blinded_signatures(
+    /// Removes the operation from the journal and makes pending inputs spendable again
+    async fn rollback_operation(
         &self,
-        keyset_id: &KeysetId,
-        pub_keys: &HashMap<u64, PublicKey>,
-        signatures: Vec<BlindedSignature>,
-        secrets: Vec<String>,
-        outputs: Vec<(BlindedMessage, BlindingFactor)>,
-    ) -> Result<Proofs, MokshaWalletError> {
-        let current_keyset_id = keyset_id.to_string(); // FIXME
+        operation: &WalletOperation,
+    ) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .update_proof_state(
+                &mut tx,
+                &operation.inputs,
+                ProofState::PendingSpent,
+                ProofState::Unspent,
+            )
+            .await?;
+        self.localstore.delete_operation(&mut tx, operation).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        let blinding_factors = outputs
-            .into_iter()
-            .map(|(_, secret)| secret)
-            .collect::<Vec<BlindingFactor>>();
+    /// If the mint rejected the request the operation is rolled back. Any other error (e.g. a network error)
+    /// leaves the operation in the journal, because the mint might have processed the request.
+    async fn fail_operation(
+        &self,
+        operation: &WalletOperation,
+        err: MokshaWalletError,
+    ) -> MokshaWalletError {
+        if is_rejected_by_mint(&err) {
+            if let Err(rollback_err) = self.rollback_operation(operation).await {
+                return rollback_err;
+            }
+        }
+        err
+    }
 
-        Ok(signatures
+    /// Creates a coin selector with the input fees of the given keysets
+    fn coin_selector(keysets: &[WalletKeyset]) -> CoinSelector {
+        keysets
             .iter()
-            .zip(blinding_factors)
-            .zip(secrets)
-            .map(|((p, blinding_factor), secret)| {
-                let key = pub_keys
-                    .get(&p.amount)
-                    .ok_or(MokshaWalletError::PubkeyNotFound)?;
-                let pub_alice = self
-                    .dhke
-                    .step3_alice(p.c_, blinding_factor.to_owned(), *key)?;
-                Ok(Proof::new(
-                    p.amount,
-                    secret,
-                    pub_alice,
-                    current_keyset_id.clone(),
-                ))
+            .fold(CoinSelector::new(), |selector, keyset| {
+                selector.with_input_fee_ppk(keyset.keyset_id.to_string(), keyset.input_fee_ppk)
             })
-            .collect::<Result<Vec<_>, MokshaWalletError>>()?
-            .into())
     }
 
-    pub async fn get_proofs(&self) -> Result<Proofs, MokshaWalletError> {
+    /// Returns the fee the mint charges for spending the given proofs
+    async fn input_fee(&self, proofs: &Proofs) -> Result<u64, MokshaWalletError> {
         let mut tx = self.localstore.begin_tx().await?;
-        let proofs = self.localstore.get_proofs(&mut tx).await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
         tx.commit().await?;
-        Ok(proofs)
+        Ok(Self::coin_selector(&keysets).fee(proofs))
     }
-}
-
-// FIXME implement for Vec<BlindedMessage, Secretkey>
-fn get_blinded_msg(blinded_messages: Vec<(BlindedMessage, BlindingFactor)>) -> Vec<BlindedMessage> {
-    blinded_messages
-        .into_iter()
-        .map(|(msg, _)| msg)
-        .collect::<Vec<BlindedMessage>>()
-}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+    /// Selects the proofs for a melt of `amount`. If there is no exact match, the selected proofs are swapped
+    /// first, so that the melt covers `amount` and the input fee of the swapped proofs.
+    async fn select_melt_proofs(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        amount: u64,
+    ) -> Result<Proofs, MokshaWalletError> {
+        let all_proofs = self
+            .get_proofs_by_mint_and_unit(&wallet_keyset.mint_url, &wallet_keyset.currency_unit)
+            .await?;
+        if amount > all_proofs.total_amount() {
+            return Err(MokshaWalletError::NotEnoughTokens);
+        }
 
-    use crate::client::MockCashuClient;
-    use crate::localstore::sqlite::SqliteLocalStore;
-    use crate::localstore::{LocalStore, WalletKeyset};
-    use crate::wallet::WalletBuilder;
+        let keysets = self.get_wallet_keysets().await?;
+        let selector = Self::coin_selector(&keysets);
+        let selection = selector.select(&all_proofs, amount)?;
+        if selection.is_exact(amount) {
+            return Ok(selection.proofs);
+        }
 
-    use moksha_core::fixture::{read_fixture, read_fixture_as};
-    use moksha_core::keyset::{KeysetId, Keysets, MintKeyset};
-    use moksha_core::primitives::{
-        CurrencyUnit, KeyResponse, KeysResponse, PaymentMethod, PostMeltBolt11Response,
-        PostMeltQuoteBolt11Response, PostMintBolt11Response, PostSwapResponse,
-    };
+        let melt_amount = selector.amount_with_fee(&wallet_keyset.keyset_id.to_string(), amount);
+        let selection = selector.select(&all_proofs, melt_amount)?;
+        let selected_tokens = (wallet_keyset.mint_url.to_owned(), selection.proofs).into();
+        let (_, melt_tokens) = self
+            .swap_tokens(wallet_keyset, &selected_tokens, melt_amount.into())
+            .await?;
+        Ok(melt_tokens.proofs())
+    }
 
-    use moksha_core::token::TokenV3;
-    use secp256k1::PublicKey;
-    use url::Url;
+    /// Swaps the tokens at the mint and stores the new proofs in the wallet. Inputs that are stored in the
+    /// wallet are deleted. Returns the remaining tokens and the tokens for `splt_amount`. The input fee is
+    /// deducted from the remaining tokens. The remaining tokens stay in the wallet and are split with the split
+    /// strategy of the wallet, the tokens for `splt_amount` use as few proofs as possible.
+    pub async fn swap_tokens(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        tokens: &TokenV3,
+        splt_amount: Amount,
+    ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
+        let fee = self.input_fee(&tokens.proofs()).await?;
+        let first_amount: Amount = tokens
+            .total_amount()
+            .checked_sub(splt_amount.0 + fee)
+            .ok_or(MokshaWalletError::NotEnoughTokens)?
+            .into();
+        self.swap_split_amounts(
+            wallet_keyset,
+            tokens,
+            first_amount.split_with_strategy(&self.split_strategy),
+            splt_amount.split(),
+        )
+        .await
+    }
 
-    fn create_mock() -> MockCashuClient {
-        let keys = MintKeyset::new("mykey", "");
-        let key_response = KeyResponse {
+    /// Swaps the tokens into outputs with the given denominations. The outputs must add up to the amount of
+    /// the tokens minus the input fee.
+    async fn swap_split_amounts(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        tokens: &TokenV3,
+        first_amounts: SplitAmount,
+        second_amounts: SplitAmount,
+    ) -> Result<(TokenV3, TokenV3), MokshaWalletError> {
+        let fee = self.input_fee(&tokens.proofs()).await?;
+        let first_outputs = self
+            .create_outputs(&wallet_keyset.keyset_id, first_amounts)
+            .await?;
+        let second_outputs = self
+            .create_outputs(&wallet_keyset.keyset_id, second_amounts)
+            .await?;
+
+        let len_first = first_outputs.len();
+        let operation = WalletOperation::new(
+            OperationKind::Swap,
+            wallet_keyset,
+            None,
+            tokens.proofs(),
+            [first_outputs, second_outputs].concat(),
+        );
+        let total_outputs = operation.blinded_messages();
+
+        if tokens.total_amount() != total_outputs.total_amount() + fee {
+            return Err(MokshaWalletError::InvalidProofs);
+        }
+
+        let operation = self.begin_operation(operation).await?;
+        let split_result = match self
+            .client
+            .post_swap(&wallet_keyset.mint_url, tokens.proofs(), total_outputs)
+            .await
+        {
+            Ok(split_result) => split_result,
+            Err(e) => return Err(self.fail_operation(&operation, e).await),
+        };
+
+        if split_result.signatures.is_empty() {
+            self.rollback_operation(&operation).await?;
+            return Ok((TokenV3::empty(), TokenV3::empty()));
+        }
+
+        let proofs = self
+            .create_proofs_from_blinded_signatures(
+                wallet_keyset,
+                &split_result.signatures,
+                &operation.outputs,
+            )?
+            .proofs();
+
+        // the operation stays in the journal, so the outputs can be restored later
+        let proofs_amount = Proofs::from(proofs.clone()).total_amount();
+        if proofs_amount != tokens.total_amount() - fee {
+            return Err(MokshaWalletError::UnexpectedResponse(format!(
+                "swap returned {proofs_amount} instead of {} (sat)",
+                tokens.total_amount() - fee
+            )));
+        }
+
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .delete_proofs(&mut tx, &operation.inputs)
+            .await?;
+        self.localstore
+            .add_proofs(&mut tx, &proofs.clone().into())
+            .await?;
+        self.localstore
+            .delete_operation(&mut tx, &operation)
+            .await?;
+        tx.commit().await?;
+
+        let first_tokens: TokenV3 = (
+            wallet_keyset.mint_url.to_owned(),
+            wallet_keyset.currency_unit.clone(),
+            proofs[0..len_first].to_vec().into(),
+        )
+            .into();
+        let second_tokens: TokenV3 = (
+            wallet_keyset.mint_url.to_owned(),
+            wallet_keyset.currency_unit.clone(),
+            proofs[len_first..proofs.len()].to_vec().into(),
+        )
+            .into();
+
+        Ok((first_tokens, second_tokens))
+    }
+
+    pub async fn get_mint_info(
+        &self,
+        mint_url: &Url,
+    ) -> Result<MintInfoResponse, MokshaWalletError> {
+        self.client.get_info(mint_url).await
+    }
+
+    fn decode_invoice(payment_request: &str) -> Result<LNInvoice, MokshaWalletError> {
+        LNInvoice::from_str(payment_request)
+            .map_err(|err| MokshaWalletError::DecodeInvoice(payment_request.to_owned(), err))
+    }
+
+    pub async fn mint_tokens(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        payment_method: &PaymentMethod,
+        amount: Amount,
+        quote_id: String,
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let outputs = self
+            .create_outputs(
+                &wallet_keyset.keyset_id,
+                amount.split_with_strategy(&self.split_strategy),
+            )
+            .await?;
+        let kind = match payment_method {
+            PaymentMethod::Bolt11 => OperationKind::MintBolt11,
+            PaymentMethod::BtcOnchain => OperationKind::MintOnchain,
+        };
+        let operation = self
+            .begin_operation(WalletOperation::new(
+                kind,
+                wallet_keyset,
+                Some(quote_id),
+                Proofs::empty(),
+                outputs,
+            ))
+            .await?;
+
+        let signatures = match self.post_mint(&operation).await {
+            Ok(signatures) => signatures,
+            Err(e) => return Err(self.fail_operation(&operation, e).await),
+        };
+        self.store_minted_proofs(wallet_keyset, &operation, &signatures)
+            .await
+    }
+
+    /// Stores the proofs of a mint operation and removes it from the journal
+    async fn store_minted_proofs(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        operation: &WalletOperation,
+        signatures: &[BlindedSignature],
+    ) -> Result<TokenV3, MokshaWalletError> {
+        let proofs = self.create_proofs_from_blinded_signatures(
+            wallet_keyset,
+            signatures,
+            &operation.outputs,
+        )?;
+
+        let tokens: TokenV3 = (wallet_keyset.mint_url.to_owned(), proofs).into();
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .add_proofs(&mut tx, &tokens.proofs())
+            .await?;
+        self.localstore
+            .add_transaction(
+                &mut tx,
+                &WalletTransaction::new(
+                    TransactionKind::Mint,
+                    wallet_keyset,
+                    tokens.total_amount(),
+                ),
+            )
+            .await?;
+        self.localstore.delete_operation(&mut tx, operation).await?;
+        if let Some(quote_id) = &operation.quote_id {
+            self.localstore
+                .update_mint_quote_state(&mut tx, quote_id, MintQuoteState::Issued)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(tokens)
+    }
+
+    async fn post_mint(
+        &self,
+        operation: &WalletOperation,
+    ) -> Result<Vec<BlindedSignature>, MokshaWalletError> {
+        let quote_id = operation.quote_id.clone().unwrap_or_default();
+        Ok(match operation.kind {
+            OperationKind::MintOnchain => {
+                self.client
+                    .post_mint_onchain(&operation.mint_url, quote_id, operation.blinded_messages())
+                    .await?
+                    .signatures
+            }
+            _ => {
+                self.client
+                    .post_mint_bolt11(&operation.mint_url, quote_id, operation.blinded_messages())
+                    .await?
+                    .signatures
+            }
+        })
+    }
+
+    /// Creates blank outputs for the fee reserve of a melt. The mint returns the overpaid fees as change.
+    pub async fn create_blank(
+        &self,
+        fee_reserve: Amount,
+        keyset_id: &KeysetId,
+    ) -> Result<Vec<PendingOutput>, MokshaWalletError> {
+        if fee_reserve.0 == 0 {
+            return Ok(vec![]);
+        }
+
+        // the mint chooses the amounts of the change, so the split strategy does not apply here
+        let fee_reserve_float = fee_reserve.0 as f64;
+        let count = (fee_reserve_float.log2().ceil() as u64).max(1);
+
+        self.create_outputs(keyset_id, vec![1; count as usize].into())
+            .await
+    }
+
+    fn create_proofs_from_blinded_signatures(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        signatures: &[BlindedSignature],
+        outputs: &[PendingOutput],
+    ) -> Result<Proofs, MokshaWalletError> {
+        Ok(signatures
+            .iter()
+            .zip(outputs)
+            .map(|(p, output)| {
+                let key = wallet_keyset
+                    .public_keys
+                    .get(&p.amount)
+                    .ok_or(MokshaWalletError::PubkeyNotFound)?;
+                let pub_alice =
+                    self.dhke
+                        .step3_alice(p.c_, output.blinding_factor.clone(), *key)?;
+                Ok(Proof::new(
+                    p.amount,
+                    output.secret.clone(),
+                    pub_alice,
+                    wallet_keyset.keyset_id.to_string(),
+                ))
+            })
+            .collect::<Result<Vec<_>, MokshaWalletError>>()?
+            .into())
+    }
+
+    /// Exports keysets, proofs in all states, transactions, trusted mints and mint quotes into a backup that is
+    /// encrypted with `password`
+    pub async fn export_backup(&self, password: &str) -> Result<String, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let backup = WalletBackup {
+            version: BACKUP_VERSION,
+            created_at: chrono::Utc::now().timestamp() as u64,
+            keysets: self.localstore.get_keysets(&mut tx).await?,
+            proofs: self.localstore.get_stored_proofs(&mut tx).await?,
+            transactions: self
+                .localstore
+                .get_transactions(&mut tx, &TransactionFilter::default())
+                .await?,
+            trusted_mints: self.localstore.get_trusted_mints(&mut tx).await?,
+            mint_quotes: self.localstore.get_mint_quotes(&mut tx).await?,
+        };
+        tx.commit().await?;
+        backup.encrypt(password)
+    }
+
+    /// Merges a backup created by [`Wallet::export_backup`] into the wallet. Proofs that are already stored
+    /// are skipped by their secret and keep their state, the last index of known keysets is only increased.
+    pub async fn import_backup(
+        &self,
+        backup: &str,
+        password: &str,
+    ) -> Result<BackupImportResult, MokshaWalletError> {
+        let backup = WalletBackup::decrypt(backup, password)?;
+        let mut result = BackupImportResult::default();
+
+        let mut tx = self.localstore.begin_tx().await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        for keyset in backup.keysets {
+            match keysets
+                .iter()
+                .find(|k| k.keyset_id == keyset.keyset_id && k.mint_url == keyset.mint_url)
+            {
+                Some(existing) if existing.last_index < keyset.last_index => {
+                    self.localstore
+                        .update_keyset_last_index(
+                            &mut tx,
+                            &WalletKeyset {
+                                last_index: keyset.last_index,
+                                ..existing.clone()
+                            },
+                        )
+                        .await?;
+                }
+                Some(_) => {}
+                None => {
+                    self.localstore
+                        .upsert_keyset(&mut tx, &WalletKeyset { id: None, ..keyset })
+                        .await?;
+                    result.keysets += 1;
+                }
+            }
+        }
+
+        let secrets = self
+            .localstore
+            .get_proof_secrets(&mut tx)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let stored_proofs = backup
+            .proofs
+            .into_iter()
+            .filter(|stored| !secrets.contains(&stored.proof.secret))
+            .collect::<Vec<_>>();
+        let proofs: Proofs = stored_proofs
+            .iter()
+            .map(|stored| stored.proof.clone())
+            .collect::<Vec<_>>()
+            .into();
+        self.localstore.add_proofs(&mut tx, &proofs).await?;
+        result.proofs = proofs.len();
+        result.amount = proofs.total_amount();
+
+        // proofs are added as unspent and then moved to their state
+        let mut by_state = HashMap::<_, Vec<Proof>>::new();
+        for stored in stored_proofs {
+            by_state
+                .entry((stored.state, stored.token))
+                .or_default()
+                .push(stored.proof);
+        }
+        for ((state, token), proofs) in by_state {
+            let proofs: Proofs = proofs.into();
+            match (state, token) {
+                (ProofState::Unspent, _) => {}
+                (ProofState::Reserved, Some(token)) => {
+                    self.localstore
+                        .reserve_proofs(&mut tx, &proofs, &token)
+                        .await?;
+                }
+                (state, _) => {
+                    self.localstore
+                        .update_proof_state(&mut tx, &proofs, ProofState::Unspent, state)
+                        .await?;
+                }
+            }
+        }
+
+        let transactions = self
+            .localstore
+            .get_transactions(&mut tx, &TransactionFilter::default())
+            .await?
+            .into_iter()
+            .map(|transaction| WalletTransaction {
+                id: None,
+                ..transaction
+            })
+            .collect::<Vec<_>>();
+        for transaction in backup.transactions {
+            let transaction = WalletTransaction {
+                id: None,
+                ..transaction
+            };
+            if !transactions.contains(&transaction) {
+                self.localstore
+                    .add_transaction(&mut tx, &transaction)
+                    .await?;
+                result.transactions += 1;
+            }
+        }
+
+        let trusted_mints = self.localstore.get_trusted_mints(&mut tx).await?;
+        let has_preferred_mint = trusted_mints.iter().any(|mint| mint.preferred);
+        for trusted_mint in backup.trusted_mints {
+            if trusted_mints
+                .iter()
+                .all(|mint| mint.mint_url != trusted_mint.mint_url)
+            {
+                self.localstore
+                    .upsert_trusted_mint(
+                        &mut tx,
+                        &TrustedMint {
+                            preferred: trusted_mint.preferred && !has_preferred_mint,
+                            ..trusted_mint
+                        },
+                    )
+                    .await?;
+                result.trusted_mints += 1;
+            }
+        }
+
+        let quote_ids = self
+            .localstore
+            .get_mint_quotes(&mut tx)
+            .await?
+            .into_iter()
+            .map(|quote| quote.quote_id)
+            .collect::<HashSet<_>>();
+        for quote in backup.mint_quotes {
+            if !quote_ids.contains(&quote.quote_id) {
+                self.localstore.add_mint_quote(&mut tx, &quote).await?;
+                result.mint_quotes += 1;
+            }
+        }
+        tx.commit().await?;
+        Ok(result)
+    }
+
+    /// Returns the BIP39 mnemonic of the wallet seed
+    pub fn seed_words(&self) -> &str {
+        self.secret.seed_words()
+    }
+
+    /// Restores the proofs of a mint from the wallet seed as described in
+    /// [Nut-13](https://github.com/cashubtc/nuts/blob/main/13.md). The outputs of every keyset are derived in
+    /// batches until the mint has not signed any output of [`RESTORE_EMPTY_BATCHES`] batches in a row. Spent
+    /// proofs and proofs that are already in the wallet are skipped. Returns the restored amount.
+    pub async fn restore(&self, mint_url: &Url) -> Result<u64, MokshaWalletError> {
+        self.add_mint_keysets(mint_url).await?;
+        let keysets = self
+            .get_wallet_keysets()
+            .await?
+            .into_iter()
+            .filter(|keyset| &keyset.mint_url == mint_url)
+            .collect::<Vec<_>>();
+        let mut tx = self.localstore.begin_tx().await?;
+        let stored_secrets = self
+            .localstore
+            .get_proof_secrets(&mut tx)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        tx.commit().await?;
+
+        let mut restored = 0;
+        for keyset in keysets {
+            let (proofs, last_counter) = self.restore_keyset(&keyset).await?;
+            let proofs: Proofs = self
+                .unspent_proofs(mint_url, proofs)
+                .await?
+                .into_iter()
+                .filter(|proof| !stored_secrets.contains(&proof.secret))
+                .collect::<Vec<_>>()
+                .into();
+
+            let mut tx = self.localstore.begin_tx().await?;
+            if let Some(last_counter) = last_counter {
+                if last_counter as u64 > keyset.last_index {
+                    self.localstore
+                        .update_keyset_last_index(
+                            &mut tx,
+                            &WalletKeyset {
+                                last_index: last_counter as u64,
+                                ..keyset.clone()
+                            },
+                        )
+                        .await?;
+                }
+            }
+            if !proofs.is_empty() {
+                self.localstore.add_proofs(&mut tx, &proofs).await?;
+                self.localstore
+                    .add_transaction(
+                        &mut tx,
+                        &WalletTransaction::new(
+                            TransactionKind::Receive,
+                            &keyset,
+                            proofs.total_amount(),
+                        ),
+                    )
+                    .await?;
+            }
+            tx.commit().await?;
+            restored += proofs.total_amount();
+        }
+        Ok(restored)
+    }
+
+    /// Returns all proofs the mint has signed for the keyset and the highest counter of a signed output
+    async fn restore_keyset(
+        &self,
+        keyset: &WalletKeyset,
+    ) -> Result<(Vec<Proof>, Option<u32>), MokshaWalletError> {
+        let mut proofs = vec![];
+        let mut last_counter = None;
+        let mut empty_batches = 0;
+        let mut start = 0;
+
+        while empty_batches < RESTORE_EMPTY_BATCHES {
+            let outputs = self
+                .secret
+                .derive_range(&keyset.keyset_id, start, RESTORE_BATCH_SIZE)?
+                .into_iter()
+                .zip(start..)
+                .map(|((secret, blinding_factor), counter)| {
+                    let b_ = self.dhke.step1_alice(secret.clone(), &blinding_factor)?;
+                    Ok(PendingOutput {
+                        counter,
+                        secret,
+                        blinding_factor,
+                        blinded_message: BlindedMessage {
+                            amount: 1,
+                            b_,
+                            id: keyset.keyset_id.to_string(),
+                        },
+                    })
+                })
+                .collect::<Result<Vec<_>, MokshaWalletError>>()?;
+
+            let (signed_outputs, signatures) = self.post_restore_outputs(keyset, &outputs).await?;
+
+            if signed_outputs.is_empty() {
+                empty_batches += 1;
+            } else {
+                empty_batches = 0;
+                last_counter = signed_outputs.iter().map(|o| o.counter).max();
+            }
+            proofs.extend(
+                self.create_proofs_from_blinded_signatures(keyset, &signatures, &signed_outputs)?
+                    .proofs(),
+            );
+            start += RESTORE_BATCH_SIZE;
+        }
+        Ok((proofs, last_counter))
+    }
+
+    /// Asks the mint for the signatures of the outputs (Nut-09) and returns the outputs it has signed together
+    /// with their signatures
+    async fn post_restore_outputs(
+        &self,
+        keyset: &WalletKeyset,
+        outputs: &[PendingOutput],
+    ) -> Result<(Vec<PendingOutput>, Vec<BlindedSignature>), MokshaWalletError> {
+        let response = self
+            .client
+            .post_restore(
+                &keyset.mint_url,
+                outputs.iter().map(|o| o.blinded_message.clone()).collect(),
+            )
+            .await?;
+        let signed_outputs = response
+            .outputs
+            .iter()
+            .map(|message| {
+                outputs
+                    .iter()
+                    .find(|o| o.blinded_message.b_ == message.b_)
+                    .cloned()
+                    .ok_or_else(|| {
+                        MokshaWalletError::UnexpectedResponse(
+                            "mint restored an unknown output".to_owned(),
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, MokshaWalletError>>()?;
+        Ok((signed_outputs, response.signatures))
+    }
+
+    /// Asks the mint for the state of the proofs and returns the unspent ones
+    async fn unspent_proofs(
+        &self,
+        mint_url: &Url,
+        proofs: Vec<Proof>,
+    ) -> Result<Vec<Proof>, MokshaWalletError> {
+        let states = self.proof_states(mint_url, &proofs).await?;
+        Ok(proofs
+            .into_iter()
+            .zip(states)
+            .filter(|(_, state)| state == &SpentState::Unspent)
+            .map(|(proof, _)| proof)
+            .collect())
+    }
+
+    /// Asks the mint for the state of the proofs (Nut-07) and returns the states in the order of the proofs
+    async fn proof_states(
+        &self,
+        mint_url: &Url,
+        proofs: &[Proof],
+    ) -> Result<Vec<SpentState>, MokshaWalletError> {
+        if proofs.is_empty() {
+            return Ok(vec![]);
+        }
+        let ys = proofs
+            .iter()
+            .map(|proof| proof.y())
+            .collect::<Result<Vec<_>, _>>()?;
+        let states = self
+            .client
+            .post_check_state(mint_url, ys.clone())
+            .await?
+            .states;
+
+        ys.iter()
+            .map(|y| {
+                states
+                    .iter()
+                    .find(|state| &state.y == y)
+                    .map(|state| state.state.clone())
+                    .ok_or_else(|| {
+                        MokshaWalletError::UnexpectedResponse(
+                            "mint returned no state for a proof".to_owned(),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the combined state of the inputs of an operation: spent if any input is spent, pending if any
+    /// input is pending. `None` if the mint doesn't support checking the state.
+    async fn inputs_state(
+        &self,
+        operation: &WalletOperation,
+    ) -> Result<Option<SpentState>, MokshaWalletError> {
+        let states = match self
+            .proof_states(&operation.mint_url, &operation.inputs.proofs())
+            .await
+        {
+            Ok(states) => states,
+            Err(e) if is_rejected_by_mint(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(if states.contains(&SpentState::Spent) {
+            SpentState::Spent
+        } else if states.contains(&SpentState::Pending) {
+            SpentState::Pending
+        } else {
+            SpentState::Unspent
+        }))
+    }
+
+    /// Returns the proofs of the journaled outputs the mint has already signed (Nut-09) or `None` if the mint
+    /// doesn't support restoring signatures
+    async fn restore_operation_outputs(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        operation: &WalletOperation,
+    ) -> Result<Option<Proofs>, MokshaWalletError> {
+        if operation.outputs.is_empty() {
+            return Ok(Some(Proofs::empty()));
+        }
+        match self
+            .post_restore_outputs(wallet_keyset, &operation.outputs)
+            .await
+        {
+            Ok((outputs, signatures)) => Ok(Some(self.create_proofs_from_blinded_signatures(
+                wallet_keyset,
+                &signatures,
+                &outputs,
+            )?)),
+            Err(e) if is_rejected_by_mint(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Deletes the inputs of the operation, stores the proofs and removes the operation from the journal
+    async fn complete_operation(
+        &self,
+        operation: &WalletOperation,
+        proofs: &Proofs,
+    ) -> Result<(), MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        self.localstore
+            .delete_proofs(&mut tx, &operation.inputs)
+            .await?;
+        self.localstore.add_proofs(&mut tx, proofs).await?;
+        self.localstore.delete_operation(&mut tx, operation).await?;
+        if let (Some(quote_id), OperationKind::MintBolt11 | OperationKind::MintOnchain) =
+            (&operation.quote_id, &operation.kind)
+        {
+            self.localstore
+                .update_mint_quote_state(&mut tx, quote_id, MintQuoteState::Issued)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Resolves operations that were interrupted before their result was stored, e.g. because the
+    /// wallet crashed while waiting for the mint. Should be called on startup.
+    pub async fn recover_pending_operations(
+        &self,
+    ) -> Result<Vec<(WalletOperation, RecoveryResult)>, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let operations = self.localstore.get_operations(&mut tx).await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        tx.commit().await?;
+
+        let mut results = vec![];
+        for operation in operations {
+            let wallet_keyset = match keysets
+                .iter()
+                .find(|k| k.keyset_id == operation.keyset_id && k.mint_url == operation.mint_url)
+            {
+                Some(keyset) => keyset,
+                None => {
+                    results.push((operation, RecoveryResult::Pending));
+                    continue;
+                }
+            };
+
+            let result = match operation.kind {
+                OperationKind::Swap => self.recover_swap(wallet_keyset, &operation).await?,
+                OperationKind::MintBolt11 | OperationKind::MintOnchain => {
+                    self.recover_mint(wallet_keyset, &operation).await?
+                }
+                OperationKind::MeltBolt11 | OperationKind::MeltOnchain => {
+                    self.recover_melt(wallet_keyset, &operation).await?
+                }
+            };
+            results.push((operation, result));
+        }
+        Ok(results)
+    }
+
+    /// Restores the signatures of the journaled outputs, if the mint already processed the swap. Otherwise
+    /// the swap is sent again if the inputs are still unspent.
+    async fn recover_swap(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        operation: &WalletOperation,
+    ) -> Result<RecoveryResult, MokshaWalletError> {
+        let restored = match self
+            .restore_operation_outputs(wallet_keyset, operation)
+            .await
+        {
+            Ok(Some(proofs)) if !proofs.is_empty() => {
+                self.complete_operation(operation, &proofs).await?;
+                return Ok(RecoveryResult::Restored(proofs.total_amount()));
+            }
+            Ok(restored) => restored,
+            Err(_) => return Ok(RecoveryResult::Pending),
+        };
+        let can_restore = restored.is_some();
+
+        let inputs_state = match self.inputs_state(operation).await {
+            Ok(state) => state,
+            Err(_) => return Ok(RecoveryResult::Pending),
+        };
+        match inputs_state {
+            // the outputs are not signed, so the inputs were spent by another request
+            Some(SpentState::Spent) if can_restore => {
+                let mut tx = self.localstore.begin_tx().await?;
+                self.localstore
+                    .delete_proofs(&mut tx, &operation.inputs)
+                    .await?;
+                self.localstore.delete_operation(&mut tx, operation).await?;
+                tx.commit().await?;
+                return Ok(RecoveryResult::Spent(operation.inputs.total_amount()));
+            }
+            Some(SpentState::Unspent) | None => {}
+            // the signatures of a processed swap can't be restored, keep the operation until they can
+            Some(_) => return Ok(RecoveryResult::Pending),
+        }
+
+        let response = self
+            .client
+            .post_swap(
+                &operation.mint_url,
+                operation.inputs.clone(),
+                operation.blinded_messages(),
+            )
+            .await;
+
+        match response {
+            Ok(response) if !response.signatures.is_empty() => {
+                let proofs = self.create_proofs_from_blinded_signatures(
+                    wallet_keyset,
+                    &response.signatures,
+                    &operation.outputs,
+                )?;
+                self.complete_operation(operation, &proofs).await?;
+                Ok(RecoveryResult::Replayed(proofs.total_amount()))
+            }
+            // the rejection only proves that the swap was not processed, if the state of the inputs is known
+            Ok(_) if inputs_state.is_some() => {
+                self.rollback_operation(operation).await?;
+                Ok(RecoveryResult::RolledBack(operation.inputs.total_amount()))
+            }
+            Err(e) if is_rejected_by_mint(&e) && inputs_state.is_some() => {
+                self.rollback_operation(operation).await?;
+                Ok(RecoveryResult::RolledBack(operation.inputs.total_amount()))
+            }
+            _ => Ok(RecoveryResult::Pending),
+        }
+    }
+
+    /// Restores the signatures of the journaled outputs, if the mint already issued the tokens of the quote.
+    /// Otherwise the mint request is sent again.
+    async fn recover_mint(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        operation: &WalletOperation,
+    ) -> Result<RecoveryResult, MokshaWalletError> {
+        let restored = match self
+            .restore_operation_outputs(wallet_keyset, operation)
+            .await
+        {
+            Ok(Some(proofs)) if !proofs.is_empty() => {
+                self.complete_operation(operation, &proofs).await?;
+                return Ok(RecoveryResult::Restored(proofs.total_amount()));
+            }
+            Ok(restored) => restored,
+            Err(_) => return Ok(RecoveryResult::Pending),
+        };
+
+        match self.post_mint(operation).await {
+            Ok(signatures) => {
+                let proofs = self.create_proofs_from_blinded_signatures(
+                    wallet_keyset,
+                    &signatures,
+                    &operation.outputs,
+                )?;
+                self.complete_operation(operation, &proofs).await?;
+                Ok(RecoveryResult::Replayed(proofs.total_amount()))
+            }
+            // the quote might still be paid, e.g. by a transfer whose melt is in flight
+            Err(MokshaWalletError::InvoiceNotPaidYet(_, _))
+                if !self.is_mint_quote_expired(operation).await =>
+            {
+                Ok(RecoveryResult::Pending)
+            }
+            // an expired quote that was never paid can't have been issued
+            Err(e)
+                if is_rejected_by_mint(&e)
+                    && (restored.is_some()
+                        || matches!(e, MokshaWalletError::InvoiceNotPaidYet(_, _))) =>
+            {
+                self.rollback_operation(operation).await?;
+                Ok(RecoveryResult::RolledBack(0))
+            }
+            // without restore the rejection might mean that the quote was already issued to this wallet
+            Err(_) => Ok(RecoveryResult::Pending),
+        }
+    }
+
+    /// Returns false if the mint can't be reached to check the expiry of the quote
+    async fn is_mint_quote_expired(&self, operation: &WalletOperation) -> bool {
+        if operation.kind != OperationKind::MintBolt11 {
+            return true;
+        }
+        let quote_id = operation.quote_id.clone().unwrap_or_default();
+        match self
+            .client
+            .get_mint_quote_bolt11(&operation.mint_url, quote_id)
+            .await
+        {
+            Ok(quote) => quote.expiry.map_or(true, |expiry| {
+                expiry < chrono::Utc::now().timestamp() as u64
+            }),
+            Err(e) => is_rejected_by_mint(&e),
+        }
+    }
+
+    /// Checks the melt quote. If it is paid the inputs are spent and the change is restored, otherwise the
+    /// inputs are added back to the wallet.
+    async fn recover_melt(
+        &self,
+        wallet_keyset: &WalletKeyset,
+        operation: &WalletOperation,
+    ) -> Result<RecoveryResult, MokshaWalletError> {
+        let quote_id = operation.quote_id.clone().unwrap_or_default();
+        let paid = match operation.kind {
+            OperationKind::MeltOnchain => self
+                .client
+                .get_melt_quote_onchain(&operation.mint_url, quote_id)
+                .await
+                .map(|quote| quote.paid),
+            _ => self
+                .client
+                .get_melt_quote_bolt11(&operation.mint_url, quote_id)
+                .await
+                .map(|quote| quote.paid),
+        };
+
+        match paid {
+            Ok(true) => {
+                // the change of the fee reserve (Nut-08) was signed for the journaled outputs
+                let change = match self
+                    .restore_operation_outputs(wallet_keyset, operation)
+                    .await
+                {
+                    Ok(Some(change)) => change,
+                    Ok(None) => Proofs::empty(),
+                    Err(_) => return Ok(RecoveryResult::Pending),
+                };
+                self.complete_operation(operation, &change).await?;
+                Ok(RecoveryResult::Spent(
+                    operation
+                        .inputs
+                        .total_amount()
+                        .saturating_sub(change.total_amount()),
+                ))
+            }
+            Ok(false) => {
+                self.rollback_operation(operation).await?;
+                Ok(RecoveryResult::RolledBack(operation.inputs.total_amount()))
+            }
+            Err(e) if is_rejected_by_mint(&e) => {
+                self.rollback_operation(operation).await?;
+                Ok(RecoveryResult::RolledBack(operation.inputs.total_amount()))
+            }
+            Err(_) => Ok(RecoveryResult::Pending),
+        }
+    }
+
+    pub async fn list_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<WalletTransaction>, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let transactions = self.localstore.get_transactions(&mut tx, filter).await?;
+        tx.commit().await?;
+        Ok(transactions)
+    }
+
+    pub async fn get_proofs(&self) -> Result<Proofs, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let proofs = self.localstore.get_proofs(&mut tx).await?;
+        tx.commit().await?;
+        Ok(proofs)
+    }
+
+    /// Returns the unspent proofs of all keysets of the mint with the given unit
+    pub async fn get_proofs_by_mint_and_unit(
+        &self,
+        mint_url: &Url,
+        currency_unit: &CurrencyUnit,
+    ) -> Result<Proofs, MokshaWalletError> {
+        let mut tx = self.localstore.begin_tx().await?;
+        let all_proofs = self.localstore.get_proofs(&mut tx).await?;
+        let keysets = self.localstore.get_keysets(&mut tx).await?;
+        tx.commit().await?;
+
+        let keyset_ids = keysets
+            .get_by_mint_and_unit(mint_url, currency_unit)
+            .into_iter()
+            .map(|keyset| keyset.keyset_id.to_string())
+            .collect::<HashSet<String>>();
+        Ok(all_proofs
+            .proofs()
+            .into_iter()
+            .filter(|proof| keyset_ids.contains(&proof.keyset_id))
+            .collect::<Vec<Proof>>()
+            .into())
+    }
+}
+
+/// Returns true if the mint answered with an error, i.e. the request was not processed
+const fn is_rejected_by_mint(err: &MokshaWalletError) -> bool {
+    matches!(
+        err,
+        MokshaWalletError::MintError(_) | MokshaWalletError::InvoiceNotPaidYet(_, _)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::backup::BackupImportResult;
+    use crate::client::MockCashuClient;
+    use crate::error::MokshaWalletError;
+    use crate::localstore::sqlite::SqliteLocalStore;
+    use crate::localstore::{
+        LocalStore, MintQuoteState, OperationKind, TransactionFilter, TransactionKind,
+        WalletKeyset, WalletMintQuote, WalletOperation, WalletTransaction,
+    };
+    use crate::secret::DeterministicSecret;
+    use crate::wallet::{MintQuoteResult, MintRefreshResult, RecoveryResult, WalletBuilder};
+
+    use moksha_core::amount::SplitStrategy;
+    use moksha_core::blind::{BlindedMessage, BlindedSignature};
+    use moksha_core::fixture::{read_fixture, read_fixture_as};
+    use moksha_core::keyset::{KeysetId, Keysets, MintKeyset};
+    use moksha_core::primitives::{
+        CurrencyUnit, KeyResponse, KeysResponse, MintInfoResponse, PaymentMethod,
+        PostCheckStateResponse, PostMeltBolt11Response, PostMeltQuoteBolt11Response,
+        PostMintBolt11Response, PostMintQuoteBolt11Response, PostRestoreResponse, PostSwapResponse,
+        ProofStateResponse, SpentState,
+    };
+
+    use moksha_core::dhke::Dhke;
+    use moksha_core::p2pk::P2PKSecret;
+    use moksha_core::payment_request::PaymentRequest;
+    use moksha_core::proof::{Proof, ProofDleq, Proofs};
+    use moksha_core::token::TokenV3;
+    use secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use std::str::FromStr;
+    use url::Url;
+
+    fn create_mock() -> MockCashuClient {
+        let keys = MintKeyset::new("mykey", "");
+        let key_response = KeyResponse {
             keys: keys.public_keys.clone(),
             id: keys.keyset_id.clone(),
             unit: CurrencyUnit::Sat,
         };
-        let keys_response = KeysResponse::new(key_response.clone());
-        let keys_by_id_response = keys_response.clone();
-        let keysets = Keysets::new(keys.keyset_id, CurrencyUnit::Sat, true);
+        let keys_response = KeysResponse::new(key_response.clone());
+        let keys_by_id_response = keys_response.clone();
+        let keysets = Keysets::new(keys.keyset_id, CurrencyUnit::Sat, true);
+
+        let mut client = MockCashuClient::default();
+        client
+            .expect_get_keys()
+            .returning(move |_| Ok(keys_response.clone()));
+        client
+            .expect_get_keysets()
+            .returning(move |_| Ok(keysets.clone()));
+        client
+            .expect_get_keys_by_id()
+            .returning(move |_, _| Ok(keys_by_id_response.clone()));
+        client.expect_is_v1_supported().returning(move |_| Ok(true));
+        client
+    }
+
+    #[tokio::test]
+    async fn test_blank_blinded_messages_1000_sats() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let client = create_mock();
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        let result = wallet
+            .create_blank(1000.into(), &KeysetId::new("00d31cecf59d18c0")?)
+            .await;
+        println!("{:?}", result);
+        assert!(result.is_ok());
+        let result = result.unwrap();
+        assert!(result.len() == 10);
+        assert!(result.first().unwrap().blinded_message.amount == 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blank_blinded_messages_with_split_strategy() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .with_split_strategy(SplitStrategy::TargetCount(3))
+            .build()
+            .await?;
+        let result = wallet
+            .create_blank(1000.into(), &wallet_keyset.keyset_id)
+            .await?;
+        // the mint picks the change amounts, so the blanks only cover ceil(log2(fee_reserve))
+        assert_eq!(10, result.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_with_split_strategy() -> anyhow::Result<()> {
+        let keyset = create_test_wallet_keyset()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .times(1)
+            .returning(|_, _, outputs| {
+                let signatures = outputs
+                    .iter()
+                    .map(|o| BlindedSignature {
+                        amount: o.amount,
+                        c_: o.b_,
+                        id: o.id.clone(),
+                    })
+                    .collect();
+                Ok(PostSwapResponse { signatures })
+            });
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_split_strategy(SplitStrategy::Custom(vec![10, 10]))
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let tokens: TokenV3 = (
+            keyset.mint_url.clone(),
+            CurrencyUnit::Sat,
+            proofs_of_keyset(&tokens, &keyset),
+        )
+            .into();
+        let (kept_tokens, send_tokens) = wallet.swap_tokens(&keyset, &tokens, 20.into()).await?;
+        let amounts = |tokens: &TokenV3| {
+            let mut amounts = tokens
+                .proofs()
+                .proofs()
+                .iter()
+                .map(|proof| proof.amount)
+                .collect::<Vec<_>>();
+            amounts.sort_unstable();
+            amounts
+        };
+        // only the kept tokens are split with the split strategy
+        assert_eq!(vec![2, 2, 4, 8, 8, 16], amounts(&kept_tokens));
+        assert_eq!(vec![4, 16], amounts(&send_tokens));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blank_blinded_messages_zero_sats() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let client = create_mock();
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        let result = wallet
+            .create_blank(0.into(), &KeysetId::new("00d31cecf59d18c0")?)
+            .await;
+        println!("{:?}", result);
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blank_blinded_messages_serialize() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let client = create_mock();
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let result = wallet
+            .create_blank(4000.into(), &KeysetId::new("00d31cecf59d18c0")?)
+            .await?;
+        for output in result {
+            let out = serde_json::to_string(&output.blinded_message)?;
+            assert!(!out.is_empty());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mint_tokens() -> anyhow::Result<()> {
+        let mint_response =
+            read_fixture_as::<PostMintBolt11Response>("post_mint_response_20.json")?;
+
+        let mut client = create_mock();
+        client
+            .expect_post_mint_bolt11()
+            .returning(move |_, _, _| Ok(mint_response.clone()));
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let result = wallet
+            .mint_tokens(
+                &wallet_keyset,
+                &PaymentMethod::Bolt11,
+                20.into(),
+                "hash".to_string(),
+            )
+            .await?;
+        assert_eq!(20, result.total_amount());
+        result.tokens.into_iter().for_each(|t| {
+            assert_eq!(wallet_keyset.mint_url, t.mint.expect("mint is empty"));
+        });
+
+        let transactions = wallet
+            .list_transactions(&TransactionFilter::default())
+            .await?;
+        assert_eq!(1, transactions.len());
+        assert_eq!(TransactionKind::Mint, transactions[0].kind);
+        assert_eq!(20, transactions[0].amount);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap() -> anyhow::Result<()> {
+        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .returning(move |_, _, _| Ok(split_response.clone()));
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let tokens = read_fixture("token_64.cashu")?.try_into()?;
+        let result = wallet.swap_tokens(&keyset, &tokens, 20.into()).await?;
+
+        let first = result.0;
+
+        assert_eq!(CurrencyUnit::Sat, first.clone().currency_unit.unwrap());
+        assert_eq!(24, first.total_amount());
+
+        let second = result.1;
+
+        assert_eq!(CurrencyUnit::Sat, second.clone().currency_unit.unwrap());
+        assert_eq!(40, second.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_balance() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
+        let fixture: TokenV3 = fixture.try_into()?;
+        let local_store = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = local_store.begin_tx().await?;
+        local_store.add_proofs(&mut tx, &fixture.proofs()).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(local_store)
+            .build()
+            .await?;
+
+        let result = wallet.get_balance().await?;
+        assert_eq!(60, result);
+        Ok(())
+    }
+    // FIXME
+
+    #[tokio::test]
+    async fn test_pay_invoice() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
+
+        let local_store = SqliteLocalStore::with_in_memory().await?;
+        let fixture: TokenV3 = fixture.try_into()?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = local_store.begin_tx().await?;
+        local_store
+            .add_proofs(&mut tx, &proofs_of_keyset(&fixture, &wallet_keyset))
+            .await?;
+        local_store.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?; // 60 tokens (4,8,16,32)
+        let mut mock_client = create_mock();
+        mock_client
+            .expect_post_melt_bolt11()
+            .returning(move |_, _, _, _| Ok(melt_response.clone()));
+
+        let quote_response =
+            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
+        mock_client
+            .expect_post_melt_quote_bolt11()
+            .returning(move |_, _, _, _| Ok(quote_response.clone()));
+
+        mock_client
+            .expect_post_swap()
+            .returning(|_, _, outputs| Ok(swap_response(outputs)));
+
+        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
+        let wallet = WalletBuilder::new()
+            .with_client(mock_client)
+            .with_localstore(local_store)
+            .build()
+            .await?;
+
+        // 21 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+
+        let quote = wallet
+            .get_melt_quote_bolt11(&mint_url, invoice.clone(), CurrencyUnit::Sat)
+            .await?;
+        let result = wallet.pay_invoice(&wallet_keyset, &quote, invoice).await?;
+        assert!(result.0.paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice_mpp() -> anyhow::Result<()> {
+        let keyset_a = create_test_wallet_keyset()?;
+        let keyset_b = WalletKeyset {
+            keyset_id: KeysetId::new("00d31cecf59d18c1")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            ..keyset_a.clone()
+        };
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+        let proofs_a = proofs_of_keyset(&fixture, &keyset_a)
+            .proofs()
+            .into_iter()
+            .filter(|p| p.amount == 16)
+            .collect::<Vec<_>>();
+        let proofs_b = proofs_of_keyset(&fixture, &keyset_b)
+            .proofs()
+            .into_iter()
+            .filter(|p| p.amount == 4 || p.amount == 8)
+            .collect::<Vec<_>>();
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &proofs_a.into()).await?;
+        localstore.add_proofs(&mut tx, &proofs_b.into()).await?;
+        localstore.upsert_keyset(&mut tx, &keyset_a).await?;
+        localstore.upsert_keyset(&mut tx, &keyset_b).await?;
+        tx.commit().await?;
+
+        let mut mock_client = create_mock();
+        let mint_info = read_fixture_as::<MintInfoResponse>("mint_info_mpp.json")?;
+        mock_client
+            .expect_get_info()
+            .returning(move |_| Ok(mint_info.clone()));
+        mock_client
+            .expect_post_melt_quote_bolt11()
+            .returning(|mint_url, _, _, options| {
+                let amount_msat = options.and_then(|o| o.mpp).expect("mpp not set").amount;
+                Ok(PostMeltQuoteBolt11Response {
+                    quote: mint_url.to_string(),
+                    amount: amount_msat / 1_000,
+                    fee_reserve: 0,
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?;
+        mock_client
+            .expect_post_melt_bolt11()
+            .times(2)
+            .returning(move |_, _, _, _| Ok(melt_response.clone()));
+
+        let wallet = WalletBuilder::default()
+            .with_client(mock_client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        // 20 sats, neither mint has enough funds on its own
+        let invoice = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40".to_string();
+        let results = wallet.pay_invoice_mpp(invoice, CurrencyUnit::Sat).await?;
+
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|part| part
+            .result
+            .as_ref()
+            .is_ok_and(|(response, _)| response.paid)));
+        let balances = wallet.get_balances().await?;
+        assert_eq!(
+            0,
+            balances.by_mint_and_unit(&keyset_a.mint_url, &CurrencyUnit::Sat)
+        );
+        assert_eq!(
+            8,
+            balances.by_mint_and_unit(&keyset_b.mint_url, &CurrencyUnit::Sat)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice_mpp_keeps_paid_parts() -> anyhow::Result<()> {
+        let keyset_a = create_test_wallet_keyset()?;
+        let keyset_b = WalletKeyset {
+            keyset_id: KeysetId::new("00d31cecf59d18c1")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            ..keyset_a.clone()
+        };
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+        let proofs_a = proofs_of_keyset(&fixture, &keyset_a)
+            .proofs()
+            .into_iter()
+            .filter(|p| p.amount == 16)
+            .collect::<Vec<_>>();
+        let proofs_b = proofs_of_keyset(&fixture, &keyset_b)
+            .proofs()
+            .into_iter()
+            .filter(|p| p.amount == 4 || p.amount == 8)
+            .collect::<Vec<_>>();
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &proofs_a.into()).await?;
+        localstore.add_proofs(&mut tx, &proofs_b.into()).await?;
+        localstore.upsert_keyset(&mut tx, &keyset_a).await?;
+        localstore.upsert_keyset(&mut tx, &keyset_b).await?;
+        tx.commit().await?;
+
+        let mut mock_client = create_mock();
+        let mint_info = read_fixture_as::<MintInfoResponse>("mint_info_mpp.json")?;
+        mock_client
+            .expect_get_info()
+            .returning(move |_| Ok(mint_info.clone()));
+        mock_client
+            .expect_post_melt_quote_bolt11()
+            .returning(|mint_url, _, _, options| {
+                let amount_msat = options.and_then(|o| o.mpp).expect("mpp not set").amount;
+                Ok(PostMeltQuoteBolt11Response {
+                    quote: mint_url.to_string(),
+                    amount: amount_msat / 1_000,
+                    fee_reserve: 0,
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?;
+        // the part of the second mint fails
+        mock_client
+            .expect_post_melt_bolt11()
+            .times(2)
+            .returning(move |mint_url, _, _, _| match mint_url.port() {
+                Some(3339) => Err(MokshaWalletError::MintError("payment failed".to_owned())),
+                _ => Ok(melt_response.clone()),
+            });
+
+        let wallet = WalletBuilder::default()
+            .with_client(mock_client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        // 20 sats, neither mint has enough funds on its own
+        let invoice = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40".to_string();
+        let results = wallet.pay_invoice_mpp(invoice, CurrencyUnit::Sat).await?;
+
+        assert_eq!(2, results.len());
+        let (paid, failed): (Vec<_>, Vec<_>) = results.iter().partition(|part| part.result.is_ok());
+        assert_eq!(keyset_a.mint_url, paid[0].mint_url);
+        assert_eq!(16, paid[0].amount);
+        assert_eq!(keyset_b.mint_url, failed[0].mint_url);
+        assert_eq!(4, failed[0].amount);
+
+        // the inputs of the failed part are spendable again
+        let balances = wallet.get_balances().await?;
+        assert_eq!(
+            0,
+            balances.by_mint_and_unit(&keyset_a.mint_url, &CurrencyUnit::Sat)
+        );
+        assert_eq!(
+            12,
+            balances.by_mint_and_unit(&keyset_b.mint_url, &CurrencyUnit::Sat)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_tokens_exact_match_without_swap() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&fixture, &wallet_keyset))
+            .await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        // the mock has no expectation for post_swap, so a swap would fail the test
+        let wallet = WalletBuilder::default()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let token = wallet.send_tokens(&wallet_keyset, 24).await?;
+        assert_eq!(24, token.total_amount());
+        assert_eq!(2, token.proofs().len());
+        assert_eq!(36, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_payment_request() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&fixture, &wallet_keyset))
+            .await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::default()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let other_mint = PaymentRequest {
+            mints: vec![Url::parse("http://127.0.0.1:3339")?],
+            ..Default::default()
+        };
+        let result = wallet
+            .pay_payment_request(&wallet_keyset, &other_mint, Some(24))
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::MintNotAccepted(_))));
+
+        let usd = PaymentRequest {
+            currency_unit: Some(CurrencyUnit::Usd),
+            ..Default::default()
+        };
+        let result = wallet
+            .pay_payment_request(&wallet_keyset, &usd, Some(24))
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::PaymentRequest(_))));
+
+        let request = PaymentRequest {
+            payment_id: Some("4840f51e".to_owned()),
+            amount: Some(24),
+            currency_unit: Some(CurrencyUnit::Sat),
+            mints: vec![wallet_keyset.mint_url.clone()],
+            ..Default::default()
+        };
+        let payload = wallet
+            .pay_payment_request(&wallet_keyset, &request, None)
+            .await?;
+        assert_eq!(Some("4840f51e".to_owned()), payload.id);
+        assert_eq!(wallet_keyset.mint_url, payload.mint);
+        assert_eq!(24, payload.proofs.total_amount());
+        assert_eq!(36, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_balances_by_mint_and_unit() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let other_keyset = WalletKeyset::new(
+            &KeysetId::new("009a1f293253e41e")?,
+            &Url::parse("http://127.0.0.1:3339")?,
+            &CurrencyUnit::Sat,
+            0,
+            wallet_keyset.public_keys.clone(),
+            true,
+        );
+        let token_60: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let token_64: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&token_60, &wallet_keyset))
+            .await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&token_64, &other_keyset))
+            .await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        localstore.upsert_keyset(&mut tx, &other_keyset).await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::default()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        assert_eq!(124, wallet.get_balance().await?);
+        assert_eq!(
+            60,
+            wallet
+                .get_balance_by_mint_and_unit(&wallet_keyset.mint_url, &CurrencyUnit::Sat)
+                .await?
+        );
+
+        let balances = wallet.get_balances().await?;
+        assert_eq!(2, balances.mints.len());
+        assert_eq!(124, balances.total(&CurrencyUnit::Sat));
+        assert_eq!(
+            64,
+            balances.by_mint_and_unit(&other_keyset.mint_url, &CurrencyUnit::Sat)
+        );
+
+        let result = wallet.send_tokens(&wallet_keyset, 61).await;
+        assert!(matches!(result, Err(MokshaWalletError::NotEnoughTokens)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pay_invoice_can_not_melt() -> anyhow::Result<()> {
+        let fixture = read_fixture("token_64.cashu")?; // 60 tokens (4,8,16,32)
+        let tokens: TokenV3 = fixture.try_into()?;
+
+        let localstore = SqliteLocalStore::with_in_memory()
+            .await
+            .expect("Could not create localstore");
+
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&tokens, &wallet_keyset))
+            .await?;
+        assert_eq!(64, localstore.get_proofs(&mut tx).await?.total_amount());
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_not_paid.json")?;
+
+        let mut mock_client = create_mock();
+        mock_client
+            .expect_post_melt_bolt11()
+            .returning(move |_, _, _, _| Ok(melt_response.clone()));
+
+        let quote_response =
+            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
+        mock_client
+            .expect_post_melt_quote_bolt11()
+            .returning(move |_, _, _, _| Ok(quote_response.clone()));
+        let swap_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        mock_client
+            .expect_post_swap()
+            .returning(move |_, _, _| Ok(swap_response.clone()));
+
+        let wallet = WalletBuilder::default()
+            .with_client(mock_client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        // 21 sats
+        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+
+        let quote = wallet
+            .get_melt_quote_bolt11(&wallet_keyset.mint_url, invoice.clone(), CurrencyUnit::Sat)
+            .await?;
+
+        let wallet_keyset = create_test_wallet_keyset()?;
+
+        let result = wallet.pay_invoice(&wallet_keyset, &quote, invoice).await?;
+        assert!(!result.0.paid);
+        let mut tx = localstore.begin_tx().await?;
+        assert_eq!(64, localstore.get_proofs(&mut tx).await?.total_amount());
+        assert!(!result.0.paid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_swap() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let swap_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
+        let mut client = create_mock();
+        client
+            .expect_post_restore()
+            .times(1)
+            .returning(|_, _| Ok(PostRestoreResponse::default()));
+        client
+            .expect_post_check_state()
+            .times(1)
+            .returning(|_, ys| Ok(check_state_response(ys, SpentState::Unspent)));
+        client
+            .expect_post_swap()
+            .times(1)
+            .returning(move |_, _, _| Ok(swap_response.clone()));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        // crashed after the swap request was journaled
+        let outputs = wallet
+            .create_outputs(&wallet_keyset.keyset_id, vec![4, 16, 4, 8, 32].into())
+            .await?;
+        let operation = WalletOperation::new(
+            OperationKind::Swap,
+            &wallet_keyset,
+            None,
+            tokens.proofs(),
+            outputs,
+        );
+        let operation = wallet.begin_operation(operation).await?;
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(1, result.len());
+        assert_eq!(RecoveryResult::Replayed(64), result[0].1);
+
+        let mut tx = localstore.begin_tx().await?;
+        let proofs = localstore.get_proofs(&mut tx).await?;
+        assert!(localstore.get_operations(&mut tx).await?.is_empty());
+        tx.commit().await?;
+        assert_eq!(64, proofs.total_amount());
+        assert!(proofs
+            .proofs()
+            .iter()
+            .all(|p| operation.outputs.iter().any(|o| o.secret == p.secret)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_melt_not_paid() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let quote_response =
+            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
+        let mut client = create_mock();
+        client
+            .expect_get_melt_quote_bolt11()
+            .returning(move |_, _| Ok(quote_response.clone()));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        wallet
+            .begin_operation(WalletOperation::new(
+                OperationKind::MeltBolt11,
+                &wallet_keyset,
+                Some("quote".to_owned()),
+                tokens.proofs(),
+                vec![],
+            ))
+            .await?;
+        assert_eq!(0, wallet.get_balance().await?);
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::RolledBack(60), result[0].1);
+        assert_eq!(60, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_mint_unreachable() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        client.expect_post_mint_bolt11().returning(move |_, _, _| {
+            Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned()))
+        });
+        client.expect_post_restore().returning(move |_, _| {
+            Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned()))
+        });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let result = wallet
+            .mint_tokens(
+                &wallet_keyset,
+                &PaymentMethod::Bolt11,
+                20.into(),
+                "hash".to_string(),
+            )
+            .await;
+        assert!(result.is_err());
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::Pending, result[0].1);
+        assert_eq!(Some("hash".to_owned()), result[0].0.quote_id);
+
+        let mut tx = localstore.begin_tx().await?;
+        assert_eq!(1, localstore.get_operations(&mut tx).await?.len());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_swap_rejects_incomplete_response() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        // the mint signs only the first output
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .returning(|_, _, outputs| Ok(swap_response(outputs.into_iter().take(1).collect())));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let result = wallet.swap_tokens(&wallet_keyset, &tokens, 20.into()).await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::UnexpectedResponse(_))
+        ));
+
+        let mut tx = localstore.begin_tx().await?;
+        assert_eq!(1, localstore.get_operations(&mut tx).await?.len());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_swap_processed_by_mint() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        // the mint has signed the outputs before the wallet crashed, replaying the swap is not necessary
+        let mut client = create_mock();
+        client
+            .expect_post_restore()
+            .times(1)
+            .returning(|_, outputs| Ok(restore_response(outputs)));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let outputs = wallet
+            .create_outputs(&wallet_keyset.keyset_id, vec![4, 16, 4, 8, 32].into())
+            .await?;
+        wallet
+            .begin_operation(WalletOperation::new(
+                OperationKind::Swap,
+                &wallet_keyset,
+                None,
+                tokens.proofs(),
+                outputs,
+            ))
+            .await?;
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::Restored(64), result[0].1);
+        assert_eq!(64, wallet.get_balance().await?);
+
+        let mut tx = localstore.begin_tx().await?;
+        let proofs = localstore.get_proofs(&mut tx).await?;
+        assert!(localstore.get_operations(&mut tx).await?.is_empty());
+        tx.commit().await?;
+        assert!(proofs
+            .proofs()
+            .iter()
+            .all(|p| !tokens.proofs().proofs().contains(p)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_swap_keeps_operation_without_restore() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        // a mint without Nut-07 and Nut-09 rejects the replay, because it processed the first request
+        let mut client = create_mock();
+        client
+            .expect_post_restore()
+            .returning(|_, _| Err(MokshaWalletError::MintError("not found".to_owned())));
+        client
+            .expect_post_check_state()
+            .returning(|_, _| Err(MokshaWalletError::MintError("not found".to_owned())));
+        client.expect_post_swap().times(1).returning(|_, _, _| {
+            Err(MokshaWalletError::MintError(
+                "Proof already used".to_owned(),
+            ))
+        });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let outputs = wallet
+            .create_outputs(&wallet_keyset.keyset_id, vec![64].into())
+            .await?;
+        wallet
+            .begin_operation(WalletOperation::new(
+                OperationKind::Swap,
+                &wallet_keyset,
+                None,
+                tokens.proofs(),
+                outputs,
+            ))
+            .await?;
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::Pending, result[0].1);
+
+        let mut tx = localstore.begin_tx().await?;
+        let operations = localstore.get_operations(&mut tx).await?;
+        assert_eq!(1, operations.len());
+        assert_eq!(64, operations[0].inputs.total_amount());
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_mint_already_issued() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        client
+            .expect_post_restore()
+            .times(1)
+            .returning(|_, outputs| Ok(restore_response(outputs)));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let outputs = wallet
+            .create_outputs(&wallet_keyset.keyset_id, vec![4, 16].into())
+            .await?;
+        wallet
+            .begin_operation(WalletOperation::new(
+                OperationKind::MintBolt11,
+                &wallet_keyset,
+                Some("quote".to_owned()),
+                Proofs::empty(),
+                outputs,
+            ))
+            .await?;
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::Restored(20), result[0].1);
+        assert_eq!(20, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_paid_melt_restores_change() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let quote_response =
+            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
+        let mut client = create_mock();
+        client
+            .expect_get_melt_quote_bolt11()
+            .returning(move |_, _| {
+                Ok(PostMeltQuoteBolt11Response {
+                    paid: true,
+                    ..quote_response.clone()
+                })
+            });
+        // the mint returned 4 sats of the fee reserve as change for the first blank output
+        client
+            .expect_post_restore()
+            .times(1)
+            .returning(|_, outputs| {
+                let outputs = outputs
+                    .into_iter()
+                    .take(1)
+                    .map(|o| BlindedMessage { amount: 4, ..o })
+                    .collect();
+                Ok(restore_response(outputs))
+            });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let fee_outputs = wallet
+            .create_blank(8.into(), &wallet_keyset.keyset_id)
+            .await?;
+        wallet
+            .begin_operation(WalletOperation::new(
+                OperationKind::MeltBolt11,
+                &wallet_keyset,
+                Some("quote".to_owned()),
+                tokens.proofs(),
+                fee_outputs,
+            ))
+            .await?;
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(RecoveryResult::Spent(56), result[0].1);
+        assert_eq!(4, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_resumed_after_mint_failure() -> anyhow::Result<()> {
+        let source_keyset = create_test_wallet_keyset()?;
+        let destination_keyset = WalletKeyset {
+            keyset_id: KeysetId::new("00d31cecf59d18c1")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            ..source_keyset.clone()
+        };
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&fixture, &source_keyset))
+            .await?;
+        localstore.upsert_keyset(&mut tx, &source_keyset).await?;
+        localstore
+            .upsert_keyset(&mut tx, &destination_keyset)
+            .await?;
+        tx.commit().await?;
+
+        // 20 sats
+        let invoice = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40".to_string();
+        let mut client = create_mock();
+        client
+            .expect_post_mint_quote_bolt11()
+            .returning(move |_, _, _| {
+                Ok(PostMintQuoteBolt11Response {
+                    quote: "mint-quote".to_owned(),
+                    payment_request: invoice.clone(),
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        client
+            .expect_post_melt_quote_bolt11()
+            .returning(|_, _, _, _| {
+                Ok(PostMeltQuoteBolt11Response {
+                    quote: "melt-quote".to_owned(),
+                    amount: 20,
+                    fee_reserve: 0,
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?;
+        client
+            .expect_post_melt_bolt11()
+            .times(1)
+            .returning(move |_, _, _, _| Ok(melt_response.clone()));
+        let mint_response =
+            read_fixture_as::<PostMintBolt11Response>("post_mint_response_20.json")?;
+        let mut mint_calls = 0;
+        client
+            .expect_post_mint_bolt11()
+            .times(2)
+            .returning(move |_, quote, _| {
+                assert_eq!("mint-quote", quote);
+                mint_calls += 1;
+                match mint_calls {
+                    1 => Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned())),
+                    _ => Ok(mint_response.clone()),
+                }
+            });
+        // the mint has not signed the outputs of the interrupted mint request
+        client
+            .expect_post_restore()
+            .returning(|_, _| Ok(PostRestoreResponse::default()));
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+
+        let result = wallet
+            .transfer(&source_keyset.mint_url, &destination_keyset.mint_url, 20)
+            .await;
+        assert!(result.is_err());
+
+        let balances = wallet.get_balances().await?;
+        assert_eq!(
+            40,
+            balances.by_mint_and_unit(&source_keyset.mint_url, &CurrencyUnit::Sat)
+        );
+        assert_eq!(
+            0,
+            balances.by_mint_and_unit(&destination_keyset.mint_url, &CurrencyUnit::Sat)
+        );
+
+        let result = wallet.recover_pending_operations().await?;
+        assert_eq!(1, result.len());
+        assert_eq!(OperationKind::MintBolt11, result[0].0.kind);
+        assert_eq!(RecoveryResult::Replayed(20), result[0].1);
+        assert_eq!(
+            20,
+            wallet
+                .get_balance_by_mint_and_unit(&destination_keyset.mint_url, &CurrencyUnit::Sat)
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transfer_same_mint() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .build()
+            .await?;
+
+        let result = wallet
+            .transfer(&wallet_keyset.mint_url, &wallet_keyset.mint_url, 20)
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::TransferFailed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unlock_encrypted_seed() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .with_password("secret")
+            .build()
+            .await?;
+        assert!(wallet.is_seed_encrypted().await?);
+
+        let result = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .build()
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::WalletLocked)));
+
+        let result = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .with_password("wrong")
+            .build()
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidPassword)));
+
+        let unlocked = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .with_password("secret")
+            .build()
+            .await?;
+        assert_eq!(
+            wallet.secret.seed.as_bytes(),
+            unlocked.secret.seed.as_bytes()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_change_password() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .build()
+            .await?;
+        assert!(!wallet.is_seed_encrypted().await?);
+
+        wallet.change_password(None, Some("secret")).await?;
+        let result = wallet.change_password(Some("wrong"), Some("new")).await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidPassword)));
+        wallet.change_password(Some("secret"), Some("new")).await?;
+
+        let unlocked = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .with_password("new")
+            .build()
+            .await?;
+        assert_eq!(
+            wallet.secret.seed.as_bytes(),
+            unlocked.secret.seed.as_bytes()
+        );
+
+        wallet.change_password(Some("new"), None).await?;
+        assert!(!wallet.is_seed_encrypted().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://127.0.0.1:3338")?;
+        let mut client = create_mock();
+        let mut restore_calls = 0;
+        client
+            .expect_post_restore()
+            .times(4)
+            .returning(move |_, outputs| {
+                restore_calls += 1;
+                if restore_calls > 1 {
+                    return Ok(PostRestoreResponse::default());
+                }
+                // the mint signed the outputs with counter 0, 1 and 2
+                let outputs = outputs.into_iter().take(3).collect::<Vec<_>>();
+                let signatures = outputs
+                    .iter()
+                    .map(|o| BlindedSignature {
+                        amount: 1,
+                        c_: o.b_,
+                        id: o.id.clone(),
+                    })
+                    .collect();
+                Ok(PostRestoreResponse {
+                    outputs,
+                    signatures,
+                })
+            });
+        client
+            .expect_post_check_state()
+            .times(1)
+            .returning(|_, ys| {
+                let states = ys
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, y)| ProofStateResponse {
+                        y,
+                        state: if i == 0 {
+                            SpentState::Spent
+                        } else {
+                            SpentState::Unspent
+                        },
+                        witness: None,
+                    })
+                    .collect();
+                Ok(PostCheckStateResponse { states })
+            });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .with_seed_words(
+                "half depart obvious quality work element tank gorilla view sugar picture humble",
+            )
+            .build()
+            .await?;
+
+        assert_eq!(2, wallet.restore(&mint_url).await?);
+        assert_eq!(2, wallet.get_balance().await?);
+        let keysets = wallet.get_wallet_keysets().await?;
+        assert_eq!(2, keysets[0].last_index);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_with_reserved_proofs() -> anyhow::Result<()> {
+        let mint_url = Url::parse("http://127.0.0.1:3338")?;
+        let mut client = create_mock();
+        let mut restore_calls = 0;
+        client
+            .expect_post_restore()
+            .times(8)
+            .returning(move |_, outputs| {
+                restore_calls += 1;
+                // the mint signed the outputs with counter 0, 1 and 2
+                if restore_calls % 4 != 1 {
+                    return Ok(PostRestoreResponse::default());
+                }
+                let outputs = outputs.into_iter().take(3).collect::<Vec<_>>();
+                let signatures = outputs
+                    .iter()
+                    .map(|o| BlindedSignature {
+                        amount: 1,
+                        c_: o.b_,
+                        id: o.id.clone(),
+                    })
+                    .collect();
+                Ok(PostRestoreResponse {
+                    outputs,
+                    signatures,
+                })
+            });
+        client
+            .expect_post_check_state()
+            .times(2)
+            .returning(|_, ys| {
+                let states = ys
+                    .into_iter()
+                    .map(|y| ProofStateResponse {
+                        y,
+                        state: SpentState::Unspent,
+                        witness: None,
+                    })
+                    .collect();
+                Ok(PostCheckStateResponse { states })
+            });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .with_seed_words(
+                "half depart obvious quality work element tank gorilla view sugar picture humble",
+            )
+            .build()
+            .await?;
+        assert_eq!(3, wallet.restore(&mint_url).await?);
+
+        // one of the restored proofs is reserved by a send
+        let reserved: Proofs = wallet.get_proofs().await?.proofs()[..1].to_vec().into();
+        let mut tx = wallet.localstore.begin_tx().await?;
+        wallet
+            .localstore
+            .reserve_proofs(&mut tx, &reserved, "cashuAtoken")
+            .await?;
+        tx.commit().await?;
+
+        assert_eq!(0, wallet.restore(&mint_url).await?);
+        assert_eq!(2, wallet.get_balance().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_with_different_seed_words() -> anyhow::Result<()> {
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let seed_words =
+            "half depart obvious quality work element tank gorilla view sugar picture humble";
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore.clone())
+            .with_seed_words(seed_words)
+            .build()
+            .await?;
+        assert_eq!(seed_words, wallet.seed_words());
+
+        let result = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .with_seed_words(
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            )
+            .build()
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::SeedAlreadyExists)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_import_backup() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+        let proofs = proofs_of_keyset(&tokens, &wallet_keyset);
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore
+            .upsert_keyset(
+                &mut tx,
+                &WalletKeyset {
+                    last_index: 10,
+                    ..wallet_keyset.clone()
+                },
+            )
+            .await?;
+        localstore.add_proofs(&mut tx, &proofs).await?;
+        localstore
+            .add_transaction(
+                &mut tx,
+                &WalletTransaction::new(TransactionKind::Receive, &wallet_keyset, 60),
+            )
+            .await?;
+        // the 32 sat proof was sent, but not claimed yet
+        localstore
+            .reserve_proofs(&mut tx, &proofs.proofs()[3..].to_vec().into(), "cashuAsent")
+            .await?;
+        let quote = WalletMintQuote {
+            quote_id: "quote".to_owned(),
+            mint_url: wallet_keyset.mint_url.clone(),
+            payment_method: PaymentMethod::Bolt11,
+            currency_unit: CurrencyUnit::Sat,
+            amount: 20,
+            payment_request: "lnbcrt200n".to_owned(),
+            expiry: None,
+            state: MintQuoteState::Unpaid,
+        };
+        localstore.add_mint_quote(&mut tx, &quote).await?;
+        tx.commit().await?;
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        wallet.trust_mint(&wallet_keyset.mint_url, true).await?;
+        let backup = wallet.export_backup("secret").await?;
+
+        // the other wallet already contains the first proof
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        localstore
+            .add_proofs(&mut tx, &proofs.proofs()[..1].to_vec().into())
+            .await?;
+        tx.commit().await?;
+        let other_wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let result = other_wallet.import_backup(&backup, "wrong").await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidPassword)));
+
+        let result = other_wallet.import_backup(&backup, "secret").await?;
+        assert_eq!(
+            BackupImportResult {
+                keysets: 0,
+                proofs: 3,
+                amount: 56,
+                transactions: 1,
+                trusted_mints: 1,
+                mint_quotes: 1,
+            },
+            result
+        );
+        assert_eq!(28, other_wallet.get_balance().await?);
+        let mut tx = other_wallet.localstore.begin_tx().await?;
+        assert_eq!(
+            vec!["cashuAsent".to_owned()],
+            other_wallet.localstore.get_reserved_tokens(&mut tx).await?
+        );
+        assert_eq!(
+            vec![quote],
+            other_wallet
+                .localstore
+                .get_pending_mint_quotes(&mut tx)
+                .await?
+        );
+        tx.commit().await?;
+        assert_eq!(10, other_wallet.get_wallet_keysets().await?[0].last_index);
+        assert_eq!(
+            Some(wallet_keyset.mint_url.clone()),
+            other_wallet.get_preferred_mint().await?
+        );
+
+        let result = other_wallet.import_backup(&backup, "secret").await?;
+        assert_eq!(BackupImportResult::default(), result);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_pending_mint_quotes() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        let mut quote_calls = 0;
+        client
+            .expect_post_mint_quote_bolt11()
+            .times(3)
+            .returning(move |_, _, _| {
+                quote_calls += 1;
+                Ok(PostMintQuoteBolt11Response {
+                    quote: format!("quote-{quote_calls}"),
+                    payment_request: "lnbcrt200n".to_owned(),
+                    paid: false,
+                    // the second quote is expired
+                    expiry: Some(if quote_calls == 2 { 1 } else { u64::MAX }),
+                })
+            });
+        client.expect_get_mint_quote_bolt11().returning(|_, quote| {
+            Ok(PostMintQuoteBolt11Response {
+                paid: quote == "quote-1",
+                quote,
+                payment_request: "lnbcrt200n".to_owned(),
+                expiry: None,
+            })
+        });
+        let mint_response =
+            read_fixture_as::<PostMintBolt11Response>("post_mint_response_20.json")?;
+        client
+            .expect_post_mint_bolt11()
+            .times(1)
+            .returning(move |_, quote, _| {
+                assert_eq!("quote-1", quote);
+                Ok(mint_response.clone())
+            });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        for _ in 0..3 {
+            wallet
+                .create_quote_bolt11(&wallet_keyset.mint_url, 20)
+                .await?;
+        }
+
+        let results = wallet
+            .check_pending_mint_quotes()
+            .await?
+            .into_iter()
+            .map(|(quote, result)| (quote.quote_id, result))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("quote-1".to_owned(), MintQuoteResult::Minted(20)),
+                ("quote-2".to_owned(), MintQuoteResult::Expired),
+                ("quote-3".to_owned(), MintQuoteResult::Unpaid),
+            ],
+            results
+        );
+        assert_eq!(20, wallet.get_balance().await?);
+
+        let results = wallet.check_pending_mint_quotes().await?;
+        assert_eq!(1, results.len());
+        assert_eq!(MintQuoteResult::Unpaid, results[0].1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_pending_mint_quotes_rejected_or_unreachable() -> anyhow::Result<()> {
+        let wallet_keyset = create_test_wallet_keyset()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        let mut quote_calls = 0;
+        client
+            .expect_post_mint_quote_bolt11()
+            .times(3)
+            .returning(move |_, _, _| {
+                quote_calls += 1;
+                Ok(PostMintQuoteBolt11Response {
+                    quote: format!("quote-{quote_calls}"),
+                    payment_request: "lnbcrt200n".to_owned(),
+                    paid: false,
+                    // the second quote is expired
+                    expiry: Some(if quote_calls == 2 { 1 } else { u64::MAX }),
+                })
+            });
+        // the mint doesn't know the first quote and is not reachable for the others
+        client
+            .expect_get_mint_quote_bolt11()
+            .returning(|_, quote| match quote.as_str() {
+                "quote-1" => Err(MokshaWalletError::MintError("quote not found".to_owned())),
+                _ => Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned())),
+            });
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        for _ in 0..3 {
+            wallet
+                .create_quote_bolt11(&wallet_keyset.mint_url, 20)
+                .await?;
+        }
+
+        let results = wallet
+            .check_pending_mint_quotes()
+            .await?
+            .into_iter()
+            .map(|(quote, result)| (quote.quote_id, result))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    "quote-1".to_owned(),
+                    MintQuoteResult::Failed(
+                        MokshaWalletError::MintError("quote not found".to_owned()).to_string()
+                    )
+                ),
+                ("quote-2".to_owned(), MintQuoteResult::Expired),
+                ("quote-3".to_owned(), MintQuoteResult::Pending),
+            ],
+            results
+        );
+
+        let results = wallet.check_pending_mint_quotes().await?;
+        assert_eq!(1, results.len());
+        assert_eq!("quote-3", results[0].0.quote_id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_mints_swaps_inactive_keyset() -> anyhow::Result<()> {
+        let old_keyset = create_test_wallet_keyset()?;
+        let new_keys = MintKeyset::new("mykey", "");
+        let new_keys_response = KeysResponse::new(KeyResponse {
+            keys: new_keys.public_keys.clone(),
+            id: new_keys.keyset_id.clone(),
+            unit: CurrencyUnit::Sat,
+        });
+        let keysets = Keysets {
+            keysets: [
+                Keysets::new(old_keyset.keyset_id.to_string(), CurrencyUnit::Sat, false).keysets,
+                Keysets::new(new_keys.keyset_id.clone(), CurrencyUnit::Sat, true).keysets,
+            ]
+            .concat(),
+        };
 
         let mut client = MockCashuClient::default();
-        client
-            .expect_get_keys()
-            .returning(move |_| Ok(keys_response.clone()));
         client
             .expect_get_keysets()
             .returning(move |_| Ok(keysets.clone()));
+        let old_keys_response = KeysResponse::new(KeyResponse {
+            keys: old_keyset.public_keys.clone(),
+            id: old_keyset.keyset_id.to_string(),
+            unit: CurrencyUnit::Sat,
+        });
+        client.expect_get_keys_by_id().returning(move |_, id| {
+            if id == new_keys_response.keysets[0].id {
+                Ok(new_keys_response.clone())
+            } else {
+                Ok(old_keys_response.clone())
+            }
+        });
+        client.expect_is_v1_supported().returning(move |_| Ok(true));
         client
-            .expect_get_keys_by_id()
-            .returning(move |_, _| Ok(keys_by_id_response.clone()));
+            .expect_post_swap()
+            .times(1)
+            .returning(|_, _, outputs| {
+                let signatures = outputs
+                    .iter()
+                    .map(|o| BlindedSignature {
+                        amount: o.amount,
+                        c_: o.b_,
+                        id: o.id.clone(),
+                    })
+                    .collect();
+                Ok(PostSwapResponse { signatures })
+            });
+
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &old_keyset).await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&tokens, &old_keyset))
+            .await?;
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let results = wallet.refresh_mints().await?;
+        assert_eq!(1, results.len());
+        let result = results[0].1.as_ref().expect("refresh failed");
+        assert_eq!(1, result.new_keysets);
+        assert_eq!(1, result.deactivated_keysets);
+        assert_eq!(60, result.swapped_amount);
+        assert_eq!(60, wallet.get_balance().await?);
+        let proofs = wallet.get_proofs().await?;
+        assert!(proofs
+            .proofs()
+            .iter()
+            .all(|proof| proof.keyset_id == new_keys.keyset_id));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_mints_continues_after_unreachable_mint() -> anyhow::Result<()> {
+        let keys = MintKeyset::new("mykey", "");
+        let reachable_url = Url::parse("http://127.0.0.1:3338")?;
+        let unreachable_url = Url::parse("http://127.0.0.1:3339")?;
+        let keys_response = KeysResponse::new(KeyResponse {
+            keys: keys.public_keys.clone(),
+            id: keys.keyset_id.clone(),
+            unit: CurrencyUnit::Sat,
+        });
+        let keysets = Keysets::new(keys.keyset_id.clone(), CurrencyUnit::Sat, true);
+
+        let mut client = MockCashuClient::default();
         client.expect_is_v1_supported().returning(move |_| Ok(true));
+        client.expect_get_keysets().returning(move |mint_url| {
+            if mint_url.port() == Some(3339) {
+                Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned()))
+            } else {
+                Ok(keysets.clone())
+            }
+        });
         client
+            .expect_get_keys_by_id()
+            .returning(move |_, _| Ok(keys_response.clone()));
+
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        for mint_url in [&reachable_url, &unreachable_url] {
+            localstore
+                .upsert_keyset(
+                    &mut tx,
+                    &WalletKeyset::new(
+                        &KeysetId::new(&keys.keyset_id)?,
+                        mint_url,
+                        &CurrencyUnit::Sat,
+                        0,
+                        keys.public_keys.clone(),
+                        true,
+                    ),
+                )
+                .await?;
+        }
+        tx.commit().await?;
+
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let results = wallet.refresh_mints().await?;
+        assert_eq!(2, results.len());
+        for (mint_url, result) in results {
+            if mint_url == unreachable_url {
+                assert!(matches!(
+                    result,
+                    Err(MokshaWalletError::UnexpectedResponse(_))
+                ));
+            } else {
+                assert_eq!(
+                    MintRefreshResult {
+                        new_keysets: 0,
+                        deactivated_keysets: 0,
+                        swapped_amount: 0,
+                    },
+                    result?
+                );
+            }
+        }
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_blank_blinded_messages_1000_sats() -> anyhow::Result<()> {
+    async fn test_consolidate() -> anyhow::Result<()> {
+        let keyset = create_test_wallet_keyset()?;
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
+        let proof = proofs_of_keyset(&tokens, &keyset).proofs()[0].clone();
+        let proofs: Proofs = (0..20)
+            .map(|i| Proof {
+                amount: 1,
+                secret: format!("{}{i}", proof.secret),
+                ..proof.clone()
+            })
+            .collect::<Vec<_>>()
+            .into();
+
         let localstore = SqliteLocalStore::with_in_memory().await?;
-        let wallet_keyset = create_test_wallet_keyset()?;
         let mut tx = localstore.begin_tx().await?;
-        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        localstore.add_proofs(&mut tx, &proofs).await?;
+        tx.commit().await?;
+
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .times(1)
+            .returning(|_, _, outputs| {
+                let signatures = outputs
+                    .iter()
+                    .map(|o| BlindedSignature {
+                        amount: o.amount,
+                        c_: o.b_,
+                        id: o.id.clone(),
+                    })
+                    .collect();
+                Ok(PostSwapResponse { signatures })
+            });
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .build()
+            .await?;
+
+        let result = wallet
+            .consolidate(&keyset.mint_url, &CurrencyUnit::Sat, 1)
+            .await?;
+        assert_eq!(20, result.proofs_before);
+        // 1, 2, 4, 8 and the remaining 5 as 1 and 4
+        assert_eq!(6, result.proofs_after);
+        assert_eq!(20, wallet.get_balance().await?);
+        assert_eq!(6, wallet.get_proofs().await?.len());
+
+        // consolidating again doesn't lower the number of proofs
+        let result = wallet
+            .consolidate(&keyset.mint_url, &CurrencyUnit::Sat, 1)
+            .await?;
+        assert_eq!(6, result.proofs_after);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_offline_and_sync() -> anyhow::Result<()> {
+        let mint_key = SecretKey::from_slice(&[1; 32])?;
+        let keyset = create_offline_test_keyset(&mint_key)?;
+        // the wallet has no active keyset at the other mint
+        let inactive_keyset = WalletKeyset {
+            keyset_id: KeysetId::new("00ffd48b8f5ecf80")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            active: false,
+            ..keyset.clone()
+        };
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
+        localstore.upsert_keyset(&mut tx, &inactive_keyset).await?;
+        tx.commit().await?;
+
+        let seed_words = DeterministicSecret::generate_random_seed_words()?;
+        let pubkey = DeterministicSecret::from_seed_words(&seed_words)?
+            .derive_p2pk_key()?
+            .public_key(&Secp256k1::new());
+        let proofs = vec![
+            create_offline_proof(&keyset, &mint_key, 1, &pubkey)?,
+            create_offline_proof(&keyset, &mint_key, 2, &pubkey)?,
+            create_offline_proof(&keyset, &mint_key, 4, &pubkey)?,
+        ];
+        let spent_y = proofs[1].y()?;
+
+        let mut client = create_mock();
+        // the proof of 2 sat was spent by the sender, the proof of 4 sat is rejected without being spent
+        client.expect_post_swap().returning(|_, inputs, outputs| {
+            for input in inputs.proofs() {
+                let witness = input.witness.clone().expect("witness is missing").parse()?;
+                let secret = P2PKSecret::from_str(&input.secret)?;
+                assert!(secret.verify_witness(&input.secret, &witness));
+            }
+            if inputs.proofs().iter().any(|proof| proof.amount != 1) {
+                return Err(MokshaWalletError::MintError(
+                    "Proof already used secret".to_owned(),
+                ));
+            }
+            let signatures = outputs
+                .iter()
+                .map(|o| BlindedSignature {
+                    amount: o.amount,
+                    c_: o.b_,
+                    id: o.id.clone(),
+                })
+                .collect();
+            Ok(PostSwapResponse { signatures })
+        });
+        client
+            .expect_post_check_state()
+            .times(1)
+            .returning(move |_, ys| {
+                Ok(PostCheckStateResponse {
+                    states: ys
+                        .into_iter()
+                        .map(|y| ProofStateResponse {
+                            y,
+                            state: if y == spent_y {
+                                SpentState::Spent
+                            } else {
+                                SpentState::Unspent
+                            },
+                            witness: None,
+                        })
+                        .collect(),
+                })
+            });
+        let wallet = WalletBuilder::new()
+            .with_client(client)
+            .with_localstore(localstore)
+            .with_seed_words(seed_words)
+            .build()
+            .await?;
+
+        let tokens: TokenV3 = (keyset.mint_url.clone(), CurrencyUnit::Sat, proofs.into()).into();
+        assert_eq!(7, wallet.receive_offline(&tokens).await?);
+        let tokens: TokenV3 = (
+            inactive_keyset.mint_url.clone(),
+            CurrencyUnit::Sat,
+            create_offline_proof(&inactive_keyset, &mint_key, 8, &pubkey)?.into(),
+        )
+            .into();
+        assert_eq!(8, wallet.receive_offline(&tokens).await?);
+        assert_eq!(0, wallet.get_balance().await?);
+        assert_eq!(15, wallet.get_unverified_proofs().await?.total_amount());
+
+        let results = wallet.sync_offline_proofs().await?;
+        assert_eq!(2, results.len());
+        assert_eq!(keyset.mint_url, results[0].mint_url);
+        assert_eq!(1, results[0].amount);
+        assert_eq!(2, results[0].double_spent.total_amount());
+        assert_eq!(4, results[0].unverified.total_amount());
+        assert_eq!(inactive_keyset.mint_url, results[1].mint_url);
+        assert_eq!(0, results[1].amount);
+        assert_eq!(8, results[1].unverified.total_amount());
+        assert_eq!(1, wallet.get_balance().await?);
+        // the rejected and the skipped proofs are retried by the next sync
+        assert_eq!(12, wallet.get_unverified_proofs().await?.total_amount());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_offline_invalid_tokens() -> anyhow::Result<()> {
+        let mint_key = SecretKey::from_slice(&[1; 32])?;
+        let keyset = create_offline_test_keyset(&mint_key)?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.upsert_keyset(&mut tx, &keyset).await?;
         tx.commit().await?;
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(localstore)
+            .build()
+            .await?;
+        let pubkey = wallet.p2pk_pubkey()?;
+        let into_tokens = |proof: Proof| -> TokenV3 {
+            (keyset.mint_url.clone(), CurrencyUnit::Sat, proof.into()).into()
+        };
+
+        let other_pubkey = SecretKey::from_slice(&[2; 32])?.public_key(&Secp256k1::new());
+        let result = wallet
+            .receive_offline(&into_tokens(create_offline_proof(
+                &keyset,
+                &mint_key,
+                1,
+                &other_pubkey,
+            )?))
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::NotLockedToWallet)));
+
+        // the sender or the owner of another key could spend the proofs
+        for secret in [
+            P2PKSecret {
+                tags: vec![vec!["pubkeys".to_owned(), pubkey.to_string()]],
+                ..P2PKSecret::new(other_pubkey)
+            },
+            P2PKSecret {
+                tags: vec![vec!["pubkeys".to_owned(), other_pubkey.to_string()]],
+                ..P2PKSecret::new(pubkey)
+            },
+            P2PKSecret {
+                tags: vec![vec!["refund".to_owned(), other_pubkey.to_string()]],
+                ..P2PKSecret::new(pubkey)
+            },
+        ] {
+            let result = wallet
+                .receive_offline(&into_tokens(create_locked_proof(
+                    &keyset, &mint_key, 1, &secret,
+                )?))
+                .await;
+            assert!(matches!(result, Err(MokshaWalletError::NotLockedToWallet)));
+        }
+
+        let proof = create_offline_proof(&keyset, &mint_key, 1, &pubkey)?;
+        let result = wallet
+            .receive_offline(&into_tokens(Proof {
+                c: create_offline_proof(&keyset, &mint_key, 1, &pubkey)?.c,
+                ..proof.clone()
+            }))
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::InvalidDleq)));
+
+        let result = wallet
+            .receive_offline(&into_tokens(Proof {
+                keyset_id: "00ffd48b8f5ecf80".to_owned(),
+                ..proof.clone()
+            }))
+            .await;
+        assert!(matches!(result, Err(MokshaWalletError::UnknownKeyset(_))));
+
+        wallet.receive_offline(&into_tokens(proof.clone())).await?;
+        let result = wallet.receive_offline(&into_tokens(proof)).await;
+        assert!(matches!(
+            result,
+            Err(MokshaWalletError::ProofAlreadyReceived)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trusted_mints() -> anyhow::Result<()> {
+        let wallet = WalletBuilder::new()
+            .with_client(create_mock())
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
+            .build()
+            .await?;
+        let mint_a = Url::parse("http://127.0.0.1:3338")?;
+        let mint_b = Url::parse("http://127.0.0.1:3339")?;
+
+        // every mint is trusted without a policy
+        assert!(wallet.is_trusted_mint(&mint_b).await?);
+        assert_eq!(None, wallet.get_preferred_mint().await?);
+
+        wallet.trust_mint(&mint_a, true).await?;
+        assert!(wallet.is_trusted_mint(&mint_a).await?);
+        assert!(!wallet.is_trusted_mint(&mint_b).await?);
+        assert_eq!(Some(mint_a.clone()), wallet.get_preferred_mint().await?);
 
-        let client = create_mock();
+        wallet.trust_mint(&mint_b, true).await?;
+        assert_eq!(Some(mint_b.clone()), wallet.get_preferred_mint().await?);
+
+        wallet.untrust_mint(&mint_b).await?;
+        assert!(!wallet.is_trusted_mint(&mint_b).await?);
+        assert_eq!(None, wallet.get_preferred_mint().await?);
+        Ok(())
+    }
 
+    #[tokio::test]
+    async fn test_receive_into_preferred_mint_without_preferred_mint() -> anyhow::Result<()> {
+        let tokens: TokenV3 = read_fixture("token_60.cashu")?.try_into()?;
         let wallet = WalletBuilder::new()
-            .with_client(client)
-            .with_localstore(localstore)
+            .with_client(create_mock())
+            .with_localstore(SqliteLocalStore::with_in_memory().await?)
             .build()
             .await?;
-        let result = wallet
-            .create_blank(1000.into(), &KeysetId::new("00d31cecf59d18c0")?)
-            .await;
-        println!("{:?}", result);
-        assert!(result.is_ok());
-        let result = result.unwrap();
-        assert!(result.len() == 10);
-        assert!(result.first().unwrap().0.amount == 1);
 
+        let result = wallet.receive_into_preferred_mint(&tokens).await;
+        assert!(matches!(result, Err(MokshaWalletError::NoPreferredMint)));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_blank_blinded_messages_zero_sats() -> anyhow::Result<()> {
+    async fn test_receive_into_preferred_mint_sweep_failed() -> anyhow::Result<()> {
+        let token_keyset = create_test_wallet_keyset()?;
+        let preferred_keyset = WalletKeyset {
+            keyset_id: KeysetId::new("00d31cecf59d18c1")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            ..token_keyset.clone()
+        };
+        let tokens: TokenV3 = read_fixture("token_64.cashu")?.try_into()?;
+
         let localstore = SqliteLocalStore::with_in_memory().await?;
-        let wallet_keyset = create_test_wallet_keyset()?;
         let mut tx = localstore.begin_tx().await?;
-        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        localstore.upsert_keyset(&mut tx, &token_keyset).await?;
+        localstore.upsert_keyset(&mut tx, &preferred_keyset).await?;
         tx.commit().await?;
 
-        let client = create_mock();
+        let mut client = create_mock();
+        client
+            .expect_post_swap()
+            .times(1)
+            .returning(|_, _, outputs| Ok(swap_response(outputs)));
+        client
+            .expect_post_mint_quote_bolt11()
+            .returning(|_, _, _| Err(MokshaWalletError::MintError("offline".to_owned())));
 
         let wallet = WalletBuilder::new()
             .with_client(client)
             .with_localstore(localstore)
             .build()
             .await?;
-        let result = wallet
-            .create_blank(0.into(), &KeysetId::new("00d31cecf59d18c0")?)
-            .await;
-        println!("{:?}", result);
-        assert!(result.is_ok());
-        assert!(result.unwrap().is_empty());
+        wallet.trust_mint(&preferred_keyset.mint_url, true).await?;
 
+        let result = wallet.receive_into_preferred_mint(&tokens).await;
+        assert!(
+            matches!(result, Err(MokshaWalletError::SweepFailed(mint_url, 64, _)) if mint_url == token_keyset.mint_url)
+        );
+        // the tokens are kept at the untrusted mint
+        assert_eq!(
+            64,
+            wallet
+                .get_balance_by_mint_and_unit(&token_keyset.mint_url, &CurrencyUnit::Sat)
+                .await?
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_blank_blinded_messages_serialize() -> anyhow::Result<()> {
+    async fn test_sweep_mint_lowers_amount_by_fee_reserve() -> anyhow::Result<()> {
+        let source_keyset = create_test_wallet_keyset()?;
+        let destination_keyset = WalletKeyset {
+            keyset_id: KeysetId::new("00d31cecf59d18c1")?,
+            mint_url: Url::parse("http://127.0.0.1:3339")?,
+            ..source_keyset.clone()
+        };
+        let fixture: TokenV3 = read_fixture("token_60.cashu")?.try_into()?; // 60 tokens (4,8,16,32)
+
         let localstore = SqliteLocalStore::with_in_memory().await?;
-        let wallet_keyset = create_test_wallet_keyset()?;
         let mut tx = localstore.begin_tx().await?;
-        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        localstore
+            .add_proofs(&mut tx, &proofs_of_keyset(&fixture, &source_keyset))
+            .await?;
+        localstore.upsert_keyset(&mut tx, &source_keyset).await?;
+        localstore
+            .upsert_keyset(&mut tx, &destination_keyset)
+            .await?;
         tx.commit().await?;
 
-        let client = create_mock();
+        // 20 sats
+        let invoice = "lnbc200n1pj9eanxsp5agdl4rd0twdljpcgmg67dwj9mseu5m4lwfhslkws4uh4m5f5pcrqpp5lvspx676rykr64l02s97wjztcxe355qck0naydrsvvkqw42cc35sdq2f38xy6t5wvxqzjccqpjrzjq027t9tsc6jn5ve2k6gnn689unn8h239juuf9s3ce09aty6ed73t5z7nqsqqsygqqyqqqqqqqqqqqqgq9q9qyysgqs5msn4j9v53fq000zhw0gulkcx2dlnfdt953v2ur7z765jj3m0fx6cppkpjwntq5nsqm273u4eevva508pvepg8mh27sqcd29sfjr4cq255a40".to_string();
+        let quoted_amounts = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let mut client = create_mock();
+        let amounts = quoted_amounts.clone();
+        client
+            .expect_post_mint_quote_bolt11()
+            .times(2)
+            .returning(move |_, amount, _| {
+                amounts.lock().expect("lock failed").push(amount);
+                Ok(PostMintQuoteBolt11Response {
+                    quote: format!("mint-quote-{amount}"),
+                    payment_request: invoice.clone(),
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        let amounts = quoted_amounts.clone();
+        client
+            .expect_post_melt_quote_bolt11()
+            .times(2)
+            .returning(move |_, _, _, _| {
+                let amount = *amounts
+                    .lock()
+                    .expect("lock failed")
+                    .last()
+                    .expect("no quote");
+                Ok(PostMeltQuoteBolt11Response {
+                    quote: "melt-quote".to_owned(),
+                    amount,
+                    fee_reserve: 2,
+                    paid: false,
+                    expiry: None,
+                })
+            });
+        let melt_response =
+            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?;
+        client
+            .expect_post_melt_bolt11()
+            .times(1)
+            .returning(move |_, proofs, _, _| {
+                assert_eq!(60, proofs.total_amount());
+                Ok(melt_response.clone())
+            });
+        let mint_response =
+            read_fixture_as::<PostMintBolt11Response>("post_mint_response_20.json")?;
+        client
+            .expect_post_mint_bolt11()
+            .times(1)
+            .returning(move |_, quote, _| {
+                assert_eq!("mint-quote-58", quote);
+                Ok(mint_response.clone())
+            });
 
         let wallet = WalletBuilder::new()
             .with_client(client)
@@ -964,212 +5275,238 @@ mod tests {
             .await?;
 
         let result = wallet
-            .create_blank(4000.into(), &KeysetId::new("00d31cecf59d18c0")?)
+            .sweep_mint(&source_keyset.mint_url, &destination_keyset.mint_url, 60)
             .await?;
-        for (blinded_message, _, _) in result {
-            let out = serde_json::to_string(&blinded_message)?;
-            assert!(!out.is_empty());
-        }
+        assert_eq!(vec![60, 58], *quoted_amounts.lock().expect("lock failed"));
+        assert_eq!(2, result.fee);
+        assert_eq!(
+            0,
+            wallet
+                .get_balance_by_mint_and_unit(&source_keyset.mint_url, &CurrencyUnit::Sat)
+                .await?
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_mint_tokens() -> anyhow::Result<()> {
-        let mint_response =
-            read_fixture_as::<PostMintBolt11Response>("post_mint_response_20.json")?;
-
-        let mut client = create_mock();
-        client
-            .expect_post_mint_bolt11()
-            .returning(move |_, _, _| Ok(mint_response.clone()));
-
+    async fn test_reclaim_unclaimed_tokens() -> anyhow::Result<()> {
+        let token = read_fixture("token_64.cashu")?;
+        let tokens: TokenV3 = token.clone().try_into()?;
         let localstore = SqliteLocalStore::with_in_memory().await?;
         let wallet_keyset = create_test_wallet_keyset()?;
         let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
+        localstore
+            .reserve_proofs(&mut tx, &tokens.proofs(), token.trim())
+            .await?;
         localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
         tx.commit().await?;
 
-        let wallet = WalletBuilder::new()
-            .with_client(client)
-            .with_localstore(localstore)
-            .build()
-            .await?;
-
-        let result = wallet
-            .mint_tokens(
-                &wallet_keyset,
-                &PaymentMethod::Bolt11,
-                20.into(),
-                "hash".to_string(),
-            )
-            .await?;
-        assert_eq!(20, result.total_amount());
-        result.tokens.into_iter().for_each(|t| {
-            assert_eq!(wallet_keyset.mint_url, t.mint.expect("mint is empty"));
-        });
-        Ok(())
-    }
-
-    #[tokio::test]
-    async fn test_swap() -> anyhow::Result<()> {
-        let split_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
         let mut client = create_mock();
+        client
+            .expect_post_check_state()
+            .times(1)
+            .returning(|_, ys| Ok(check_state_response(ys, SpentState::Unspent)));
         client
             .expect_post_swap()
-            .returning(move |_, _, _| Ok(split_response.clone()));
-        let localstore = SqliteLocalStore::with_in_memory().await?;
-        let keyset = create_test_wallet_keyset()?;
-        let mut tx = localstore.begin_tx().await?;
-        localstore.upsert_keyset(&mut tx, &keyset).await?;
-        tx.commit().await?;
+            .times(1)
+            .returning(|_, _, outputs| Ok(swap_response(outputs)));
 
         let wallet = WalletBuilder::new()
             .with_client(client)
-            .with_localstore(localstore)
+            .with_localstore(localstore.clone())
             .build()
             .await?;
+        assert_eq!(0, wallet.get_balance().await?);
 
-        let tokens = read_fixture("token_64.cashu")?.try_into()?;
-        let result = wallet.swap_tokens(&keyset, &tokens, 20.into()).await?;
-
-        let first = result.0;
-
-        assert_eq!(CurrencyUnit::Sat, first.clone().currency_unit.unwrap());
-        assert_eq!(24, first.total_amount());
-
-        let second = result.1;
-
-        assert_eq!(CurrencyUnit::Sat, second.clone().currency_unit.unwrap());
-        assert_eq!(40, second.total_amount());
-        Ok(())
-    }
-
-    #[tokio::test]
-    async fn test_get_balance() -> anyhow::Result<()> {
-        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
-        let fixture: TokenV3 = fixture.try_into()?;
-        let local_store = SqliteLocalStore::with_in_memory().await?;
-        let mut tx = local_store.begin_tx().await?;
-        local_store.add_proofs(&mut tx, &fixture.proofs()).await?;
+        let result = wallet.reclaim_unclaimed_tokens().await?;
+        assert_eq!(64, result.reclaimed);
+        assert_eq!(0, result.claimed);
+        assert!(result.errors.is_empty());
+        let mut tx = localstore.begin_tx().await?;
+        assert!(localstore.get_reserved_tokens(&mut tx).await?.is_empty());
         tx.commit().await?;
-
-        let wallet = WalletBuilder::new()
-            .with_client(create_mock())
-            .with_localstore(local_store)
-            .build()
-            .await?;
-
-        let result = wallet.get_balance().await?;
-        assert_eq!(60, result);
         Ok(())
     }
-    // FIXME
 
     #[tokio::test]
-    async fn test_pay_invoice() -> anyhow::Result<()> {
-        let fixture = read_fixture("token_60.cashu")?; // 60 tokens (4,8,16,32)
-
-        let local_store = SqliteLocalStore::with_in_memory().await?;
-        let fixture: TokenV3 = fixture.try_into()?;
-        let mut tx = local_store.begin_tx().await?;
-        local_store.add_proofs(&mut tx, &fixture.proofs()).await?;
+    async fn test_reclaim_continues_after_failed_token() -> anyhow::Result<()> {
+        let token_60 = read_fixture("token_60.cashu")?;
+        let token_64 = read_fixture("token_64.cashu")?;
+        let tokens_60: TokenV3 = token_60.clone().try_into()?;
+        let tokens_64: TokenV3 = token_64.clone().try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
         let wallet_keyset = create_test_wallet_keyset()?;
-        local_store.upsert_keyset(&mut tx, &wallet_keyset).await?;
+        let mut tx = localstore.begin_tx().await?;
+        localstore.add_proofs(&mut tx, &tokens_60.proofs()).await?;
+        localstore.add_proofs(&mut tx, &tokens_64.proofs()).await?;
+        localstore
+            .reserve_proofs(&mut tx, &tokens_60.proofs(), token_60.trim())
+            .await?;
+        localstore
+            .reserve_proofs(&mut tx, &tokens_64.proofs(), token_64.trim())
+            .await?;
+        localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
         tx.commit().await?;
 
-        let melt_response =
-            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_21.json")?; // 60 tokens (4,8,16,32)
-        let mut mock_client = create_mock();
-        mock_client
-            .expect_post_melt_bolt11()
-            .returning(move |_, _, _, _| Ok(melt_response.clone()));
-
-        let quote_response =
-            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
-        mock_client
-            .expect_post_melt_quote_bolt11()
-            .returning(move |_, _, _| Ok(quote_response.clone()));
-
-        let swap_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
-        mock_client
+        let mut client = create_mock();
+        client
+            .expect_post_check_state()
+            .times(2)
+            .returning(|_, ys| Ok(check_state_response(ys, SpentState::Unspent)));
+        // the swap of the first token fails, the second one is reclaimed anyway
+        let mut swap_calls = 0;
+        client
             .expect_post_swap()
-            .returning(move |_, _, _| Ok(swap_response.clone()));
+            .times(2)
+            .returning(move |_, _, outputs| {
+                swap_calls += 1;
+                match swap_calls {
+                    1 => Err(MokshaWalletError::UnexpectedResponse("timeout".to_owned())),
+                    _ => Ok(swap_response(outputs)),
+                }
+            });
 
-        let mint_url = Url::parse("http://localhost:8080/").expect("invalid url");
         let wallet = WalletBuilder::new()
-            .with_client(mock_client)
-            .with_localstore(local_store)
+            .with_client(client)
+            .with_localstore(localstore.clone())
             .build()
             .await?;
 
-        // FIXME fix mock response: Warning Error in swap: input 32 != output 24 + 40
-
-        // 21 sats
-        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
-
-        let quote = wallet
-            .get_melt_quote_bolt11(&mint_url, invoice.clone(), CurrencyUnit::Sat)
-            .await?;
-        let result = wallet.pay_invoice(&wallet_keyset, &quote, invoice).await?;
-        assert!(result.0.paid);
+        let result = wallet.reclaim_unclaimed_tokens().await?;
+        assert_eq!(1, result.errors.len());
+        assert!(result.reclaimed > 0);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_pay_invoice_can_not_melt() -> anyhow::Result<()> {
-        let fixture = read_fixture("token_64.cashu")?; // 60 tokens (4,8,16,32)
-        let tokens: TokenV3 = fixture.try_into()?;
-
-        let localstore = SqliteLocalStore::with_in_memory()
-            .await
-            .expect("Could not create localstore");
-
+    async fn test_reclaim_claimed_tokens() -> anyhow::Result<()> {
+        let token = read_fixture("token_60.cashu")?;
+        let tokens: TokenV3 = token.clone().try_into()?;
+        let localstore = SqliteLocalStore::with_in_memory().await?;
+        let wallet_keyset = create_test_wallet_keyset()?;
         let mut tx = localstore.begin_tx().await?;
         localstore.add_proofs(&mut tx, &tokens.proofs()).await?;
-        assert_eq!(64, localstore.get_proofs(&mut tx).await?.total_amount());
-        let wallet_keyset = create_test_wallet_keyset()?;
+        localstore
+            .reserve_proofs(&mut tx, &tokens.proofs(), token.trim())
+            .await?;
         localstore.upsert_keyset(&mut tx, &wallet_keyset).await?;
         tx.commit().await?;
 
-        let melt_response =
-            read_fixture_as::<PostMeltBolt11Response>("post_melt_response_not_paid.json")?;
-
-        let mut mock_client = create_mock();
-        mock_client
-            .expect_post_melt_bolt11()
-            .returning(move |_, _, _, _| Ok(melt_response.clone()));
-
-        let quote_response =
-            read_fixture_as::<PostMeltQuoteBolt11Response>("post_melt_quote_response.json")?;
-        mock_client
-            .expect_post_melt_quote_bolt11()
-            .returning(move |_, _, _| Ok(quote_response.clone()));
-        let swap_response = read_fixture_as::<PostSwapResponse>("post_swap_response_24_40.json")?;
-        mock_client
-            .expect_post_swap()
-            .returning(move |_, _, _| Ok(swap_response.clone()));
+        let mut client = create_mock();
+        client
+            .expect_post_check_state()
+            .times(1)
+            .returning(|_, ys| Ok(check_state_response(ys, SpentState::Spent)));
+        client.expect_post_swap().never();
 
-        let wallet = WalletBuilder::default()
-            .with_client(mock_client)
+        let wallet = WalletBuilder::new()
+            .with_client(client)
             .with_localstore(localstore.clone())
             .build()
             .await?;
 
-        // 21 sats
-        let invoice = "lnbcrt210n1pjg6mqhpp5pza5wzh0csjjuvfpjpv4zdjmg30vedj9ycv5tyfes9x7dp8axy0sdqqcqzzsxqyz5vqsp5vtxg4c5tw2s2zxxya2a7an0psn9mcfmlqctxzntm3sngnpyk3muq9qyyssqf8z5f90yu3wrmsufnnza25qjlnvc6ukdr094ckzn63ktcy6z5fw5mxf9skndpg2p4648gfjfvvx4qg2lqvlryyycg5k7x9h4dw70t4qq37pegm".to_string();
+        let result = wallet.reclaim_unclaimed_tokens().await?;
+        assert_eq!(0, result.reclaimed);
+        assert_eq!(60, result.claimed);
+        assert_eq!(0, wallet.get_balance().await?);
+        let mut tx = localstore.begin_tx().await?;
+        assert!(localstore.get_reserved_tokens(&mut tx).await?.is_empty());
+        assert!(localstore.get_operations(&mut tx).await?.is_empty());
+        tx.commit().await?;
+        Ok(())
+    }
 
-        let quote = wallet
-            .get_melt_quote_bolt11(&wallet_keyset.mint_url, invoice.clone(), CurrencyUnit::Sat)
-            .await?;
+    /// Moves the proofs of the token to the given keyset, because the fixtures use legacy keyset ids
+    fn swap_response(outputs: Vec<BlindedMessage>) -> PostSwapResponse {
+        PostSwapResponse {
+            signatures: restore_response(outputs).signatures,
+        }
+    }
 
-        let wallet_keyset = create_test_wallet_keyset()?;
+    /// Response of a mint that has signed all outputs
+    fn restore_response(outputs: Vec<BlindedMessage>) -> PostRestoreResponse {
+        let signatures = outputs
+            .iter()
+            .map(|o| BlindedSignature {
+                amount: o.amount,
+                c_: o.b_,
+                id: o.id.clone(),
+            })
+            .collect();
+        PostRestoreResponse {
+            outputs,
+            signatures,
+        }
+    }
 
-        let result = wallet.pay_invoice(&wallet_keyset, &quote, invoice).await?;
-        assert!(!result.0.paid);
-        let mut tx = localstore.begin_tx().await?;
-        assert_eq!(64, localstore.get_proofs(&mut tx).await?.total_amount());
-        assert!(!result.0.paid);
-        Ok(())
+    fn check_state_response(ys: Vec<PublicKey>, state: SpentState) -> PostCheckStateResponse {
+        PostCheckStateResponse {
+            states: ys
+                .into_iter()
+                .map(|y| ProofStateResponse {
+                    y,
+                    state: state.clone(),
+                    witness: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn proofs_of_keyset(token: &TokenV3, keyset: &WalletKeyset) -> Proofs {
+        token
+            .proofs()
+            .proofs()
+            .into_iter()
+            .map(|proof| Proof {
+                keyset_id: keyset.keyset_id.to_string(),
+                ..proof
+            })
+            .collect::<Vec<Proof>>()
+            .into()
+    }
+
+    /// Keyset that uses the same key for every amount, so the tests can sign proofs
+    fn create_offline_test_keyset(mint_key: &SecretKey) -> anyhow::Result<WalletKeyset> {
+        let public_key = mint_key.public_key(&Secp256k1::new());
+        let public_keys = (0..8).map(|i| (2_u64.pow(i), public_key)).collect();
+        Ok(WalletKeyset::new(
+            &KeysetId::new("00d31cecf59d18c0")?,
+            &Url::parse("http://127.0.0.1:3338")?,
+            &CurrencyUnit::Sat,
+            0,
+            public_keys,
+            true,
+        ))
+    }
+
+    /// Creates a proof with a DLEQ proof that is locked to `pubkey`
+    fn create_offline_proof(
+        keyset: &WalletKeyset,
+        mint_key: &SecretKey,
+        amount: u64,
+        pubkey: &PublicKey,
+    ) -> anyhow::Result<Proof> {
+        create_locked_proof(keyset, mint_key, amount, &P2PKSecret::new(*pubkey))
+    }
+
+    fn create_locked_proof(
+        keyset: &WalletKeyset,
+        mint_key: &SecretKey,
+        amount: u64,
+        secret: &P2PKSecret,
+    ) -> anyhow::Result<Proof> {
+        let dhke = Dhke::new();
+        let secret = secret.to_string();
+        let r = SecretKey::new(&mut rand::thread_rng());
+        let b_ = dhke.step1_alice(secret.clone(), &r.into())?;
+        let c_ = dhke.step2_bob(b_, mint_key)?;
+        let c = dhke.step3_alice(c_, r.into(), mint_key.public_key(&Secp256k1::new()))?;
+        let (e, s) = dhke.step2_bob_dleq(b_, mint_key, &SecretKey::new(&mut rand::thread_rng()))?;
+        Ok(Proof {
+            dleq: Some(ProofDleq { e, s, r }),
+            ..Proof::new(amount, secret, c, keyset.keyset_id.to_string())
+        })
     }
 
     fn create_test_wallet_keyset() -> anyhow::Result<WalletKeyset> {